@@ -37,7 +37,7 @@ fn run_criterion_perft(
 }
 
 pub fn criterion_make_unmake_move(c: &mut Criterion) {
-    let state =
+    let mut state =
         State::new_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQ - 0 1");
     let moves = get_pseudo_legal_moves(&state);
 
@@ -45,8 +45,9 @@ pub fn criterion_make_unmake_move(c: &mut Criterion) {
     group.throughput(Throughput::Elements(moves.len() as u64));
     group.bench_function("General make unmake", |b| {
         b.iter(|| {
-            for moove in &moves[0..moves.len()] {
-                let _ = state.make_move(std::hint::black_box(*moove));
+            for moove in &moves {
+                let undo = state.make_move(std::hint::black_box(*moove));
+                state.unmake_move(*moove, undo);
             }
         })
     });
@@ -1,14 +1,17 @@
 use mouse::State;
-use mouse::backend::perft::perft;
+use mouse::backend::perft::perft_parallel;
 use perft_fixtures::perft_fixtures::{LONG_PERFT, PerftFixture};
+use std::thread;
 use std::time::Instant;
 
 pub fn manual_perft() {
     let mut total_nodes = 0;
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    println!("Threads: {}", threads);
 
     let now = Instant::now();
     for perft_fixture in LONG_PERFT {
-        let nodes = run_nps_perft(perft_fixture);
+        let nodes = run_nps_perft(perft_fixture, threads);
         total_nodes += nodes;
     }
 
@@ -25,14 +28,15 @@ pub fn manual_perft() {
 // PERFT with nps
 // --------------------------------------------- //
 
-fn run_nps_perft(perft_fixture: PerftFixture) -> u64 {
+fn run_nps_perft(perft_fixture: PerftFixture, threads: usize) -> u64 {
     let fen = perft_fixture.perft_setup.fen;
     let state = State::new_from_fen(fen);
 
     // Start timer to calculate nodes per second.
     let now = Instant::now();
 
-    let nodes = perft(&state, perft_fixture.depth);
+    let nodes = perft_parallel(&state, perft_fixture.depth, threads);
+    assert_eq!(nodes, perft_fixture.expected_nodes);
 
     let elapsed = now.elapsed();
     let nodes_per_second = nodes as f64 / elapsed.as_secs_f64();
@@ -1,7 +1,9 @@
-use std::fs;
+use std::{env, fs};
 
 mod caches_non_sliders;
+mod caches_rays;
 mod caches_sliders;
+mod caches_zobrist;
 mod build_util;
 
 const DIR_PATH: &str = "src/backend/";
@@ -9,10 +11,21 @@ const DIR_PATH: &str = "src/backend/";
 fn main() {
     println!("cargo:rerun-if-changed=build/build.rs");
     println!("cargo:rerun-if-changed=build/caches_non_sliders");
+    println!("cargo:rerun-if-changed=build/caches_rays");
     println!("cargo:rerun-if-changed=build/caches_sliders");
+    println!("cargo:rerun-if-changed=build/caches_zobrist");
     println!("cargo:rerun-if-changed=build/build_util");
     println!("cargo:rerun-if-changed=src/backend/caches.rs");
 
+    // Slider attacks are looked up with BMI2 PEXT on x86_64 and with magic bitboards everywhere else.
+    // The `magic` feature forces magic bitboards, e.g. for AMD Zen 1 and 2 where PEXT is very slow.
+    println!("cargo:rustc-check-cfg=cfg(pext)");
+    let is_x86_64 = env::var("CARGO_CFG_TARGET_ARCH").is_ok_and(|arch| arch == "x86_64");
+    let is_magic_forced = env::var_os("CARGO_FEATURE_MAGIC").is_some();
+    if is_x86_64 && !is_magic_forced {
+        println!("cargo:rustc-cfg=pext");
+    }
+
     let mut king_moves = [0u64; 64];
     for (square, king_moves_for_square) in king_moves.iter_mut().enumerate() {
         *king_moves_for_square = caches_non_sliders::gen_king_moves(square as i8);
@@ -25,13 +38,20 @@ fn main() {
 
     let capture_pawn_moves = caches_non_sliders::gen_pawn_captures();
 
+    let squares_between = caches_rays::gen_squares_between();
+    let squares_line = caches_rays::gen_squares_line();
+
     let pext_data = caches_sliders::gen_cache_sliders();
     let rook_pext_mask = pext_data.rook_pext_mask;
     let rook_pext_index = pext_data.rook_pext_index;
     let bishop_pext_mask = pext_data.bishop_pext_mask;
     let bishop_pext_index = pext_data.bishop_pext_index;
+
+    let magic_data = caches_sliders::gen_cache_magics(&pext_data);
     let pext_table = pext_data.pext_table;
 
+    let zobrist_data = caches_zobrist::gen_zobrist_keys();
+
     let cache_strings = [
         format!(
             "pub const KING_MOVES: [BitBoard; SQUARES_AMOUNT] = unsafe{{std::mem::transmute({})}};",
@@ -46,6 +66,14 @@ fn main() {
             array_to_string(&capture_pawn_moves[0]),
             array_to_string(&capture_pawn_moves[1])
         ),
+        format!(
+            "pub static SQUARES_BETWEEN: [[BitBoard; SQUARES_AMOUNT]; SQUARES_AMOUNT] = unsafe{{std::mem::transmute({})}};",
+            array_2d_to_string(&squares_between)
+        ),
+        format!(
+            "pub static SQUARES_LINE: [[BitBoard; SQUARES_AMOUNT]; SQUARES_AMOUNT] = unsafe{{std::mem::transmute({})}};",
+            array_2d_to_string(&squares_line)
+        ),
         format!(
             "pub const ROOK_PEXT_MASK: [BitBoard; SQUARES_AMOUNT] = unsafe{{std::mem::transmute({})}};",
             array_to_string(&rook_pext_mask)
@@ -63,10 +91,47 @@ fn main() {
             bishop_pext_index
         ),
         format!(
-            "pub static PEXT_TABLE: [BitBoard; {}] = unsafe{{std::mem::transmute({})}};",
+            "#[cfg(pext)]\npub static PEXT_TABLE: [BitBoard; {}] = unsafe{{std::mem::transmute({})}};",
             caches_sliders::PEXT_TABLE_SIZE,
             array_to_string(&pext_table)
         ),
+        format!(
+            "#[cfg(not(pext))]\npub const ROOK_MAGICS: [u64; SQUARES_AMOUNT] = {:?};",
+            magic_data.rook_magics
+        ),
+        format!(
+            "#[cfg(not(pext))]\npub const ROOK_MAGIC_SHIFTS: [u8; SQUARES_AMOUNT] = {:?};",
+            magic_data.rook_magic_shifts
+        ),
+        format!(
+            "#[cfg(not(pext))]\npub const BISHOP_MAGICS: [u64; SQUARES_AMOUNT] = {:?};",
+            magic_data.bishop_magics
+        ),
+        format!(
+            "#[cfg(not(pext))]\npub const BISHOP_MAGIC_SHIFTS: [u8; SQUARES_AMOUNT] = {:?};",
+            magic_data.bishop_magic_shifts
+        ),
+        format!(
+            "#[cfg(not(pext))]\npub static MAGIC_TABLE: [BitBoard; {}] = unsafe{{std::mem::transmute({})}};",
+            caches_sliders::PEXT_TABLE_SIZE,
+            array_to_string(&magic_data.magic_table)
+        ),
+        format!(
+            "pub const ZOBRIST_PIECE_SQUARE_KEYS: [[[u64; SQUARES_AMOUNT]; PIECE_TYPE_COUNT]; SIDES] = {:?};",
+            zobrist_data.piece_square_keys
+        ),
+        format!(
+            "pub const ZOBRIST_SIDE_KEY: u64 = {:?};",
+            zobrist_data.side_key
+        ),
+        format!(
+            "pub const ZOBRIST_CASTLING_KEYS: [u64; 4] = {:?};",
+            zobrist_data.castling_keys
+        ),
+        format!(
+            "pub const ZOBRIST_EN_PASSANT_FILE_KEYS: [u64; 8] = {:?};",
+            zobrist_data.en_passant_file_keys
+        ),
     ];
 
     let mut file_content = String::from(
@@ -76,6 +141,7 @@ fn main() {
 use crate::backend::types::bitboard::BitBoard;
 use crate::backend::constants::SQUARES_AMOUNT;
 use crate::backend::constants::SIDES;
+use crate::backend::constants::PIECE_TYPE_COUNT;
 ",
     );
     for string in cache_strings {
@@ -97,3 +163,14 @@ fn array_to_string<const N: usize>(array: &[u64; N]) -> String {
     string.push(']');
     string
 }
+
+fn array_2d_to_string<const N: usize, const M: usize>(array: &[[u64; M]; N]) -> String {
+    let mut string = String::new();
+    string.push('[');
+    for inner_array in array {
+        string.push_str(&array_to_string(inner_array));
+        string.push(',');
+    }
+    string.push(']');
+    string
+}
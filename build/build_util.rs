@@ -37,3 +37,12 @@ pub fn square_to_file(square: i8) -> i8 {
 pub fn square_from_rank_and_file(rank: i8, file: i8) -> i8 {
     rank * 8 + file
 }
+
+/// A simple xorshift64* pseudo random number generator.
+/// https://www.chessprogramming.org/Pseudorandom_Number_Generator
+pub fn next_random(random_state: &mut u64) -> u64 {
+    *random_state ^= *random_state >> 12;
+    *random_state ^= *random_state << 25;
+    *random_state ^= *random_state >> 27;
+    random_state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+}
@@ -0,0 +1,78 @@
+use crate::build_util::{
+    is_square_valid, square_from_rank_and_file, square_to_bb, square_to_file, square_to_rank,
+};
+
+// The eight directions a slider can move in, given as (file offset, rank offset).
+const DIRECTIONS: [(i8, i8); 8] = [
+    (0, 1),
+    (1, 1),
+    (1, 0),
+    (1, -1),
+    (0, -1),
+    (-1, -1),
+    (-1, 0),
+    (-1, 1),
+];
+
+/// Generates for every pair of squares a bitboard with all squares strictly between them.
+/// If the two squares do not share a rank, file or diagonal the bitboard is empty.
+pub fn gen_squares_between() -> [[u64; 64]; 64] {
+    let mut squares_between = [[0; 64]; 64];
+
+    for from in 0..64 {
+        for (file_offset, rank_offset) in DIRECTIONS {
+            // Walk into the direction and remember every square we passed.
+            let mut passed_squares = 0;
+            let mut rank = square_to_rank(from) + rank_offset;
+            let mut file = square_to_file(from) + file_offset;
+
+            while is_square_valid(rank, file) {
+                let to = square_from_rank_and_file(rank, file);
+                squares_between[from as usize][to as usize] = passed_squares;
+                passed_squares |= square_to_bb(to);
+
+                rank += rank_offset;
+                file += file_offset;
+            }
+        }
+    }
+
+    squares_between
+}
+
+/// Generates for every pair of squares a bitboard with the full line (edge to edge) that goes through both of them.
+/// If the two squares do not share a rank, file or diagonal the bitboard is empty.
+pub fn gen_squares_line() -> [[u64; 64]; 64] {
+    let mut squares_line = [[0; 64]; 64];
+
+    for from in 0..64 {
+        for (file_offset, rank_offset) in DIRECTIONS {
+            // The line consists of the ray in this direction, the ray in the opposite direction and the square itself.
+            let ray = gen_ray(from, file_offset, rank_offset);
+            let line = ray | gen_ray(from, -file_offset, -rank_offset) | square_to_bb(from);
+
+            // Every square on the ray in this direction shares that line with `from`.
+            for to in 0..64 {
+                if ray & square_to_bb(to) != 0 {
+                    squares_line[from as usize][to as usize] = line;
+                }
+            }
+        }
+    }
+
+    squares_line
+}
+
+fn gen_ray(square: i8, file_offset: i8, rank_offset: i8) -> u64 {
+    let mut ray = 0;
+    let mut rank = square_to_rank(square) + rank_offset;
+    let mut file = square_to_file(square) + file_offset;
+
+    while is_square_valid(rank, file) {
+        ray |= square_to_bb(square_from_rank_and_file(rank, file));
+        rank += rank_offset;
+        file += file_offset;
+    }
+
+    ray
+}
@@ -1,4 +1,4 @@
-use crate::build_util::{is_square_valid, square_to_file, square_to_rank};
+use crate::build_util::{is_square_valid, next_random, square_to_file, square_to_rank};
 
 pub const PEXT_TABLE_SIZE: usize = 107_648;
 
@@ -224,3 +224,122 @@ fn calculate_max_slide_range(square: i8, direction: &SlideDirection, blocker_bb:
     }
     result
 }
+
+// ----------------------------------------
+// MAGIC GEN LOGIC
+// ----------------------------------------
+// https://www.chessprogramming.org/Magic_Bitboards
+// Instead of PEXT, the blockers are multiplied with a "magic" number and shifted down.
+// This works on every CPU and is a lot faster than PEXT on CPUs that emulate it in microcode.
+// The magic backend reuses the masks and the table offsets of the PEXT backend,
+// since a square needs exactly as many entries with both approaches.
+
+pub struct MagicData {
+    pub rook_magics: [u64; 64],
+    pub rook_magic_shifts: [u8; 64],
+    pub bishop_magics: [u64; 64],
+    pub bishop_magic_shifts: [u8; 64],
+    pub magic_table: [u64; PEXT_TABLE_SIZE],
+}
+
+pub fn gen_cache_magics(pext_data: &PextData) -> MagicData {
+    let mut rook_magics = [0; 64];
+    let mut rook_magic_shifts = [0; 64];
+    let mut bishop_magics = [0; 64];
+    let mut bishop_magic_shifts = [0; 64];
+
+    let mut magic_table = [0; PEXT_TABLE_SIZE];
+
+    gen_magics_for_piece(
+        &Piece::Rook,
+        &pext_data.rook_pext_mask,
+        &pext_data.rook_pext_index,
+        &mut rook_magics,
+        &mut rook_magic_shifts,
+        &mut magic_table,
+    );
+
+    gen_magics_for_piece(
+        &Piece::Bishop,
+        &pext_data.bishop_pext_mask,
+        &pext_data.bishop_pext_index,
+        &mut bishop_magics,
+        &mut bishop_magic_shifts,
+        &mut magic_table,
+    );
+
+    MagicData {
+        rook_magics,
+        rook_magic_shifts,
+        bishop_magics,
+        bishop_magic_shifts,
+        magic_table,
+    }
+}
+
+fn gen_magics_for_piece(
+    piece: &Piece,
+    piece_mask: &[u64; 64],
+    piece_index: &[usize; 64],
+    piece_magics: &mut [u64; 64],
+    piece_magic_shifts: &mut [u8; 64],
+    magic_table: &mut [u64; PEXT_TABLE_SIZE],
+) {
+    // Any seed works, this one just finds all magics quickly.
+    let mut random_state = 0x1F2E_3D4C_5B6A_7988u64;
+
+    for square in 0..64 {
+        let relevant_squares = piece_mask[square as usize];
+        let amount_of_blocker_squares = relevant_squares.count_ones();
+        let shift = 64 - amount_of_blocker_squares;
+        let amount_of_possible_blocker_configurations = 1usize << amount_of_blocker_squares;
+
+        // Precompute all blocker configurations and the resulting moves.
+        let mut blockers = Vec::with_capacity(amount_of_possible_blocker_configurations);
+        let mut moves = Vec::with_capacity(amount_of_possible_blocker_configurations);
+        for blocker_config_index in 0..amount_of_possible_blocker_configurations {
+            let blocker_bb = pdep64(blocker_config_index as u64, relevant_squares);
+            blockers.push(blocker_bb);
+            moves.push(calculate_slider_move_bitboard(piece, square, blocker_bb));
+        }
+
+        // Try random numbers until one maps every blocker configuration to an index
+        // that is either unused or already contains the same moves.
+        let table_start = piece_index[square as usize];
+        let mut used = vec![false; amount_of_possible_blocker_configurations];
+        loop {
+            let magic = sparse_random(&mut random_state);
+            // Magics that don't spread the mask into the top bits are never going to work.
+            if (relevant_squares.wrapping_mul(magic) >> 56).count_ones() < 6 {
+                continue;
+            }
+
+            used.fill(false);
+            let mut is_valid = true;
+            for (blocker_bb, moves_bb) in blockers.iter().zip(moves.iter()) {
+                let index = (blocker_bb.wrapping_mul(magic) >> shift) as usize;
+                let table_entry = &mut magic_table[table_start + index];
+
+                if !used[index] {
+                    used[index] = true;
+                    *table_entry = *moves_bb;
+                } else if *table_entry != *moves_bb {
+                    is_valid = false;
+                    break;
+                }
+            }
+
+            if is_valid {
+                piece_magics[square as usize] = magic;
+                piece_magic_shifts[square as usize] = shift as u8;
+                break;
+            }
+        }
+    }
+}
+
+/// Magics with few set bits are much more likely to work,
+/// so we and together three random numbers.
+fn sparse_random(random_state: &mut u64) -> u64 {
+    next_random(random_state) & next_random(random_state) & next_random(random_state)
+}
@@ -0,0 +1,48 @@
+use crate::build_util::next_random;
+
+// ----------------------------------------
+// ZOBRIST KEY GEN LOGIC
+// ----------------------------------------
+// https://www.chessprogramming.org/Zobrist_Hashing
+
+// Any non-zero seed works, it just has to stay the same between builds.
+const SEED: u64 = 0x9E37_79B9_7F4A_7C15;
+
+pub struct ZobristData {
+    pub piece_square_keys: [[[u64; 64]; 6]; 2],
+    pub side_key: u64,
+    pub castling_keys: [u64; 4],
+    pub en_passant_file_keys: [u64; 8],
+}
+
+pub fn gen_zobrist_keys() -> ZobristData {
+    let mut random_state = SEED;
+
+    let mut piece_square_keys = [[[0; 64]; 6]; 2];
+    for side_keys in piece_square_keys.iter_mut() {
+        for piece_keys in side_keys.iter_mut() {
+            for key in piece_keys.iter_mut() {
+                *key = next_random(&mut random_state);
+            }
+        }
+    }
+
+    let side_key = next_random(&mut random_state);
+
+    let mut castling_keys = [0; 4];
+    for key in castling_keys.iter_mut() {
+        *key = next_random(&mut random_state);
+    }
+
+    let mut en_passant_file_keys = [0; 8];
+    for key in en_passant_file_keys.iter_mut() {
+        *key = next_random(&mut random_state);
+    }
+
+    ZobristData {
+        piece_square_keys,
+        side_key,
+        castling_keys,
+        en_passant_file_keys,
+    }
+}
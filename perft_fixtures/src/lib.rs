@@ -1 +1,2 @@
 pub mod perft_fixtures;
+pub mod perft_suite;
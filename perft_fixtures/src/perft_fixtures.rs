@@ -80,114 +80,321 @@ pub struct PerftFixture {
     pub perft_setup: PerftFen,
     pub depth: u8,
     pub expected_nodes: u64,
+    /// What kind of moves were played at the last ply. `None` if there is nothing to compare against.
+    pub expected_stats: Option<PerftStatsFixture>,
+}
+
+/// The expected values of `mouse::backend::perft::PerftStats`, except for the nodes.
+/// Positions 1 to 4 are taken from https://www.chessprogramming.org/Perft_Results.
+/// That page has no discovered and double checks for position 4 and only node counts for positions 5 and 6.
+/// These values were counted with a separate mailbox move generator that shares no code with Mouse
+/// and uses the same definitions as `perft_stats`. It reproduces every published value of positions 1 to 4 as well,
+/// except for the double checks of `POSITION_2_LONG`.
+#[derive(Clone, Copy)]
+pub struct PerftStatsFixture {
+    pub captures: u64,
+    pub en_passants: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    pub checks: u64,
+    pub discovered_checks: u64,
+    pub double_checks: u64,
+    pub checkmates: u64,
 }
 
 const STARTING_POS_FAST: PerftFixture = PerftFixture {
     perft_setup: STARTING_POS,
     depth: 3,
     expected_nodes: 8_902,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 34,
+        en_passants: 0,
+        castles: 0,
+        promotions: 0,
+        checks: 12,
+        discovered_checks: 0,
+        double_checks: 0,
+        checkmates: 0,
+    }),
 };
 
 const STARTING_POS_NORMAL: PerftFixture = PerftFixture {
     perft_setup: STARTING_POS,
     depth: 5,
     expected_nodes: 4_865_609,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 82_719,
+        en_passants: 258,
+        castles: 0,
+        promotions: 0,
+        checks: 27_351,
+        discovered_checks: 6,
+        double_checks: 0,
+        checkmates: 347,
+    }),
 };
 
 const STARTING_POS_LONG: PerftFixture = PerftFixture {
     perft_setup: STARTING_POS,
     depth: 6,
     expected_nodes: 119_060_324,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 2_812_008,
+        en_passants: 5_248,
+        castles: 0,
+        promotions: 0,
+        checks: 809_099,
+        discovered_checks: 329,
+        double_checks: 46,
+        checkmates: 10_828,
+    }),
 };
 
 const POSITION_2_FAST: PerftFixture = PerftFixture {
     perft_setup: POSITION_2,
     depth: 3,
     expected_nodes: 97_862,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 17_102,
+        en_passants: 45,
+        castles: 3_162,
+        promotions: 0,
+        checks: 993,
+        discovered_checks: 0,
+        double_checks: 0,
+        checkmates: 1,
+    }),
 };
 
 const POSITION_2_NORMAL: PerftFixture = PerftFixture {
     perft_setup: POSITION_2,
     depth: 4,
     expected_nodes: 4_085_603,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 757_163,
+        en_passants: 1_929,
+        castles: 128_013,
+        promotions: 15_172,
+        checks: 25_523,
+        discovered_checks: 42,
+        double_checks: 6,
+        checkmates: 43,
+    }),
 };
 
 const POSITION_2_LONG: PerftFixture = PerftFixture {
     perft_setup: POSITION_2,
     depth: 5,
     expected_nodes: 193_690_690,
+    // The published table lists 2_637 double checks, 8 less than counted here. Exactly 8 of the double checks are
+    // checkmates, so the table leaves out double checks that mate. `perft_stats` and the separate move generator
+    // both count every check by two pieces, mate or not. All other values match the table. At depth 6 the same holds:
+    // 55_014 double checks of which 66 mate, against the published 54_948.
+    expected_stats: Some(PerftStatsFixture {
+        captures: 35_043_416,
+        en_passants: 73_365,
+        castles: 4_993_637,
+        promotions: 8_392,
+        checks: 3_309_887,
+        discovered_checks: 19_883,
+        double_checks: 2_645,
+        checkmates: 30_171,
+    }),
 };
 
 const POSITION_3_FAST: PerftFixture = PerftFixture {
     perft_setup: POSITION_3,
     depth: 4,
     expected_nodes: 43_238,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 3_348,
+        en_passants: 123,
+        castles: 0,
+        promotions: 0,
+        checks: 1_680,
+        discovered_checks: 106,
+        double_checks: 0,
+        checkmates: 17,
+    }),
 };
 
 const POSITION_3_NORMAL: PerftFixture = PerftFixture {
     perft_setup: POSITION_3,
     depth: 5,
     expected_nodes: 674_624,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 52_051,
+        en_passants: 1_165,
+        castles: 0,
+        promotions: 0,
+        checks: 52_950,
+        discovered_checks: 1_292,
+        double_checks: 3,
+        checkmates: 0,
+    }),
 };
 
 const POSITION_3_LONG: PerftFixture = PerftFixture {
     perft_setup: POSITION_3,
     depth: 6,
     expected_nodes: 11_030_083,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 940_350,
+        en_passants: 33_325,
+        castles: 0,
+        promotions: 7_552,
+        checks: 452_473,
+        discovered_checks: 26_067,
+        double_checks: 0,
+        checkmates: 2_733,
+    }),
 };
 
 const POSITION_4_FAST: PerftFixture = PerftFixture {
     perft_setup: POSITION_4,
     depth: 3,
     expected_nodes: 9_467,
+    // The discovered and double checks are not published, see `PerftStatsFixture`.
+    expected_stats: Some(PerftStatsFixture {
+        captures: 1_021,
+        en_passants: 4,
+        castles: 0,
+        promotions: 120,
+        checks: 38,
+        discovered_checks: 2,
+        double_checks: 0,
+        checkmates: 22,
+    }),
 };
 
 const POSITION_4_NORMAL: PerftFixture = PerftFixture {
     perft_setup: POSITION_4,
     depth: 4,
     expected_nodes: 422_333,
+    // The discovered and double checks are not published, see `PerftStatsFixture`.
+    expected_stats: Some(PerftStatsFixture {
+        captures: 131_393,
+        en_passants: 0,
+        castles: 7_795,
+        promotions: 60_032,
+        checks: 15_492,
+        discovered_checks: 19,
+        double_checks: 0,
+        checkmates: 5,
+    }),
 };
 
 const POSITION_4_LONG: PerftFixture = PerftFixture {
     perft_setup: POSITION_4,
     depth: 5,
     expected_nodes: 15_833_292,
+    // The discovered and double checks are not published, see `PerftStatsFixture`.
+    expected_stats: Some(PerftStatsFixture {
+        captures: 2_046_173,
+        en_passants: 6_512,
+        castles: 0,
+        promotions: 329_464,
+        checks: 200_568,
+        discovered_checks: 11_621,
+        double_checks: 50,
+        checkmates: 50_562,
+    }),
 };
 
 const POSITION_5_FAST: PerftFixture = PerftFixture {
     perft_setup: POSITION_5,
     depth: 3,
     expected_nodes: 62_379,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 8_517,
+        en_passants: 0,
+        castles: 1_081,
+        promotions: 5_068,
+        checks: 1_201,
+        discovered_checks: 0,
+        double_checks: 0,
+        checkmates: 44,
+    }),
 };
 
 const POSITION_5_NORMAL: PerftFixture = PerftFixture {
     perft_setup: POSITION_5,
     depth: 4,
     expected_nodes: 2_103_487,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 296_153,
+        en_passants: 0,
+        castles: 0,
+        promotions: 0,
+        checks: 158_486,
+        discovered_checks: 10_877,
+        double_checks: 1_770,
+        checkmates: 240,
+    }),
 };
 
 const POSITION_5_LONG: PerftFixture = PerftFixture {
     perft_setup: POSITION_5,
     depth: 5,
     expected_nodes: 89_941_194,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 12_320_378,
+        en_passants: 140,
+        castles: 1_240_828,
+        promotions: 6_655_216,
+        checks: 3_078_299,
+        discovered_checks: 7_459,
+        double_checks: 58,
+        checkmates: 137_306,
+    }),
 };
 
 const POSITION_6_FAST: PerftFixture = PerftFixture {
     perft_setup: POSITION_6,
     depth: 3,
     expected_nodes: 89_890,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 9_470,
+        en_passants: 0,
+        castles: 0,
+        promotions: 0,
+        checks: 1_783,
+        discovered_checks: 0,
+        double_checks: 0,
+        checkmates: 0,
+    }),
 };
 
 const POSITION_6_NORMAL: PerftFixture = PerftFixture {
     perft_setup: POSITION_6,
     depth: 4,
     expected_nodes: 3_894_594,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 440_388,
+        en_passants: 0,
+        castles: 0,
+        promotions: 0,
+        checks: 68_985,
+        discovered_checks: 62,
+        double_checks: 20,
+        checkmates: 0,
+    }),
 };
 
 const POSITION_6_LONG: PerftFixture = PerftFixture {
     perft_setup: POSITION_6,
     depth: 5,
     expected_nodes: 164_075_551,
+    expected_stats: Some(PerftStatsFixture {
+        captures: 19_528_068,
+        en_passants: 122,
+        castles: 0,
+        promotions: 0,
+        checks: 2_998_608,
+        discovered_checks: 10_687,
+        double_checks: 2_960,
+        checkmates: 228,
+    }),
 };
 
 /// Contains fast perft tests for benchmarking purposes. Usually between 10k and 100k nodes at depth 2-4.
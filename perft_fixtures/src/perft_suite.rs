@@ -0,0 +1,324 @@
+// Loads perft suites in the EPD format of perftsuite.epd and checks them against a perft implementation.
+// Every line is a fen followed by the expected node counts:
+// `rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 400`
+// Empty lines and lines starting with `#` are ignored.
+
+use std::fmt::{Display, Formatter};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// The directory with the suites checked into the repo.
+pub const PERFT_SUITE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/suites");
+
+// -----------------------------------------
+// Parsing
+// -----------------------------------------
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PerftSuiteEntry {
+    /// Where the entry came from, for error messages.
+    pub file_name: String,
+    pub line_number: usize,
+    pub fen: String,
+    /// (depth, nodes), in the order they appear in the line.
+    pub expected_nodes: Vec<(u8, u64)>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PerftSuiteErrorKind {
+    /// The line has node counts, but no fen in front of them.
+    MissingFen,
+    /// A `;` is followed by something that is not `D<depth> <nodes>`.
+    InvalidNodeCount(String),
+    /// The same depth appears twice in one line.
+    DuplicateDepth(u8),
+    /// The file could not be read.
+    Io(String),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PerftSuiteError {
+    pub file_name: String,
+    /// 0 if the error is not about a single line.
+    pub line_number: usize,
+    pub kind: PerftSuiteErrorKind,
+}
+
+impl Display for PerftSuiteError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}: ", self.file_name, self.line_number)?;
+        match &self.kind {
+            PerftSuiteErrorKind::MissingFen => write!(f, "the line has no fen"),
+            PerftSuiteErrorKind::InvalidNodeCount(operation) => {
+                write!(f, "expected D<depth> <nodes>, but found {:?}", operation)
+            }
+            PerftSuiteErrorKind::DuplicateDepth(depth) => {
+                write!(f, "depth {} appears twice", depth)
+            }
+            PerftSuiteErrorKind::Io(error) => write!(f, "could not read the file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for PerftSuiteError {}
+
+/// Parses the content of a suite. `file_name` is only used for error messages.
+pub fn parse_perft_suite(
+    file_name: &str,
+    content: &str,
+) -> Result<Vec<PerftSuiteEntry>, PerftSuiteError> {
+    let mut entries = Vec::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let error = |kind| PerftSuiteError {
+            file_name: file_name.to_string(),
+            line_number: index + 1,
+            kind,
+        };
+
+        let mut fields = line.split(';');
+        let fen = fields.next().unwrap_or_default().trim();
+        if fen.is_empty() {
+            return Err(error(PerftSuiteErrorKind::MissingFen));
+        }
+
+        let mut expected_nodes: Vec<(u8, u64)> = Vec::new();
+        for operation in fields {
+            let (depth, nodes) = parse_node_count(operation).ok_or_else(|| {
+                error(PerftSuiteErrorKind::InvalidNodeCount(
+                    operation.trim().to_string(),
+                ))
+            })?;
+            if expected_nodes
+                .iter()
+                .any(|&(other_depth, _)| other_depth == depth)
+            {
+                return Err(error(PerftSuiteErrorKind::DuplicateDepth(depth)));
+            }
+            expected_nodes.push((depth, nodes));
+        }
+
+        entries.push(PerftSuiteEntry {
+            file_name: file_name.to_string(),
+            line_number: index + 1,
+            fen: fen.to_string(),
+            expected_nodes,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Parses `D<depth> <nodes>`.
+fn parse_node_count(operation: &str) -> Option<(u8, u64)> {
+    let mut tokens = operation.split_whitespace();
+    let depth = tokens.next()?.strip_prefix('D')?.parse::<u8>().ok()?;
+    let nodes = tokens.next()?.parse::<u64>().ok()?;
+    tokens.next().is_none().then_some((depth, nodes))
+}
+
+pub fn load_perft_suite(path: &Path) -> Result<Vec<PerftSuiteEntry>, PerftSuiteError> {
+    let file_name = path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().to_string(),
+    );
+    let content = std::fs::read_to_string(path).map_err(|error| PerftSuiteError {
+        file_name: file_name.clone(),
+        line_number: 0,
+        kind: PerftSuiteErrorKind::Io(error.to_string()),
+    })?;
+    parse_perft_suite(&file_name, &content)
+}
+
+/// Loads every `.epd` file in `directory`, sorted by name, so new suites are picked up without touching any code.
+pub fn load_perft_suites(directory: &Path) -> Result<Vec<PerftSuiteEntry>, PerftSuiteError> {
+    let io_error = |error: std::io::Error| PerftSuiteError {
+        file_name: directory.display().to_string(),
+        line_number: 0,
+        kind: PerftSuiteErrorKind::Io(error.to_string()),
+    };
+
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(directory)
+        .map_err(io_error)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()
+        .map_err(io_error)?;
+    paths.retain(|path| path.extension().is_some_and(|extension| extension == "epd"));
+    paths.sort();
+
+    let mut entries = Vec::new();
+    for path in paths {
+        entries.extend(load_perft_suite(&path)?);
+    }
+    Ok(entries)
+}
+
+// -----------------------------------------
+// Running
+// -----------------------------------------
+
+/// How much of a suite to run.
+#[derive(Clone, Debug)]
+pub struct PerftSuiteBudget {
+    /// Deeper node counts are skipped.
+    pub max_depth: u8,
+    /// Once this much time has passed, the remaining node counts are skipped.
+    pub time_limit: Option<Duration>,
+}
+
+impl PerftSuiteBudget {
+    /// Lets `MOUSE_PERFT_SUITE_DEPTH` and `MOUSE_PERFT_SUITE_SECONDS` override the given budget,
+    /// e.g. to run the full suite once before a release.
+    pub fn from_env(default: PerftSuiteBudget) -> PerftSuiteBudget {
+        let read = |name: &str| {
+            std::env::var(name).ok().map(|value| {
+                value
+                    .parse::<u64>()
+                    .unwrap_or_else(|_| panic!("{} is not a number", name))
+            })
+        };
+
+        PerftSuiteBudget {
+            max_depth: read("MOUSE_PERFT_SUITE_DEPTH")
+                .map_or(default.max_depth, |depth| depth.min(u8::MAX as u64) as u8),
+            time_limit: read("MOUSE_PERFT_SUITE_SECONDS")
+                .map(Duration::from_secs)
+                .or(default.time_limit),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PerftSuiteFailure {
+    /// The perft result differs from the expected node count.
+    Mismatch {
+        entry: PerftSuiteEntry,
+        depth: u8,
+        expected: u64,
+        actual: u64,
+    },
+    /// The perft implementation rejected the position, e.g. because the fen is invalid.
+    InvalidPosition {
+        entry: PerftSuiteEntry,
+        error: String,
+    },
+}
+
+impl Display for PerftSuiteFailure {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PerftSuiteFailure::Mismatch {
+                entry,
+                depth,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}:{}: {} at depth {}: expected {}, got {}",
+                entry.file_name, entry.line_number, entry.fen, depth, expected, actual
+            ),
+            PerftSuiteFailure::InvalidPosition { entry, error } => write!(
+                f,
+                "{}:{}: {}: {}",
+                entry.file_name, entry.line_number, entry.fen, error
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct PerftSuiteReport {
+    /// Node counts that were compared.
+    pub checked: usize,
+    /// Node counts that were skipped because of the budget.
+    pub skipped: usize,
+    pub failures: Vec<PerftSuiteFailure>,
+}
+
+impl PerftSuiteReport {
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl Display for PerftSuiteReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{} node counts checked, {} skipped, {} failed",
+            self.checked,
+            self.skipped,
+            self.failures.len()
+        )?;
+        for failure in &self.failures {
+            writeln!(f, "{}", failure)?;
+        }
+        Ok(())
+    }
+}
+
+/// Runs `perft(fen, depth)` for every node count of every entry within the budget and collects all failures,
+/// instead of stopping at the first one.
+///
+/// All entries are checked at depth 1 first, then at depth 2 and so on.
+/// If the time runs out, every position has at least been checked at the shallow depths.
+pub fn run_perft_suite(
+    entries: &[PerftSuiteEntry],
+    budget: &PerftSuiteBudget,
+    mut perft: impl FnMut(&str, u8) -> Result<u64, String>,
+) -> PerftSuiteReport {
+    let start = Instant::now();
+    let mut report = PerftSuiteReport::default();
+    let mut is_invalid = vec![false; entries.len()];
+
+    let deepest = entries
+        .iter()
+        .flat_map(|entry| entry.expected_nodes.iter().map(|&(depth, _)| depth))
+        .max()
+        .unwrap_or(0);
+
+    for depth in 0..=deepest {
+        for (index, entry) in entries.iter().enumerate() {
+            let Some(&(_, expected)) = entry
+                .expected_nodes
+                .iter()
+                .find(|&&(entry_depth, _)| entry_depth == depth)
+            else {
+                continue;
+            };
+
+            let is_out_of_time = budget
+                .time_limit
+                .is_some_and(|time_limit| start.elapsed() >= time_limit);
+            if depth > budget.max_depth || is_out_of_time || is_invalid[index] {
+                report.skipped += 1;
+                continue;
+            }
+
+            report.checked += 1;
+            match perft(&entry.fen, depth) {
+                Ok(actual) if actual == expected => {}
+                Ok(actual) => report.failures.push(PerftSuiteFailure::Mismatch {
+                    entry: entry.clone(),
+                    depth,
+                    expected,
+                    actual,
+                }),
+                Err(error) => {
+                    // The position will not get any better at a higher depth.
+                    is_invalid[index] = true;
+                    report.failures.push(PerftSuiteFailure::InvalidPosition {
+                        entry: entry.clone(),
+                        error,
+                    });
+                }
+            }
+        }
+    }
+
+    report
+}
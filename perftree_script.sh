@@ -1,3 +1,3 @@
 #!/bin/bash
 
-./target/debug/Mouse "$@"
\ No newline at end of file
+./target/debug/Mouse perftree "$@"
\ No newline at end of file
@@ -0,0 +1,52 @@
+use crate::backend::eval::pesto_tables::{
+    ENDGAME_PIECE_VALUES, ENDGAME_TABLES, MAX_PHASE, MIDDLEGAME_PIECE_VALUES, MIDDLEGAME_TABLES,
+    PHASE_WEIGHTS,
+};
+use crate::backend::game_state::bb_manager::BBManager;
+use crate::backend::game_state::state::State;
+use crate::backend::types::piece::{ALL_PIECES, Side};
+use crate::backend::types::square::Square;
+
+/// Evaluates the position in centipawns from the point of view of the side to move.
+/// Middlegame and endgame scores are computed separately and then blended by how much material is left.
+pub fn evaluate(state: &State) -> i32 {
+    let score = evaluate_bb_manager(&state.bb_manager);
+    match state.active_color {
+        Side::White => score,
+        Side::Black => -score,
+    }
+}
+
+/// Evaluates the pieces from white's point of view.
+fn evaluate_bb_manager(bb_manager: &BBManager) -> i32 {
+    let mut middlegame_score = 0;
+    let mut endgame_score = 0;
+    let mut phase = 0;
+
+    for piece in ALL_PIECES {
+        let index = piece as usize;
+        for (side, sign) in [(Side::White, 1), (Side::Black, -1)] {
+            for square in bb_manager.get_colored_piece_bb(piece, side) {
+                let table_index = get_table_index(square, side);
+                middlegame_score +=
+                    sign * (MIDDLEGAME_PIECE_VALUES[index] + MIDDLEGAME_TABLES[index][table_index]);
+                endgame_score +=
+                    sign * (ENDGAME_PIECE_VALUES[index] + ENDGAME_TABLES[index][table_index]);
+                phase += PHASE_WEIGHTS[index];
+            }
+        }
+    }
+
+    // Promotions can push the phase above the maximum.
+    let phase = phase.min(MAX_PHASE);
+    (middlegame_score * phase + endgame_score * (MAX_PHASE - phase)) / MAX_PHASE
+}
+
+/// The tables start with a8, while squares start with a1. Flipping the rank fixes that for white.
+/// Black sees the board upside down, so its squares can be used as they are.
+fn get_table_index(square: Square, side: Side) -> usize {
+    match side {
+        Side::White => (square ^ 56) as usize,
+        Side::Black => square as usize,
+    }
+}
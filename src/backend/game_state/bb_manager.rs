@@ -10,7 +10,7 @@ use crate::backend::types::square::Square;
 ///
 /// - `bitboard_index_to_piece`: An array that maps each index in the `bitboards` array
 ///   back to its corresponding `Piece`.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct BBManager {
     white_bb: BitBoard,
     black_bb: BitBoard,
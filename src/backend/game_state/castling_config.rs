@@ -0,0 +1,176 @@
+use crate::backend::caches::SQUARES_BETWEEN;
+use crate::backend::constants::{A1, A8, E1, E8, H1, H8, SIDES};
+use crate::backend::types::bitboard::BitBoard;
+use crate::backend::types::moove::{CastleType, Moove};
+use crate::backend::types::piece::Side;
+use crate::backend::types::square::{Square, get_file};
+
+// Chess960 (Fischer Random): https://www.chessprogramming.org/Chess960
+// The king and the rooks may start on any file, as long as the king is between the rooks.
+// No matter where they start, castling puts them on the same squares as in standard chess:
+// The king on the g or c file and the rook on the f or d file.
+
+const CASTLE_TYPES: usize = 2;
+
+/// Where the king and the castling rooks of each side started, and everything the move gen derives from that.
+/// It is computed once when the position is set up, so standard chess and Chess960 generate castles equally fast.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CastlingConfig {
+    king_squares: [Square; SIDES],
+    /// Indexed by side and castle type.
+    rook_squares: [[Square; CASTLE_TYPES]; SIDES],
+    /// The squares that have to be empty, apart from the castling king and rook themselves.
+    empty_squares: [[BitBoard; CASTLE_TYPES]; SIDES],
+    /// The squares the king stands on, moves through and lands on. None of them may be attacked.
+    check_squares: [[BitBoard; CASTLE_TYPES]; SIDES],
+    moves: [[Moove; CASTLE_TYPES]; SIDES],
+}
+
+/// The squares the king and the rook move from and to when castling.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct CastleSquares {
+    pub king_from: Square,
+    pub king_to: Square,
+    pub rook_from: Square,
+    pub rook_to: Square,
+}
+
+impl CastlingConfig {
+    /// Creates the config of standard chess.
+    pub fn new() -> CastlingConfig {
+        let mut castling_config = CastlingConfig {
+            king_squares: [E1, E8],
+            rook_squares: [[A1, H1], [A8, H8]],
+            empty_squares: [[BitBoard::new(); CASTLE_TYPES]; SIDES],
+            check_squares: [[BitBoard::new(); CASTLE_TYPES]; SIDES],
+            moves: [[Moove::new(E1, E1); CASTLE_TYPES]; SIDES],
+        };
+        for side in Side::get_all_colors() {
+            for castle_type in CastleType::get_all_types() {
+                let rook_square = castling_config.get_rook_square(side, castle_type);
+                let king_square = castling_config.get_king_square(side);
+                castling_config.set_castle(side, castle_type, king_square, rook_square);
+            }
+        }
+        castling_config
+    }
+
+    /// Sets where the king and the rook of this castle start. The other castle of the side should use the same king square.
+    pub fn set_castle(
+        &mut self,
+        side: Side,
+        castle_type: CastleType,
+        king_square: Square,
+        rook_square: Square,
+    ) {
+        let castle_squares = get_castle_squares(king_square, rook_square, castle_type);
+        let castle_index = castle_type as usize;
+
+        self.king_squares[side as usize] = king_square;
+        self.rook_squares[side as usize][castle_index] = rook_square;
+        let king_path_bb = get_squares_from_to(castle_squares.king_from, castle_squares.king_to);
+        let rook_path_bb = get_squares_from_to(castle_squares.rook_from, castle_squares.rook_to);
+        let castling_pieces_bb =
+            BitBoard::new_from_square(king_square) | BitBoard::new_from_square(rook_square);
+
+        self.empty_squares[side as usize][castle_index] =
+            (king_path_bb | rook_path_bb) & !castling_pieces_bb;
+        self.check_squares[side as usize][castle_index] = king_path_bb;
+        self.moves[side as usize][castle_index] = get_castle_move(castle_squares);
+    }
+
+    pub fn get_king_square(&self, side: Side) -> Square {
+        self.king_squares[side as usize]
+    }
+
+    pub fn get_rook_square(&self, side: Side, castle_type: CastleType) -> Square {
+        self.rook_squares[side as usize][castle_type as usize]
+    }
+
+    /// The square the king lands on, which is on the c or g file.
+    pub fn get_king_destination(&self, side: Side, castle_type: CastleType) -> Square {
+        let king_square = self.get_king_square(side);
+        let rook_square = self.get_rook_square(side, castle_type);
+        get_castle_squares(king_square, rook_square, castle_type).king_to
+    }
+
+    pub fn get_empty_squares(&self, side: Side, castle_type: CastleType) -> BitBoard {
+        self.empty_squares[side as usize][castle_type as usize]
+    }
+
+    pub fn get_check_squares(&self, side: Side, castle_type: CastleType) -> BitBoard {
+        self.check_squares[side as usize][castle_type as usize]
+    }
+
+    pub fn get_castle_move(&self, side: Side, castle_type: CastleType) -> Moove {
+        self.moves[side as usize][castle_type as usize]
+    }
+
+    /// Returns true if a king or a castling rook starts somewhere else than in standard chess.
+    /// Uci engines have to be told about this with `UCI_Chess960`, otherwise they can't castle here.
+    pub fn is_chess960(&self) -> bool {
+        *self != CastlingConfig::new()
+    }
+}
+
+/// Returns the squares the king and the rook move from and to if this castle move is played.
+/// The move alone is enough, no matter how it is encoded.
+pub fn get_castle_squares_of_move(moove: Moove) -> CastleSquares {
+    let king_square = moove.get_from();
+    let castle_type = moove.get_castle_type();
+    let rook_square = if moove.is_king_takes_rook() {
+        moove.get_to()
+    } else {
+        // Standard castles always use the rook in the corner.
+        match castle_type {
+            CastleType::Long => get_first_square_of_rank(king_square),
+            CastleType::Short => get_first_square_of_rank(king_square) + 7,
+        }
+    };
+    get_castle_squares(king_square, rook_square, castle_type)
+}
+
+fn get_castle_squares(
+    king_square: Square,
+    rook_square: Square,
+    castle_type: CastleType,
+) -> CastleSquares {
+    let (king_file, rook_file) = match castle_type {
+        CastleType::Long => (2, 3),
+        CastleType::Short => (6, 5),
+    };
+    let first_square_of_rank = get_first_square_of_rank(king_square);
+    CastleSquares {
+        king_from: king_square,
+        king_to: first_square_of_rank + king_file,
+        rook_from: rook_square,
+        rook_to: first_square_of_rank + rook_file,
+    }
+}
+
+/// Keeps the usual encoding for castles that could happen in standard chess, so e.g. e1g1 stays e1g1.
+fn get_castle_move(castle_squares: CastleSquares) -> Moove {
+    let is_rook_in_corner = matches!(get_file(castle_squares.rook_from), 0 | 7);
+    if get_file(castle_squares.king_from) == 4 && is_rook_in_corner {
+        Moove::new(castle_squares.king_from, castle_squares.king_to)
+    } else {
+        Moove::new_king_takes_rook(castle_squares.king_from, castle_squares.rook_from)
+    }
+}
+
+fn get_first_square_of_rank(square: Square) -> Square {
+    square - get_file(square) as Square
+}
+
+/// Both squares and all squares between them.
+fn get_squares_from_to(from: Square, to: Square) -> BitBoard {
+    SQUARES_BETWEEN[from as usize][to as usize]
+        | BitBoard::new_from_square(from)
+        | BitBoard::new_from_square(to)
+}
+
+impl Default for CastlingConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
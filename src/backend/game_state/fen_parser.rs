@@ -1,14 +1,163 @@
-use crate::backend::types::moove::Moove;
+use crate::backend::types::moove::{CastleType, Moove};
 use crate::backend::game_state::bb_manager::BBManager;
+use crate::backend::game_state::castling_config::CastlingConfig;
 use crate::backend::game_state::irreversible_data::IrreversibleData;
+use crate::backend::game_state::state::State;
+use crate::backend::movegen::check_decider::is_in_check;
 use crate::backend::types::piece::Piece::{Bishop, King, Knight, Pawn, Queen, Rook};
 use crate::backend::types::piece::Side::{Black, White};
 use crate::backend::types::piece::{Piece, Side};
 use crate::backend::types::square::Square;
-use crate::backend::types::square::square_from_rank_and_file;
+use crate::backend::types::square::{get_file, get_rank, square_from_rank_and_file, square_to_string};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// The fen string of the starting position.
+pub const STARTING_POSITION_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+/// The six space separated fields of a fen string.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FenField {
+    PiecePlacement,
+    ActiveColor,
+    CastlingRights,
+    EnPassantSquare,
+    HalfMoveClock,
+    FullMoveNumber,
+}
+
+impl Display for FenField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FenField::PiecePlacement => "piece placement",
+            FenField::ActiveColor => "active color",
+            FenField::CastlingRights => "castling rights",
+            FenField::EnPassantSquare => "en passant square",
+            FenField::HalfMoveClock => "halfmove clock",
+            FenField::FullMoveNumber => "fullmove number",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Describes why a fen string could not be parsed.
+/// All positions are character indices into the complete fen string, starting at 0.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FenError {
+    /// One of the four mandatory fields is missing.
+    MissingField(FenField),
+    /// There is something after the fullmove number.
+    TooManyFields { position: usize },
+    /// A field contains a character that is not allowed there.
+    InvalidCharacter {
+        field: FenField,
+        character: char,
+        position: usize,
+    },
+    /// The piece placement does not consist of exactly eight ranks.
+    InvalidRankCount { count: usize },
+    /// A rank does not describe exactly eight squares. `rank` is counted from 1 to 8, as on the board.
+    InvalidRankLength { rank: i8, position: usize },
+    /// A pawn stands on the first or eighth rank, which it can never reach without promoting.
+    PawnOnBackRank { position: usize },
+    /// The halfmove clock or fullmove number is not a valid number.
+    InvalidNumber { field: FenField, position: usize },
+    /// The en passant square is not on the rank a pawn of the side that just moved could have skipped.
+    InvalidEnPassantSquare { position: usize },
+    /// A castling right is given, but there is no king or no matching rook on the back rank.
+    InvalidCastlingRight { character: char, position: usize },
+    /// Each side needs exactly one king.
+    InvalidKingCount { side: Side, count: u32 },
+    /// The side that is not to move is in check, so its king could be captured.
+    OpponentInCheck,
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "the {} field is missing", field),
+            FenError::TooManyFields { position } => {
+                write!(f, "unexpected extra field at position {}", position)
+            }
+            FenError::InvalidCharacter {
+                field,
+                character,
+                position,
+            } => write!(
+                f,
+                "invalid character {:?} in the {} field at position {}",
+                character, field, position
+            ),
+            FenError::InvalidRankCount { count } => {
+                write!(f, "expected 8 ranks, but found {}", count)
+            }
+            FenError::InvalidRankLength { rank, position } => write!(
+                f,
+                "rank {} does not describe exactly 8 squares (at position {})",
+                rank, position
+            ),
+            FenError::PawnOnBackRank { position } => write!(
+                f,
+                "the pawn at position {} is on the first or eighth rank",
+                position
+            ),
+            FenError::InvalidNumber { field, position } => {
+                write!(
+                    f,
+                    "the {} at position {} is not a valid number",
+                    field, position
+                )
+            }
+            FenError::InvalidEnPassantSquare { position } => write!(
+                f,
+                "the en passant square at position {} is on the wrong rank",
+                position
+            ),
+            FenError::InvalidCastlingRight {
+                character,
+                position,
+            } => write!(
+                f,
+                "castling right {:?} at position {} needs the king and a matching rook on the back rank",
+                character, position
+            ),
+            FenError::InvalidKingCount { side, count } => {
+                write!(f, "{:?} has {} kings instead of 1", side, count)
+            }
+            FenError::OpponentInCheck => write!(f, "the side that is not to move is in check"),
+        }
+    }
+}
+
+impl Error for FenError {}
+
+/// A square or move in uci notation, like `e4` or `e7e8q`, could not be read.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum UciNotationError {
+    InvalidSquare(String),
+    InvalidMove(String),
+}
+
+impl Display for UciNotationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UciNotationError::InvalidSquare(square) => {
+                write!(f, "{:?} is not a square in uci notation", square)
+            }
+            UciNotationError::InvalidMove(moove) => {
+                write!(f, "{:?} is not a move in uci notation", moove)
+            }
+        }
+    }
+}
+
+impl Error for UciNotationError {}
 
 /// Parses a FEN (Forsyth-Edwards Notation) string and updates the corresponding game game_state.
-/// https://www.chessprogramming.org/Forsyth-Edwards_Notation
+/// https://www.chessprogramming.org/Forsyth-Edwards_Notation
+///
+/// The halfmove clock and fullmove number are optional, since many tools leave them out.
+/// They default to 0 and 1.
 ///
 /// # Arguments
 ///
@@ -16,225 +165,404 @@ use crate::backend::types::square::square_from_rank_and_file;
 /// * `bit_board_manager` - A mutable reference to a `BitBoardManager` to update the board positions.
 /// * `active_color` - A mutable reference to a `PieceColor` to set the active player.
 /// * `irreversible_data` - A mutable reference to `IrreversibleData`.
+/// * `castling_config` - A mutable reference to the `CastlingConfig`, which stores which rooks the castling rights belong to.
 /// * `half_move_clock` - A mutable reference to a `u16` to update the current half-move clock count.
+/// * `full_move_number` - A mutable reference to a `u16` to update the current full move number.
 pub fn parse_fen(
     fen_string: &str,
     bit_board_manager: &mut BBManager,
     active_color: &mut Side,
     irreversible_data: &mut IrreversibleData,
+    castling_config: &mut CastlingConfig,
     half_move_clock: &mut u16,
-) {
-    let fen_string = fen_string.split_whitespace().collect::<Vec<&str>>();
+    full_move_number: &mut u16,
+) -> Result<(), FenError> {
+    let fields = split_fields(fen_string);
 
-    let positions_string = fen_string[0];
-    parse_position(bit_board_manager, positions_string);
+    let (position, positions_string) = get_field(&fields, FenField::PiecePlacement)?;
+    parse_position(bit_board_manager, positions_string, position)?;
 
-    let active_color_string = fen_string[1];
-    parse_active_color(active_color, active_color_string);
+    let (position, active_color_string) = get_field(&fields, FenField::ActiveColor)?;
+    parse_active_color(active_color, active_color_string, position)?;
 
-    let castling_rights_string = fen_string[2];
-    parse_castling_rights(irreversible_data, castling_rights_string);
+    let (position, castling_rights_string) = get_field(&fields, FenField::CastlingRights)?;
+    parse_castling_rights(
+        irreversible_data,
+        castling_config,
+        bit_board_manager,
+        castling_rights_string,
+        position,
+    )?;
 
-    let en_passant_file_string = fen_string[3];
-    parse_en_passant(irreversible_data, en_passant_file_string);
+    let (position, en_passant_file_string) = get_field(&fields, FenField::EnPassantSquare)?;
+    parse_en_passant(
+        irreversible_data,
+        *active_color,
+        en_passant_file_string,
+        position,
+    )?;
 
-    let half_move_clock_string = fen_string[4];
-    *half_move_clock = half_move_clock_string.parse::<u16>().unwrap();
+    *half_move_clock = match fields.get(4) {
+        None => 0,
+        Some((position, half_move_clock_string)) => {
+            parse_number(half_move_clock_string, FenField::HalfMoveClock, *position)?
+        }
+    };
+
+    *full_move_number = match fields.get(5) {
+        None => 1,
+        Some((position, full_move_number_string)) => {
+            parse_number(full_move_number_string, FenField::FullMoveNumber, *position)?
+        }
+    };
 
-    let _full_move_number_string = fen_string[5];
-    // I don't store this data as it isn't used for anything.
+    if let Some((position, _)) = fields.get(6) {
+        return Err(FenError::TooManyFields {
+            position: *position,
+        });
+    }
+
+    Ok(())
 }
 
-fn parse_en_passant(irreversible_data: &mut IrreversibleData, en_passant_file_string: &str) {
-    if en_passant_file_string == "-" {
-        irreversible_data.en_passant_square = None;
-        return;
+/// Checks the things about a parsed position that the move gen relies on.
+pub fn validate_position(state: &State) -> Result<(), FenError> {
+    for side in Side::get_all_colors() {
+        let count = state
+            .bb_manager
+            .get_colored_piece_bb(King, side)
+            .count_ones();
+        if count != 1 {
+            return Err(FenError::InvalidKingCount { side, count });
+        }
     }
 
-    let mut file = 0;
-    let mut rank = 0;
-    for char in en_passant_file_string.chars() {
-        match char {
-            'a'..='h' => {
-                file = char.to_digit(36).unwrap() as i8 - 10;
-            }
-            '3' | '6' => {
-                rank = char.to_digit(10).unwrap() as i8 - 1;
+    if is_in_check(state, state.active_color.opposite()) {
+        return Err(FenError::OpponentInCheck);
+    }
+
+    Ok(())
+}
+
+/// Splits the fen string at whitespace and remembers the character position each field starts at.
+fn split_fields(fen_string: &str) -> Vec<(usize, &str)> {
+    let mut fields = Vec::new();
+    let mut field_start = None;
+
+    for (position, (byte_index, char)) in fen_string.char_indices().enumerate() {
+        match (char.is_whitespace(), field_start) {
+            (false, None) => field_start = Some((position, byte_index)),
+            (true, Some((start_position, start_byte_index))) => {
+                fields.push((start_position, &fen_string[start_byte_index..byte_index]));
+                field_start = None;
             }
-            _ => panic!("Invalid character in FEN string"),
+            _ => {}
         }
     }
+    if let Some((start_position, start_byte_index)) = field_start {
+        fields.push((start_position, &fen_string[start_byte_index..]));
+    }
+
+    fields
+}
+
+fn get_field<'a>(
+    fields: &[(usize, &'a str)],
+    field: FenField,
+) -> Result<(usize, &'a str), FenError> {
+    fields
+        .get(field as usize)
+        .copied()
+        .ok_or(FenError::MissingField(field))
+}
+
+fn parse_number(number_string: &str, field: FenField, position: usize) -> Result<u16, FenError> {
+    number_string
+        .parse::<u16>()
+        .map_err(|_| FenError::InvalidNumber { field, position })
+}
+
+fn parse_en_passant(
+    irreversible_data: &mut IrreversibleData,
+    active_color: Side,
+    en_passant_file_string: &str,
+    position: usize,
+) -> Result<(), FenError> {
+    if en_passant_file_string == "-" {
+        irreversible_data.en_passant_square = None;
+        return Ok(());
+    }
+
+    let mut chars = en_passant_file_string.chars();
+    let file = match chars.next() {
+        Some(char @ 'a'..='h') => char.to_digit(36).unwrap() as i8 - 10,
+        other => return Err(invalid_en_passant_character(other, position)),
+    };
+    let rank = match chars.next() {
+        Some(char @ '1'..='8') => char.to_digit(10).unwrap() as i8 - 1,
+        other => return Err(invalid_en_passant_character(other, position + 1)),
+    };
+    if let Some(char) = chars.next() {
+        return Err(invalid_en_passant_character(Some(char), position + 2));
+    }
+
+    // If white is to move, black just double pushed and skipped the sixth rank and vice versa.
+    let expected_rank = match active_color {
+        White => 5,
+        Black => 2,
+    };
+    if rank != expected_rank {
+        return Err(FenError::InvalidEnPassantSquare { position });
+    }
+
     irreversible_data.en_passant_square = Some(square_from_rank_and_file(rank, file));
+    Ok(())
 }
 
-fn parse_castling_rights(irreversible_data: &mut IrreversibleData, castling_rights_string: &str) {
-    for char in castling_rights_string.chars() {
-        match char {
-            '-' => {
-                irreversible_data.white_long_castle_rights = false;
-                irreversible_data.white_short_castle_rights = false;
-                irreversible_data.black_long_castle_rights = false;
-                irreversible_data.black_short_castle_rights = false;
-            }
-            'K' => {
-                irreversible_data.white_short_castle_rights = true;
-            }
-            'k' => {
-                irreversible_data.black_short_castle_rights = true;
+fn invalid_en_passant_character(char: Option<char>, position: usize) -> FenError {
+    match char {
+        None => FenError::MissingField(FenField::EnPassantSquare),
+        Some(character) => FenError::InvalidCharacter {
+            field: FenField::EnPassantSquare,
+            character,
+            position,
+        },
+    }
+}
+
+/// Parses the castling rights in the usual `KQkq` form as well as in the two Chess960 forms:
+/// - Shredder-FEN gives the file of the rook instead, e.g. `HAha` for the starting position.
+/// - X-FEN uses `KQkq` for the outermost rook on each side of the king and the file only for other rooks.
+///
+/// Standard chess is just the Chess960 position with the king on the e file and the rooks in the corners.
+fn parse_castling_rights(
+    irreversible_data: &mut IrreversibleData,
+    castling_config: &mut CastlingConfig,
+    bit_board_manager: &BBManager,
+    castling_rights_string: &str,
+    position: usize,
+) -> Result<(), FenError> {
+    if castling_rights_string == "-" {
+        irreversible_data.white_long_castle_rights = false;
+        irreversible_data.white_short_castle_rights = false;
+        irreversible_data.black_long_castle_rights = false;
+        irreversible_data.black_short_castle_rights = false;
+        return Ok(());
+    }
+
+    for (offset, char) in castling_rights_string.chars().enumerate() {
+        let rook_file = match char.to_ascii_lowercase() {
+            'k' | 'q' => None,
+            'a'..='h' => Some(char.to_ascii_lowercase() as i8 - 'a' as i8),
+            _ => {
+                return Err(FenError::InvalidCharacter {
+                    field: FenField::CastlingRights,
+                    character: char,
+                    position: position + offset,
+                });
             }
-            'Q' => {
-                irreversible_data.white_long_castle_rights = true;
+        };
+        let side = if char.is_ascii_uppercase() {
+            White
+        } else {
+            Black
+        };
+        let invalid_castling_right = FenError::InvalidCastlingRight {
+            character: char,
+            position: position + offset,
+        };
+
+        // Castling moves the king and the rook without checking for them, so they better be there.
+        let back_rank = match side {
+            White => 0,
+            Black => 7,
+        };
+        let king_square = bit_board_manager
+            .get_colored_piece_bb(King, side)
+            .find(|&square| get_rank(square) == back_rank)
+            .ok_or(invalid_castling_right.clone())?;
+        let (castle_type, rook_square) = match rook_file {
+            None => {
+                let castle_type = match char.to_ascii_lowercase() {
+                    'k' => CastleType::Short,
+                    _ => CastleType::Long,
+                };
+                let rook_square =
+                    get_outermost_rook(bit_board_manager, side, king_square, castle_type)
+                        .ok_or(invalid_castling_right)?;
+                (castle_type, rook_square)
             }
-            'q' => {
-                irreversible_data.black_long_castle_rights = true;
+            Some(rook_file) => {
+                let rook_square = square_from_rank_and_file(back_rank, rook_file);
+                if !bit_board_manager
+                    .get_colored_piece_bb(Rook, side)
+                    .get_square(rook_square)
+                {
+                    return Err(invalid_castling_right);
+                }
+                let castle_type = if rook_square > king_square {
+                    CastleType::Short
+                } else {
+                    CastleType::Long
+                };
+                (castle_type, rook_square)
             }
-            _ => panic!("Invalid character in FEN string"),
+        };
+
+        match (side, castle_type) {
+            (White, CastleType::Long) => irreversible_data.white_long_castle_rights = true,
+            (White, CastleType::Short) => irreversible_data.white_short_castle_rights = true,
+            (Black, CastleType::Long) => irreversible_data.black_long_castle_rights = true,
+            (Black, CastleType::Short) => irreversible_data.black_short_castle_rights = true,
         }
+        castling_config.set_castle(side, castle_type, king_square, rook_square);
+    }
+
+    Ok(())
+}
+
+/// Returns the rook on the king's rank that is furthest away from the king in the direction of the castle.
+/// This is the rook `K` and `Q` refer to in X-FEN.
+fn get_outermost_rook(
+    bb_manager: &BBManager,
+    side: Side,
+    king_square: Square,
+    castle_type: CastleType,
+) -> Option<Square> {
+    let mut rooks_on_king_rank = bb_manager
+        .get_colored_piece_bb(Rook, side)
+        .filter(|&square| get_rank(square) == get_rank(king_square));
+    match castle_type {
+        CastleType::Long => rooks_on_king_rank.find(|&square| square < king_square),
+        CastleType::Short => rooks_on_king_rank
+            .filter(|&square| square > king_square)
+            .max(),
     }
 }
 
-fn parse_active_color(active_color: &mut Side, active_color_string: &str) {
+fn parse_active_color(
+    active_color: &mut Side,
+    active_color_string: &str,
+    position: usize,
+) -> Result<(), FenError> {
     match active_color_string {
         "w" => *active_color = Side::White,
         "b" => *active_color = Side::Black,
         _ => {
-            panic!("Invalid character in FEN string");
+            // Report the first character that makes this invalid.
+            let (offset, character) = active_color_string
+                .chars()
+                .enumerate()
+                .find(|(offset, char)| *offset > 0 || !matches!(char, 'w' | 'b'))
+                .unwrap();
+            return Err(FenError::InvalidCharacter {
+                field: FenField::ActiveColor,
+                character,
+                position: position + offset,
+            });
         }
     }
+    Ok(())
 }
 
-fn parse_position(bit_board_manager: &mut BBManager, positions_string: &str) {
+/// Returns the piece and its side for a piece character, e.g. `n` is a black knight.
+pub fn piece_from_fen_char(char: char) -> Option<(Piece, Side)> {
+    let piece = match char.to_ascii_lowercase() {
+        'p' => Pawn,
+        'r' => Rook,
+        'n' => Knight,
+        'b' => Bishop,
+        'q' => Queen,
+        'k' => King,
+        _ => return None,
+    };
+    let side = if char.is_ascii_uppercase() {
+        White
+    } else {
+        Black
+    };
+    Some((piece, side))
+}
+
+fn parse_position(
+    bit_board_manager: &mut BBManager,
+    positions_string: &str,
+    position: usize,
+) -> Result<(), FenError> {
     let mut file = 0;
     let mut rank = 7;
-    for char in positions_string.chars() {
+    let mut rank_count = 1;
+
+    for (offset, char) in positions_string.chars().enumerate() {
+        let char_position = position + offset;
         match char {
             '1'..='8' => {
                 file += char.to_digit(10).unwrap() as i8;
             }
             '/' => {
+                if file != 8 {
+                    return Err(FenError::InvalidRankLength {
+                        rank: rank + 1,
+                        position: char_position,
+                    });
+                }
                 file = 0;
                 rank -= 1;
+                rank_count += 1;
+                if rank < 0 {
+                    return Err(FenError::InvalidRankCount { count: rank_count });
+                }
             }
-            'P' => {
-                fill_square(
-                    bit_board_manager,
-                    Pawn,
-                    White,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'p' => {
-                fill_square(
-                    bit_board_manager,
-                    Pawn,
-                    Black,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'R' => {
-                fill_square(
-                    bit_board_manager,
-                    Rook,
-                    White,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'r' => {
-                fill_square(
-                    bit_board_manager,
-                    Rook,
-                    Black,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'N' => {
-                fill_square(
-                    bit_board_manager,
-                    Knight,
-                    White,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'n' => {
-                fill_square(
-                    bit_board_manager,
-                    Knight,
-                    Black,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'B' => {
-                fill_square(
-                    bit_board_manager,
-                    Bishop,
-                    White,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'b' => {
-                fill_square(
-                    bit_board_manager,
-                    Bishop,
-                    Black,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'Q' => {
-                fill_square(
-                    bit_board_manager,
-                    Queen,
-                    White,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'q' => {
-                fill_square(
-                    bit_board_manager,
-                    Queen,
-                    Black,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'K' => {
-                fill_square(
-                    bit_board_manager,
-                    King,
-                    White,
-                    square_from_rank_and_file(rank, file),
-                );
-                file += 1;
-            }
-            'k' => {
+            _ => {
+                let Some((piece_type, side)) = piece_from_fen_char(char) else {
+                    return Err(FenError::InvalidCharacter {
+                        field: FenField::PiecePlacement,
+                        character: char,
+                        position: char_position,
+                    });
+                };
+                if file >= 8 {
+                    return Err(FenError::InvalidRankLength {
+                        rank: rank + 1,
+                        position: char_position,
+                    });
+                }
+                if piece_type == Pawn && (rank == 0 || rank == 7) {
+                    return Err(FenError::PawnOnBackRank {
+                        position: char_position,
+                    });
+                }
                 fill_square(
                     bit_board_manager,
-                    King,
-                    Black,
+                    piece_type,
+                    side,
                     square_from_rank_and_file(rank, file),
                 );
                 file += 1;
             }
-            _ => {
-                panic!("Invalid character in FEN string");
-            }
+        }
+
+        if file > 8 {
+            return Err(FenError::InvalidRankLength {
+                rank: rank + 1,
+                position: char_position,
+            });
         }
     }
 
-    fn fill_square(
-        bb_manager: &mut BBManager,
-        piece_type: Piece,
-        side: Side,
-        square: Square,
-    ) {
+    if rank_count != 8 {
+        return Err(FenError::InvalidRankCount { count: rank_count });
+    }
+    if file != 8 {
+        return Err(FenError::InvalidRankLength {
+            rank: rank + 1,
+            position: position + positions_string.chars().count(),
+        });
+    }
+
+    return Ok(());
+
+    fn fill_square(bb_manager: &mut BBManager, piece_type: Piece, side: Side, square: Square) {
         bb_manager.get_piece_bb_mut(piece_type).fill_square(square);
         bb_manager
             .get_all_pieces_bb_off_mut(side)
@@ -242,6 +570,138 @@ fn parse_position(bit_board_manager: &mut BBManager, positions_string: &str) {
     }
 }
 
+// -------------------
+/// Turns the state back into a fen string. Parsing the result yields the same position again.
+pub fn generate_fen(state: &State) -> String {
+    let mut fen = generate_epd_position(state);
+    fen.push_str(&format!(
+        " {} {}",
+        state.half_move_clock, state.full_move_number
+    ));
+    fen
+}
+
+/// Returns the first four fields of the fen, which is the position part of an epd.
+pub fn generate_epd_position(state: &State) -> String {
+    let mut fen = String::new();
+
+    for rank in (0..8).rev() {
+        let mut empty_squares = 0;
+        for file in 0..8 {
+            let square = square_from_rank_and_file(rank, file);
+            let Some(piece_type) = state.bb_manager.get_piece_at_square(square) else {
+                empty_squares += 1;
+                continue;
+            };
+
+            if empty_squares > 0 {
+                fen.push_str(&empty_squares.to_string());
+                empty_squares = 0;
+            }
+            let side = if state
+                .bb_manager
+                .get_all_pieces_bb_off(White)
+                .get_square(square)
+            {
+                White
+            } else {
+                Black
+            };
+            fen.push(piece_to_fen_char(piece_type, side));
+        }
+
+        if empty_squares > 0 {
+            fen.push_str(&empty_squares.to_string());
+        }
+        if rank > 0 {
+            fen.push('/');
+        }
+    }
+
+    fen.push_str(match state.active_color {
+        White => " w ",
+        Black => " b ",
+    });
+
+    let irreversible_data = &state.irreversible_data;
+    let castling_rights = [
+        (
+            irreversible_data.white_short_castle_rights,
+            White,
+            CastleType::Short,
+            'K',
+        ),
+        (
+            irreversible_data.white_long_castle_rights,
+            White,
+            CastleType::Long,
+            'Q',
+        ),
+        (
+            irreversible_data.black_short_castle_rights,
+            Black,
+            CastleType::Short,
+            'k',
+        ),
+        (
+            irreversible_data.black_long_castle_rights,
+            Black,
+            CastleType::Long,
+            'q',
+        ),
+    ];
+    // This writes X-FEN, which is the usual fen for standard chess: Only rooks that are not the outermost need their file.
+    let castling_rights_string: String = castling_rights
+        .iter()
+        .filter(|(has_right, ..)| *has_right)
+        .map(|&(_, side, castle_type, char)| {
+            let rook_square = state.castling_config.get_rook_square(side, castle_type);
+            let king_square = state.castling_config.get_king_square(side);
+            if get_outermost_rook(&state.bb_manager, side, king_square, castle_type)
+                == Some(rook_square)
+            {
+                return char;
+            }
+            let file_char = (b'a' + get_file(rook_square) as u8) as char;
+            match side {
+                White => file_char.to_ascii_uppercase(),
+                Black => file_char,
+            }
+        })
+        .collect();
+    if castling_rights_string.is_empty() {
+        fen.push('-');
+    } else {
+        fen.push_str(&castling_rights_string);
+    }
+
+    match irreversible_data.en_passant_square {
+        None => fen.push_str(" -"),
+        Some(square) => {
+            fen.push(' ');
+            fen.push_str(&square_to_string(square));
+        }
+    }
+
+    fen
+}
+
+/// Returns the fen character of a piece, upper case for white and lower case for black.
+pub fn piece_to_fen_char(piece_type: Piece, side: Side) -> char {
+    let char = match piece_type {
+        Pawn => 'p',
+        Rook => 'r',
+        Knight => 'n',
+        Bishop => 'b',
+        Queen => 'q',
+        King => 'k',
+    };
+    match side {
+        White => char.to_ascii_uppercase(),
+        Black => char,
+    }
+}
+
 // -------------------
 // This is used during debugging and the uci interface.
 #[allow(unused)]
@@ -275,8 +735,33 @@ pub fn moove_from_uci_notation(uci_notation: &str) -> Moove {
             'q' => (Queen),
             _ => panic!("Invalid promotion type {:?}", uci_notation),
         };
-        return Moove::new_promotion(from, to, promotion_type)
+        return Moove::new_promotion(from, to, promotion_type);
     };
 
     Moove::new(from, to)
 }
+
+/// Like `square_from_uci_notation`, but returns an error instead of panicking if the string is not a square.
+pub fn try_square_from_uci_notation(uci_notation: &str) -> Result<Square, UciNotationError> {
+    match uci_notation.as_bytes() {
+        [b'a'..=b'h', b'1'..=b'8'] => Ok(square_from_uci_notation(uci_notation)),
+        _ => Err(UciNotationError::InvalidSquare(uci_notation.to_string())),
+    }
+}
+
+/// Like `moove_from_uci_notation`, but returns an error instead of panicking if the string is not a uci move.
+/// This does not check whether the move is legal in any position.
+pub fn try_moove_from_uci_notation(uci_notation: &str) -> Result<Moove, UciNotationError> {
+    let is_square = |square: &[u8]| matches!(square, [b'a'..=b'h', b'1'..=b'8']);
+
+    let bytes = uci_notation.as_bytes();
+    let is_valid = (bytes.len() == 4 || bytes.len() == 5)
+        && is_square(&bytes[0..2])
+        && is_square(&bytes[2..4])
+        && bytes.get(4).is_none_or(|char| b"rnbq".contains(char));
+
+    if !is_valid {
+        return Err(UciNotationError::InvalidMove(uci_notation.to_string()));
+    }
+    Ok(moove_from_uci_notation(uci_notation))
+}
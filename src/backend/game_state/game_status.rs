@@ -0,0 +1,150 @@
+use crate::backend::game_state::state::State;
+use crate::backend::movegen::check_decider::is_in_check;
+use crate::backend::movegen::move_gen::get_legal_moves;
+use crate::backend::types::bitboard::BitBoard;
+use crate::backend::types::piece::Piece::{Bishop, Knight, Pawn, Queen, Rook};
+use crate::backend::types::piece::Side;
+use std::fmt::{Display, Formatter};
+
+// Made with https://tearth.dev/bitboard-viewer/
+const LIGHT_SQUARES_BB: BitBoard = BitBoard {
+    value: 0x55aa55aa55aa55aa,
+};
+
+/// Fifty moves by each side without a capture or pawn move.
+const FIFTY_MOVE_RULE_HALF_MOVES: u16 = 100;
+const SEVENTY_FIVE_MOVE_RULE_HALF_MOVES: u16 = 150;
+
+/// Whether the game is still running or how it ended.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum GameStatus {
+    Ongoing,
+    Over(Outcome),
+}
+
+/// How a game ended. `winner` is `None` for draws.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Outcome {
+    pub winner: Option<Side>,
+    pub reason: Termination,
+}
+
+/// The rule that ended the game.
+/// The fifty move rule and threefold repetition only allow a player to claim a draw,
+/// while the seventy-five move rule and fivefold repetition end the game automatically.
+/// Both kinds are reported, so the caller can decide which ones to enforce.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    InsufficientMaterial,
+    SeventyFiveMoveRule,
+    FivefoldRepetition,
+    FiftyMoveRule,
+    ThreefoldRepetition,
+}
+
+impl GameStatus {
+    pub fn is_over(&self) -> bool {
+        matches!(self, GameStatus::Over(_))
+    }
+}
+
+impl Outcome {
+    fn draw(reason: Termination) -> Outcome {
+        Outcome {
+            winner: None,
+            reason,
+        }
+    }
+
+    pub fn is_draw(&self) -> bool {
+        self.winner.is_none()
+    }
+}
+
+/// Prints the result the same way pgn does, e.g. `1-0 (checkmate)`.
+impl Display for Outcome {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let result = match self.winner {
+            Some(Side::White) => "1-0",
+            Some(Side::Black) => "0-1",
+            None => "1/2-1/2",
+        };
+        let reason = match self.reason {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::SeventyFiveMoveRule => "seventy-five move rule",
+            Termination::FivefoldRepetition => "fivefold repetition",
+            Termination::FiftyMoveRule => "fifty move rule",
+            Termination::ThreefoldRepetition => "threefold repetition",
+        };
+        write!(f, "{} ({})", result, reason)
+    }
+}
+
+/// Decides whether the game is over.
+/// A checkmate takes precedence over every draw rule, since it ends the game immediately.
+/// After that the automatic draws are reported before the claimable ones.
+pub fn get_game_status(state: &State, check_repetitions: bool) -> GameStatus {
+    if get_legal_moves(state).is_empty() {
+        let outcome = if is_in_check(state, state.active_color) {
+            Outcome {
+                winner: Some(state.active_color.opposite()),
+                reason: Termination::Checkmate,
+            }
+        } else {
+            Outcome::draw(Termination::Stalemate)
+        };
+        return GameStatus::Over(outcome);
+    }
+
+    if is_insufficient_material(state) {
+        return GameStatus::Over(Outcome::draw(Termination::InsufficientMaterial));
+    }
+
+    let repetition_count = if check_repetitions {
+        state.repetition_count()
+    } else {
+        1
+    };
+
+    let reason = if state.half_move_clock >= SEVENTY_FIVE_MOVE_RULE_HALF_MOVES {
+        Termination::SeventyFiveMoveRule
+    } else if repetition_count >= 5 {
+        Termination::FivefoldRepetition
+    } else if state.half_move_clock >= FIFTY_MOVE_RULE_HALF_MOVES {
+        Termination::FiftyMoveRule
+    } else if repetition_count >= 3 {
+        Termination::ThreefoldRepetition
+    } else {
+        return GameStatus::Ongoing;
+    };
+    GameStatus::Over(Outcome::draw(reason))
+}
+
+/// Checks if neither side can possibly checkmate, no matter how badly the other one plays.
+/// This is the case for king vs king, king and a single minor piece vs king,
+/// and if all remaining minor pieces are bishops on squares of the same color.
+pub fn is_insufficient_material(state: &State) -> bool {
+    let bb_manager = &state.bb_manager;
+
+    let heavy_pieces_and_pawns_bb = bb_manager.get_piece_bb(Pawn)
+        | bb_manager.get_piece_bb(Rook)
+        | bb_manager.get_piece_bb(Queen);
+    if heavy_pieces_and_pawns_bb.is_not_empty() {
+        return false;
+    }
+
+    let knights_bb = bb_manager.get_piece_bb(Knight);
+    let bishops_bb = bb_manager.get_piece_bb(Bishop);
+    if (knights_bb | bishops_bb).count_ones() <= 1 {
+        return true;
+    }
+
+    // Bishops that all stand on the same color can never attack a king on the other color.
+    let all_bishops_on_same_color =
+        (bishops_bb & LIGHT_SQUARES_BB).is_empty() || (bishops_bb & !LIGHT_SQUARES_BB).is_empty();
+    knights_bb.is_empty() && all_bishops_on_same_color
+}
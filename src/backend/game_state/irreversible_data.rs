@@ -4,7 +4,7 @@ use crate::backend::types::square::Square;
 
 /// The `IrreversibleData` struct stores data that is irreversible.
 /// For example, this remembers what kind of piece was captured for `unmake_move()`.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Eq, PartialEq)]
 pub struct IrreversibleData {
     pub captured_piece: Option<Piece>,
     pub en_passant_square: Option<Square>,
@@ -62,6 +62,13 @@ impl IrreversibleData {
         }
     }
 
+    pub fn get_castle_rights(&self, color: Side, castle_type: CastleType) -> bool {
+        match castle_type {
+            CastleType::Long => self.get_long_castle_rights(color),
+            CastleType::Short => self.get_short_castle_rights(color),
+        }
+    }
+
     pub fn remove_long_castle_rights(&mut self, color: Side) {
         match color {
             Side::White => self.white_long_castle_rights = false,
@@ -1,4 +1,7 @@
 pub mod bb_manager;
+pub mod castling_config;
 pub mod fen_parser;
+pub mod game_status;
 pub mod irreversible_data;
-pub mod state;
\ No newline at end of file
+pub mod state;
+pub mod zobrist;
@@ -1,28 +1,52 @@
-use crate::backend::constants::{A1, A8, H1, H8};
 use crate::backend::types::moove::{CastleType, Moove};
 use crate::backend::game_state::bb_manager::BBManager;
 use crate::backend::types::bitboard::BitBoard;
-use crate::backend::game_state::fen_parser::parse_fen;
+use crate::backend::game_state::castling_config::{CastlingConfig, get_castle_squares_of_move};
+use crate::backend::game_state::fen_parser::{
+    FenError, STARTING_POSITION_FEN, generate_fen, parse_fen, validate_position,
+};
+use crate::backend::game_state::game_status::{GameStatus, get_game_status};
 use crate::backend::game_state::irreversible_data::IrreversibleData;
+use crate::backend::movegen::move_gen::can_capture_en_passant;
+use crate::backend::movegen::check_decider::{
+    get_attackers_to_square, get_attacks_by_side, is_square_attacked,
+};
+use crate::backend::game_state::zobrist::{
+    compute_zobrist_key, get_castling_key, get_en_passant_key, get_piece_key, get_side_key,
+};
 use crate::backend::types::piece::Piece::{King, Pawn, Rook};
 use crate::backend::types::piece::{Piece, Side};
 use crate::backend::types::square::{Square, back_by_one};
-
-const ROOK_SWAP_WHITE_LONG_CASTLE_BB: BitBoard = BitBoard { value: 0x9 };
-const ROOK_SWAP_WHITE_SHORT_CASTLE_BB: BitBoard = BitBoard { value: 0xa0 };
-const ROOK_SWAP_BLACK_LONG_CASTLE_BB: BitBoard = BitBoard {
-    value: 0x900000000000000,
-};
-const ROOK_SWAP_BLACK_SHORT_CASTLE_BB: BitBoard = BitBoard {
-    value: 0xa000000000000000,
-};
+use std::fmt::{Display, Formatter};
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
 
 #[derive(Debug, Clone)]
 pub struct State {
     pub bb_manager: BBManager,
     pub irreversible_data: IrreversibleData,
+    /// Where the kings and castling rooks started. Only differs from standard chess in Chess960 positions.
+    pub castling_config: CastlingConfig,
     pub active_color: Side,
+    /// The number of half moves since the last capture or pawn move, used for the fifty move rule.
     pub half_move_clock: u16,
+    /// Starts at 1 and is incremented after every move of black.
+    pub full_move_number: u16,
+    /// The zobrist key of the position. It is updated incrementally by `make_move`.
+    pub zobrist_key: u64,
+    /// The irreversible data of all previous positions, the most recent one is on top.
+    history: Vec<IrreversibleData>,
+    /// The zobrist keys of all previous positions, the most recent one is on top. Used to detect repetitions.
+    key_history: Vec<u64>,
+}
+
+/// Everything `unmake_move` needs to revert a move that is not stored in the history stack.
+#[derive(Copy, Clone, Debug)]
+pub struct Undo {
+    moved_piece: Piece,
+    half_move_clock: u16,
+    full_move_number: u16,
+    zobrist_key: u64,
 }
 
 impl State {
@@ -31,103 +55,317 @@ impl State {
     /// Those cant be `default`ed and i would rather keep it constitent.
     #[allow(clippy::new_without_default)]
     pub fn new() -> State {
-        State {
+        let mut state = State {
             bb_manager: BBManager::new(),
             active_color: Side::White,
             irreversible_data: IrreversibleData::new_with_castling_true(),
+            castling_config: CastlingConfig::new(),
             half_move_clock: 0,
-        }
+            full_move_number: 1,
+            zobrist_key: 0,
+            history: Vec::new(),
+            key_history: Vec::new(),
+        };
+        state.zobrist_key = compute_zobrist_key(&state);
+        state
     }
 
     /// Creates a new `GameState` instance based on the fen string.
+    /// Panics if the fen string is invalid, use `try_from_fen` for fens that come from the outside.
     pub fn new_from_fen(fen_string: &str) -> State {
+        match State::try_from_fen(fen_string) {
+            Ok(state) => state,
+            Err(error) => panic!("Invalid fen string {:?}: {}", fen_string, error),
+        }
+    }
+
+    /// Creates a new `GameState` instance based on the fen string.
+    /// `startpos` is accepted as a shortcut for the starting position, just like in uci.
+    pub fn try_from_fen(fen_string: &str) -> Result<State, FenError> {
+        let fen_string = match fen_string.trim() {
+            "startpos" => STARTING_POSITION_FEN,
+            fen_string => fen_string,
+        };
+
         let mut bb_manager = BBManager::new();
         let mut active_color = Side::White;
         let mut irreversible_data = IrreversibleData::new();
+        let mut castling_config = CastlingConfig::new();
         let mut half_move_clock = 0;
+        let mut full_move_number = 1;
 
         parse_fen(
             fen_string,
             &mut bb_manager,
             &mut active_color,
             &mut irreversible_data,
+            &mut castling_config,
             &mut half_move_clock,
-        );
+            &mut full_move_number,
+        )?;
 
-        State {
+        let mut state = State {
             bb_manager,
             active_color,
             irreversible_data,
+            castling_config,
             half_move_clock,
+            full_move_number,
+            zobrist_key: 0,
+            history: Vec::new(),
+            key_history: Vec::new(),
+        };
+        validate_position(&state)?;
+        state.zobrist_key = compute_zobrist_key(&state);
+        Ok(state)
+    }
+
+    /// Returns the fen string of this state.
+    pub fn to_fen(&self) -> String {
+        generate_fen(self)
+    }
+
+    /// Returns a bitboard with all pieces - of both sides - that attack the given square.
+    /// Sliders are blocked by the pieces in `occupancy_bb`, which does not have to match the real board.
+    /// Pass `self.bb_manager.get_all_pieces_bb()` to get the attackers in the current position.
+    pub fn attackers_to(&self, square: Square, occupancy_bb: BitBoard) -> BitBoard {
+        get_attackers_to_square(self, square, occupancy_bb)
+    }
+
+    /// Returns a bitboard with all squares that are attacked by at least one piece of `side`.
+    pub fn attacks_by(&self, side: Side) -> BitBoard {
+        get_attacks_by_side(self, side)
+    }
+
+    /// Checks if any piece of `by_side` attacks the given square.
+    pub fn is_square_attacked(&self, square: Square, by_side: Side) -> bool {
+        is_square_attacked(self, square, by_side)
+    }
+
+    /// Returns whether the game is over and why, only looking at the current position.
+    /// Repetitions are not detected, use `status_with_history` for that.
+    pub fn status(&self) -> GameStatus {
+        get_game_status(self, false)
+    }
+
+    /// Like `status`, but also detects threefold and fivefold repetitions.
+    /// Only positions that were reached with `make_move` since this state was created are known.
+    pub fn status_with_history(&self) -> GameStatus {
+        get_game_status(self, true)
+    }
+
+    /// Returns the en passant square, but only if the side to move can legally capture on it.
+    /// After most double pushes no pawn is able to, and the position is then the same as without the square.
+    pub fn get_capturable_en_passant_square(&self) -> Option<Square> {
+        if can_capture_en_passant(self) {
+            self.irreversible_data.en_passant_square
+        } else {
+            None
         }
     }
 
-    /// Executes a move.
+    /// Returns how often the current position occurred, including the current occurrence.
+    /// Positions count as equal if their zobrist keys are equal, so side to move, castling rights and
+    /// en passant square have to match as well. The en passant square only counts if it can be captured on.
+    pub fn repetition_count(&self) -> usize {
+        // A capture or pawn move makes repeating earlier positions impossible,
+        // so only the last `half_move_clock` positions need to be looked at.
+        // Positions with the other side to move can't match either, so only every second one is checked.
+        let relevant_positions = (self.half_move_clock as usize).min(self.key_history.len());
+        let repetitions = self
+            .key_history
+            .iter()
+            .rev()
+            .take(relevant_positions)
+            .skip(1)
+            .step_by(2)
+            .filter(|key| **key == self.zobrist_key)
+            .count();
+        repetitions + 1
+    }
+
+    /// Executes a move on a copy of this state and returns the copy.
+    /// This is convenient, but slow, since the whole `State` gets cloned.
+    /// Prefer `make_move` and `unmake_move` when walking the game tree.
     ///
     /// # Arguments
     ///
     /// * `chess_move` - A `Moove` object representing the move to be made.
-    pub fn make_move(&self, moove: Moove) -> State {
+    pub fn make_move_copy(&self, moove: Moove) -> State {
         let mut next_state = self.clone();
+        next_state.make_move(moove);
+        next_state
+    }
+
+    /// Executes a move in place.
+    /// The previous irreversible data is pushed onto the history stack
+    /// and everything else `unmake_move` needs is returned as an `Undo`.
+    ///
+    /// # Arguments
+    ///
+    /// * `chess_move` - A `Moove` object representing the move to be made.
+    pub fn make_move(&mut self, moove: Moove) -> Undo {
         // The new irreversible data.
         let mut next_ir_data = IrreversibleData::new_from_previous_state(&self.irreversible_data);
 
         // Get the type of moved piece.
-        let moved_piece = self.bb_manager.get_piece_at_square(moove.get_from()).unwrap();
-
-        // Usually the square something was captured on (if something was captured at all) is the square we moved to...
-        let mut capture_square = moove.get_to();
-        if moved_piece == Pawn {
-            // ... unless this is an en passant capture, we then need to update the capture square.
-            next_state.make_move_ep_capture(moove, &mut capture_square);
-            // Check if a double pawn push was played and store the en passant file
-            next_state.make_move_double_pawn_push(moove, &mut next_ir_data);
-        }
+        let moved_piece = self
+            .bb_manager
+            .get_piece_at_square(moove.get_from())
+            .unwrap();
+        let undo = Undo {
+            moved_piece,
+            half_move_clock: self.half_move_clock,
+            full_move_number: self.full_move_number,
+            zobrist_key: self.zobrist_key,
+        };
+
+        // Remove the old side to move, castling rights and en passant file from the key.
+        // The new ones are added once they are known.
+        self.zobrist_key ^=
+            get_side_key() ^ get_castling_key(&self.irreversible_data) ^ get_en_passant_key(self);
 
-        // If something was captured, remove the piece and update irreversible data.
-        next_state.make_move_capture(&mut next_ir_data, capture_square);
+        // Castling moves two pieces and, in Chess960, may look like the king capturing its own rook.
+        if moved_piece == King && moove.is_castle() {
+            self.make_move_castle(moove, &mut next_ir_data);
+        } else {
+            // Usually the square something was captured on (if something was captured at all) is the square we moved to...
+            let mut capture_square = moove.get_to();
+            if moved_piece == Pawn {
+                // ... unless this is an en passant capture, we then need to update the capture square.
+                self.make_move_ep_capture(moove, &mut capture_square);
+                // Check if a double pawn push was played and store the en passant file
+                self.make_move_double_pawn_push(moove, &mut next_ir_data);
+            }
+
+            // If something was captured, remove the piece and update irreversible data.
+            self.make_move_capture(&mut next_ir_data, capture_square);
 
-        // Get the bitboard for the piece that was moved.
-        let mut moved_piece_bb = next_state.bb_manager.get_piece_bb_mut(moved_piece);
+            // Get the bitboard for the piece that was moved.
+            let mut moved_piece_bb = self.bb_manager.get_piece_bb_mut(moved_piece);
 
-        // Clear the square that the piece was moved from.
-        moved_piece_bb.clear_square(moove.get_from());
+            // Clear the square that the piece was moved from.
+            moved_piece_bb.clear_square(moove.get_from());
 
-        // Update the moved piece bb if it was a pawn promotion
-        match moove.get_promotion_type() {
-            None => {}
-            Some(promotion_type) => {
-                moved_piece_bb = next_state.bb_manager.get_piece_bb_mut(promotion_type);
+            // Update the moved piece bb if it was a pawn promotion
+            match moove.get_promotion_type() {
+                None => {}
+                Some(promotion_type) => {
+                    moved_piece_bb = self.bb_manager.get_piece_bb_mut(promotion_type);
+                }
             }
-        }
-        // Fill the square it moved to.
-        moved_piece_bb.fill_square(moove.get_to());
+            // Fill the square it moved to.
+            moved_piece_bb.fill_square(moove.get_to());
 
-        next_state
-            .bb_manager
-            .get_all_pieces_bb_off_mut(self.active_color)
-            .fill_square(moove.get_to());
-        next_state
-            .bb_manager
-            .get_all_pieces_bb_off_mut(self.active_color)
-            .clear_square(moove.get_from());
+            let piece_on_to_square = moove.get_promotion_type().unwrap_or(moved_piece);
+            self.zobrist_key ^= get_piece_key(moved_piece, self.active_color, moove.get_from())
+                ^ get_piece_key(piece_on_to_square, self.active_color, moove.get_to());
+
+            let friendly_bb = self.bb_manager.get_all_pieces_bb_off_mut(self.active_color);
+            friendly_bb.fill_square(moove.get_to());
+            friendly_bb.clear_square(moove.get_from());
 
-        // Some special king handling
-        if moved_piece == King {
-            next_state.make_move_king(moove, &mut next_ir_data);
+            // If the king moved we can't castle anymore
+            if moved_piece == King {
+                next_ir_data.remove_long_castle_rights(self.active_color);
+                next_ir_data.remove_short_castle_rights(self.active_color);
+            }
         }
 
-        next_state.make_move_castling_rights_on_rook_move_or_capture(
+        self.make_move_castling_rights_on_rook_move_or_capture(
             &mut next_ir_data,
             moved_piece,
             moove.get_from(),
             self.active_color,
         );
 
+        self.zobrist_key ^= get_castling_key(&next_ir_data);
+
         // Take care of some basics.
-        next_state.active_color = self.active_color.opposite();
-        next_state.irreversible_data = next_ir_data;
-        next_state
+        // The fifty move rule counter is reset by every capture and pawn move.
+        if moved_piece == Pawn || next_ir_data.captured_piece.is_some() {
+            self.half_move_clock = 0;
+        } else {
+            self.half_move_clock = self.half_move_clock.saturating_add(1);
+        }
+        // Fens may set the clocks to anything, so they stop at the maximum instead of overflowing.
+        if self.active_color == Side::Black {
+            self.full_move_number = self.full_move_number.saturating_add(1);
+        }
+        self.active_color = self.active_color.opposite();
+        let previous_ir_data = std::mem::replace(&mut self.irreversible_data, next_ir_data);
+        self.history.push(previous_ir_data);
+        self.key_history.push(undo.zobrist_key);
+        // Whether en passant is possible depends on the new board and side to move.
+        self.zobrist_key ^= get_en_passant_key(self);
+
+        debug_assert_eq!(
+            self.zobrist_key,
+            compute_zobrist_key(self),
+            "The incremental zobrist key diverged after {}",
+            moove
+        );
+        undo
+    }
+
+    /// Reverts a move that was executed with `make_move`.
+    /// Moves have to be unmade in the reverse order they were made in.
+    ///
+    /// # Arguments
+    ///
+    /// * `chess_move` - The `Moove` that was passed to `make_move`.
+    /// * `undo` - The `Undo` that `make_move` returned for this move.
+    pub fn unmake_move(&mut self, moove: Moove, undo: Undo) {
+        // The side that made the move is to move again.
+        self.active_color = self.active_color.opposite();
+        let captured_piece = self.irreversible_data.captured_piece;
+        self.irreversible_data = self
+            .history
+            .pop()
+            .expect("unmake_move was called without a matching make_move");
+        self.key_history.pop();
+        self.half_move_clock = undo.half_move_clock;
+        self.full_move_number = undo.full_move_number;
+        self.zobrist_key = undo.zobrist_key;
+
+        // Castles never capture, so moving the king and the rook back is all there is to do.
+        if undo.moved_piece == King && moove.is_castle() {
+            let castle_squares = get_castle_squares_of_move(moove);
+            self.move_castling_pieces(
+                castle_squares.king_to,
+                castle_squares.rook_to,
+                castle_squares.king_from,
+                castle_squares.rook_from,
+            );
+            return;
+        }
+
+        // Move the piece back. If it was a promotion, the promoted piece turns back into a pawn.
+        let piece_on_to_square = moove.get_promotion_type().unwrap_or(undo.moved_piece);
+        self.bb_manager
+            .get_piece_bb_mut(piece_on_to_square)
+            .clear_square(moove.get_to());
+        self.bb_manager
+            .get_piece_bb_mut(undo.moved_piece)
+            .fill_square(moove.get_from());
+
+        let friendly_bb = self.bb_manager.get_all_pieces_bb_off_mut(self.active_color);
+        friendly_bb.clear_square(moove.get_to());
+        friendly_bb.fill_square(moove.get_from());
+
+        // Put the captured piece back on the board.
+        if let Some(captured_piece) = captured_piece {
+            let mut capture_square = moove.get_to();
+            if undo.moved_piece == Pawn {
+                self.make_move_ep_capture(moove, &mut capture_square);
+            }
+            self.bb_manager
+                .get_piece_bb_mut(captured_piece)
+                .fill_square(capture_square);
+            self.bb_manager
+                .get_all_pieces_bb_off_mut(self.active_color.opposite())
+                .fill_square(capture_square);
+        }
     }
 
     fn make_move_ep_capture(&mut self, moove: Moove, capture_square: &mut Square) {
@@ -160,6 +398,8 @@ impl State {
             self.bb_manager
                 .get_all_pieces_bb_off_mut(self.active_color.opposite())
                 .clear_square(capture_square);
+            self.zobrist_key ^=
+                get_piece_key(captured_piece, self.active_color.opposite(), capture_square);
 
             // Remove castling rights if the captured piece was a rook on its starting square
             self.make_move_castling_rights_on_rook_move_or_capture(
@@ -184,32 +424,45 @@ impl State {
         }
     }
 
-    fn make_move_king(&mut self, moove: Moove, irreversible_data: &mut IrreversibleData) {
+    fn make_move_castle(&mut self, moove: Moove, irreversible_data: &mut IrreversibleData) {
         // If the king moved we can't castle anymore
         irreversible_data.remove_long_castle_rights(self.active_color);
         irreversible_data.remove_short_castle_rights(self.active_color);
 
-        // If we castled, we need to move the rook
-        if moove.is_castle() {
-            let rook_bb = self.bb_manager.get_piece_bb_mut(Rook);
-            let rook_swap_bb = Self::get_rook_swap_bb(moove.get_castle_type(), self.active_color);
-            *rook_bb ^= rook_swap_bb;
-            let friendly_bb = self.bb_manager.get_all_pieces_bb_off_mut(self.active_color);
-            *friendly_bb ^= rook_swap_bb;
-        }
+        let castle_squares = get_castle_squares_of_move(moove);
+        self.move_castling_pieces(
+            castle_squares.king_from,
+            castle_squares.rook_from,
+            castle_squares.king_to,
+            castle_squares.rook_to,
+        );
+        self.zobrist_key ^= get_piece_key(King, self.active_color, castle_squares.king_from)
+            ^ get_piece_key(King, self.active_color, castle_squares.king_to)
+            ^ get_piece_key(Rook, self.active_color, castle_squares.rook_from)
+            ^ get_piece_key(Rook, self.active_color, castle_squares.rook_to);
     }
 
-    fn get_rook_swap_bb(castle_type: CastleType, active_color: Side) -> BitBoard {
-        match castle_type {
-            CastleType::Long => match active_color {
-                Side::White => ROOK_SWAP_WHITE_LONG_CASTLE_BB,
-                Side::Black => ROOK_SWAP_BLACK_LONG_CASTLE_BB,
-            },
-            CastleType::Short => match active_color {
-                Side::White => ROOK_SWAP_WHITE_SHORT_CASTLE_BB,
-                Side::Black => ROOK_SWAP_BLACK_SHORT_CASTLE_BB,
-            },
-        }
+    /// Moves the king and the rook of the side to move.
+    /// In Chess960, a piece may land on a square the other one started on, so both are removed before placing them.
+    fn move_castling_pieces(
+        &mut self,
+        king_from: Square,
+        rook_from: Square,
+        king_to: Square,
+        rook_to: Square,
+    ) {
+        let king_bb = self.bb_manager.get_piece_bb_mut(King);
+        king_bb.clear_square(king_from);
+        king_bb.fill_square(king_to);
+        let rook_bb = self.bb_manager.get_piece_bb_mut(Rook);
+        rook_bb.clear_square(rook_from);
+        rook_bb.fill_square(rook_to);
+
+        let friendly_bb = self.bb_manager.get_all_pieces_bb_off_mut(self.active_color);
+        friendly_bb.clear_square(king_from);
+        friendly_bb.clear_square(rook_from);
+        friendly_bb.fill_square(king_to);
+        friendly_bb.fill_square(rook_to);
     }
 
     fn make_move_castling_rights_on_rook_move_or_capture(
@@ -221,24 +474,63 @@ impl State {
     ) {
         if piece_type == Rook {
             for castling_type in CastleType::get_all_types() {
-                let starting_square = Self::get_rook_starting_square(castling_type, relevant_side);
+                let starting_square = self
+                    .castling_config
+                    .get_rook_square(relevant_side, castling_type);
                 if relevant_square == starting_square {
                     irreversible_data.remove_castle_rights(relevant_side, castling_type);
                 }
             }
         }
     }
+}
 
-    fn get_rook_starting_square(castle_type: CastleType, color: Side) -> Square {
-        match castle_type {
-            CastleType::Long => match color {
-                Side::White => A1,
-                Side::Black => A8,
-            },
-            CastleType::Short => match color {
-                Side::White => H1,
-                Side::Black => H8,
-            },
-        }
+/// Two states are equal if they describe the same position: the same pieces, side to move, castling rights and
+/// en passant square. Like in the zobrist key, the en passant square only counts if it can be captured on.
+/// The clocks and the last captured piece are ignored, so transpositions are equal.
+impl PartialEq for State {
+    fn eq(&self, other: &Self) -> bool {
+        let (ir_data, other_ir_data) = (&self.irreversible_data, &other.irreversible_data);
+        let is_same_castling = Side::get_all_colors().into_iter().all(|side| {
+            CastleType::get_all_types().into_iter().all(|castle_type| {
+                let castling_rights = ir_data.get_castle_rights(side, castle_type);
+                let other_castling_rights = other_ir_data.get_castle_rights(side, castle_type);
+                // Where the rook started only matters as long as it may still castle.
+                castling_rights == other_castling_rights
+                    && (!castling_rights
+                        || self.castling_config.get_rook_square(side, castle_type)
+                            == other.castling_config.get_rook_square(side, castle_type))
+            })
+        });
+
+        self.bb_manager == other.bb_manager
+            && self.active_color == other.active_color
+            && self.get_capturable_en_passant_square() == other.get_capturable_en_passant_square()
+            && is_same_castling
+    }
+}
+
+impl Eq for State {}
+
+/// Hashes the state by its zobrist key. This allows using positions as keys in maps.
+/// Equal states always have the same key, different states only share one on a collision.
+impl Hash for State {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        hasher.write_u64(self.zobrist_key);
+    }
+}
+
+impl FromStr for State {
+    type Err = FenError;
+
+    fn from_str(fen_string: &str) -> Result<Self, Self::Err> {
+        State::try_from_fen(fen_string)
+    }
+}
+
+/// Displays the state as its fen string.
+impl Display for State {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_fen())
     }
 }
@@ -0,0 +1,72 @@
+use crate::backend::caches::{
+    ZOBRIST_CASTLING_KEYS, ZOBRIST_EN_PASSANT_FILE_KEYS, ZOBRIST_PIECE_SQUARE_KEYS,
+    ZOBRIST_SIDE_KEY,
+};
+use crate::backend::game_state::irreversible_data::IrreversibleData;
+use crate::backend::game_state::state::State;
+use crate::backend::types::piece::{ALL_PIECES, Piece, Side};
+use crate::backend::types::square::{Square, get_file};
+
+// Zobrist hashing: https://www.chessprogramming.org/Zobrist_Hashing
+// Every feature of a position (a piece on a square, a castling right, ...) has a random key.
+// The key of a position is the xor of the keys of all its features.
+// Since xor is its own inverse, the key can be updated incrementally when making a move.
+
+/// Returns the key of a piece of the given side standing on the given square.
+pub fn get_piece_key(piece: Piece, side: Side, square: Square) -> u64 {
+    ZOBRIST_PIECE_SQUARE_KEYS[side as usize][piece as usize][square as usize]
+}
+
+/// Returns the key that is xor-ed in if black is to move.
+pub fn get_side_key() -> u64 {
+    ZOBRIST_SIDE_KEY
+}
+
+/// Returns the combined key of all castling rights that are still available.
+pub fn get_castling_key(irreversible_data: &IrreversibleData) -> u64 {
+    let castling_rights = [
+        irreversible_data.white_long_castle_rights,
+        irreversible_data.white_short_castle_rights,
+        irreversible_data.black_long_castle_rights,
+        irreversible_data.black_short_castle_rights,
+    ];
+
+    let mut key = 0;
+    for (index, castling_right) in castling_rights.iter().enumerate() {
+        if *castling_right {
+            key ^= ZOBRIST_CASTLING_KEYS[index];
+        }
+    }
+    key
+}
+
+/// Returns the key of the en passant file or 0 if there is no en passant square the side to move can capture on.
+/// Otherwise, a position reached by a double push would never match the same position reached any other way.
+pub fn get_en_passant_key(state: &State) -> u64 {
+    match state.get_capturable_en_passant_square() {
+        None => 0,
+        Some(square) => ZOBRIST_EN_PASSANT_FILE_KEYS[get_file(square) as usize],
+    }
+}
+
+/// Computes the key of the position from scratch.
+/// `State` keeps its key up to date incrementally, this is used for the initial key and to verify it.
+pub fn compute_zobrist_key(state: &State) -> u64 {
+    let mut key = 0;
+
+    for side in Side::get_all_colors() {
+        for piece in ALL_PIECES {
+            for square in state.bb_manager.get_colored_piece_bb(piece, side) {
+                key ^= get_piece_key(piece, side, square);
+            }
+        }
+    }
+
+    if state.active_color == Side::Black {
+        key ^= get_side_key();
+    }
+
+    key ^= get_castling_key(&state.irreversible_data);
+    key ^= get_en_passant_key(state);
+    key
+}
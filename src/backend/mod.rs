@@ -1,8 +1,12 @@
 pub(crate) mod caches;
 pub mod constants;
+pub(crate) mod eval;
 pub mod perft;
+pub mod perft_bisect;
 pub(crate) mod movegen;
 pub(crate) mod game_state;
+pub(crate) mod notation;
+pub(crate) mod search;
 pub(crate) mod types;
 
 
@@ -3,37 +3,12 @@ use crate::backend::movegen::move_gen_sliders::get_slider_moves_at_square;
 use crate::backend::types::bitboard::BitBoard;
 use crate::backend::game_state::state::State;
 use crate::backend::types::piece::Piece::{Bishop, Queen, Rook};
-use crate::backend::types::piece::{ALL_PIECES, Piece, Side};
+use crate::backend::types::piece::{Piece, Side};
 use crate::backend::types::square::Square;
 
+/// Checks if the king of `color` would be in check if it stood on `king_square`.
 pub fn is_in_check_on_square(state: &State, color: Side, king_square: Square) -> bool {
-    let friendly_bb = state.bb_manager.get_all_pieces_bb_off(color);
-    let enemy_bb = state
-        .bb_manager
-        .get_all_pieces_bb_off(color.opposite());
-
-    // Iterate over all pieces. Let`s assume we are checking for knights.
-    for piece_type in ALL_PIECES {
-        // Get the bitboard that represents all possible attacks.
-        let attack_bb = get_attack_bitboard_for_piece_and_square(
-            piece_type,
-            color,
-            king_square,
-            friendly_bb,
-            enemy_bb,
-        );
-
-        // Get the bitboard that marks where enemy knights are standing.
-        let enemy_piece_bb = state.bb_manager.get_piece_bb(piece_type) & enemy_bb;
-
-        // Check if at least one of the places we could move to contains an enemy knight.
-        let resulting_bb = attack_bb & enemy_piece_bb;
-        // If so, we know that the king is in check.
-        if resulting_bb.is_not_empty() {
-            return true;
-        }
-    }
-    false
+    is_square_attacked(state, king_square, color.opposite())
 }
 
 /// Checks if a given player's king is in check in the current game game_state.
@@ -71,26 +46,71 @@ pub fn is_in_check(state: &State, color: Side) -> bool {
 }
 
 /// Returns the square where the king of the respective side is located.
-fn get_kings_square(state: &State, color: Side) -> Square {
+pub(crate) fn get_kings_square(state: &State, color: Side) -> Square {
     let king_bb = state.bb_manager.get_piece_bb(Piece::King);
     let side_bb = state.bb_manager.get_all_pieces_bb_off(color);
     let mut bb = king_bb & side_bb;
     bb.next().unwrap()
 }
 
-fn get_attack_bitboard_for_piece_and_square(
-    piece_type: Piece,
-    side: Side,
+/// Returns a bitboard with all pieces - of both sides - that attack the given square.
+/// Sliders are blocked by the pieces in `occupancy_bb` instead of the real occupancy.
+/// This allows asking "what if" questions, e.g. what would attack this square if the king was not standing here.
+pub(crate) fn get_attackers_to_square(
+    state: &State,
     square: Square,
-    friendly_bb: BitBoard,
-    enemy_bb: BitBoard,
+    occupancy_bb: BitBoard,
 ) -> BitBoard {
-    match piece_type {
-        Piece::King => KING_MOVES[square as usize],
-        Piece::Knight => KNIGHT_MOVES[square as usize],
-        Piece::Pawn => PAWN_CAPTURE_MOVES[side as usize][square as usize],
-        Rook => get_slider_moves_at_square(Rook, square, friendly_bb, enemy_bb),
-        Bishop => get_slider_moves_at_square(Bishop, square, friendly_bb, enemy_bb),
-        Queen => get_slider_moves_at_square(Queen, square, friendly_bb, enemy_bb),
+    let bb_manager = &state.bb_manager;
+
+    // Same idea as in `is_in_check`: Pretend that the square contains a piece and check what it could attack.
+    let straight_sliders_bb = bb_manager.get_piece_bb(Rook) | bb_manager.get_piece_bb(Queen);
+    let diagonal_sliders_bb = bb_manager.get_piece_bb(Bishop) | bb_manager.get_piece_bb(Queen);
+
+    let mut attackers_bb = KING_MOVES[square as usize] & bb_manager.get_piece_bb(Piece::King);
+    attackers_bb |= KNIGHT_MOVES[square as usize] & bb_manager.get_piece_bb(Piece::Knight);
+    // A white pawn on the square would attack exactly the squares black pawns attack it from and vice versa.
+    attackers_bb |= PAWN_CAPTURE_MOVES[Side::White as usize][square as usize]
+        & bb_manager.get_colored_piece_bb(Piece::Pawn, Side::Black);
+    attackers_bb |= PAWN_CAPTURE_MOVES[Side::Black as usize][square as usize]
+        & bb_manager.get_colored_piece_bb(Piece::Pawn, Side::White);
+    attackers_bb |= get_slider_moves_at_square(Rook, square, BitBoard::new(), occupancy_bb)
+        & straight_sliders_bb;
+    attackers_bb |= get_slider_moves_at_square(Bishop, square, BitBoard::new(), occupancy_bb)
+        & diagonal_sliders_bb;
+
+    attackers_bb
+}
+
+/// Checks if any piece of `by_side` attacks the given square.
+pub(crate) fn is_square_attacked(state: &State, square: Square, by_side: Side) -> bool {
+    let attackers_bb = get_attackers_to_square(state, square, state.bb_manager.get_all_pieces_bb());
+    (attackers_bb & state.bb_manager.get_all_pieces_bb_off(by_side)).is_not_empty()
+}
+
+/// Returns a bitboard with all squares that are attacked by at least one piece of `side`.
+/// Squares occupied by pieces of `side` count as attacked if another piece of `side` defends them.
+pub(crate) fn get_attacks_by_side(state: &State, side: Side) -> BitBoard {
+    let bb_manager = &state.bb_manager;
+    let occupancy_bb = bb_manager.get_all_pieces_bb();
+
+    let mut attacks_bb = BitBoard::new();
+    for square in bb_manager.get_colored_piece_bb(Piece::King, side) {
+        attacks_bb |= KING_MOVES[square as usize];
+    }
+    for square in bb_manager.get_colored_piece_bb(Piece::Knight, side) {
+        attacks_bb |= KNIGHT_MOVES[square as usize];
     }
+    for square in bb_manager.get_colored_piece_bb(Piece::Pawn, side) {
+        attacks_bb |= PAWN_CAPTURE_MOVES[side as usize][square as usize];
+    }
+    // Passing the whole occupancy as enemy pieces makes the sliders include the squares of the pieces they defend.
+    for piece_type in [Rook, Bishop, Queen] {
+        for square in bb_manager.get_colored_piece_bb(piece_type, side) {
+            attacks_bb |=
+                get_slider_moves_at_square(piece_type, square, BitBoard::new(), occupancy_bb);
+        }
+    }
+
+    attacks_bb
 }
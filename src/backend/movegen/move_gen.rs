@@ -1,13 +1,37 @@
-use crate::backend::caches::{KING_MOVES, KNIGHT_MOVES};
+use crate::backend::caches::{
+    KING_MOVES, KNIGHT_MOVES, PAWN_CAPTURE_MOVES, SQUARES_BETWEEN, SQUARES_LINE,
+};
 use crate::backend::constants::SQUARES_AMOUNT;
 use crate::backend::types::moove::Moove;
+use crate::backend::types::move_list::MoveList;
+use crate::backend::movegen::check_decider::{get_attackers_to_square, get_kings_square};
 use crate::backend::movegen::move_gen_king::gen_castles;
-use crate::backend::movegen::move_gen_pawn::gen_pawn_moves;
-use crate::backend::movegen::move_gen_sliders::get_slider_moves;
+use crate::backend::movegen::move_gen_pawn::{PROMOTION_RANKS_BB, gen_pawn_moves};
+use crate::backend::movegen::move_gen_sliders::{get_slider_moves, get_slider_moves_at_square};
 use crate::backend::types::bitboard::BitBoard;
 use crate::backend::game_state::state::State;
 use crate::backend::types::piece::Piece::*;
-use crate::backend::types::square::Square;
+use crate::backend::types::piece::{PROMOTABLE_PIECES, Piece};
+use crate::backend::types::square::{Square, back_by_one};
+
+// Used as the target mask whenever there is nothing to restrict.
+const ALL_SQUARES_BB: BitBoard = BitBoard { value: u64::MAX };
+const QUEEN_PROMOTION: [Piece; 1] = [Queen];
+const UNDER_PROMOTIONS: [Piece; 3] = [Rook, Knight, Bishop];
+
+/// The stages the legal move gen can be split into.
+/// `Captures` and `Quiets` together produce exactly the same moves as `All`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum MoveGenStage {
+    /// Every legal move.
+    All,
+    /// Captures (including en passant) and queen promotions. These are the moves a quiescence search looks at.
+    Captures,
+    /// Moves that capture nothing (including castles) and under promotions, even those that capture.
+    Quiets,
+    /// King moves and moves that capture the checker or block the check. Nothing if the king is not in check.
+    Evasions,
+}
 
 /// Generates and returns all the pseudo legal moves for the current player's pieces
 /// based on the provided game game_state. This is the entry point for the move generation.
@@ -19,15 +43,15 @@ use crate::backend::types::square::Square;
 ///
 /// # Returns
 ///
-/// * A `Vec<Moove>` containing all the computed pseudo legal moves for the current player's
+/// * A `MoveList` containing all the computed pseudo legal moves for the current player's
 ///   pieces.
-pub fn get_pseudo_legal_moves(state: &State) -> Vec<Moove> {
+pub fn get_pseudo_legal_moves(state: &State) -> MoveList {
     let friendly_pieces_bb = state.bb_manager.get_all_pieces_bb_off(state.active_color);
     let enemy_pieces_bb = state
         .bb_manager
         .get_all_pieces_bb_off(state.active_color.opposite());
 
-    let mut moves = Vec::with_capacity(50);
+    let mut moves = MoveList::new();
 
     // Move gen for king and knight (excluding castles)
     iterate_over_bitboard_for_non_slider(
@@ -53,42 +77,326 @@ pub fn get_pseudo_legal_moves(state: &State) -> Vec<Moove> {
     // Gen pawn moves, quiet, captures, double pushes
     gen_pawn_moves(
         &mut moves,
-        state,
+        state
+            .bb_manager
+            .get_colored_piece_bb(Pawn, state.active_color),
         friendly_pieces_bb,
         enemy_pieces_bb,
+        state.irreversible_data.en_passant_square,
         state.active_color,
+        ALL_SQUARES_BB,
+        &PROMOTABLE_PIECES,
     );
 
     // Gen queen, bishop and rook moves
-    get_slider_moves(
+    for piece_type in [Queen, Bishop, Rook] {
+        get_slider_moves(
+            &mut moves,
+            piece_type,
+            state
+                .bb_manager
+                .get_colored_piece_bb(piece_type, state.active_color),
+            friendly_pieces_bb,
+            enemy_pieces_bb,
+            ALL_SQUARES_BB,
+        );
+    }
+
+    moves
+}
+
+/// Generates and returns all the legal moves for the current player.
+///
+/// Instead of making every pseudo legal move and checking if the king is attacked afterward,
+/// this computes the checkers and pinned pieces up front:
+/// - If the king is in double check, only king moves are possible.
+/// - If the king is in single check, every other piece has to capture the checker or block the check.
+/// - A pinned piece may only move along the line between its king and the pinner.
+/// - The king may not move onto an attacked square. Sliders see through the king while checking this.
+/// - En passant is checked by removing both pawns from the board and looking for attackers on the king,
+///   which also catches the case where both pawns were shielding the king on the same rank.
+pub fn get_legal_moves(state: &State) -> MoveList {
+    let mut moves = MoveList::new();
+    gen_legal_moves(&mut moves, state, MoveGenStage::All);
+    moves
+}
+
+/// Generates and returns all legal captures and queen promotions. See `MoveGenStage::Captures`.
+pub fn get_legal_captures(state: &State) -> MoveList {
+    let mut moves = MoveList::new();
+    gen_legal_moves(&mut moves, state, MoveGenStage::Captures);
+    moves
+}
+
+/// Generates and returns all legal quiet moves and under promotions. See `MoveGenStage::Quiets`.
+pub fn get_legal_quiets(state: &State) -> MoveList {
+    let mut moves = MoveList::new();
+    gen_legal_moves(&mut moves, state, MoveGenStage::Quiets);
+    moves
+}
+
+/// Generates and returns all legal moves that get the king out of check. See `MoveGenStage::Evasions`.
+pub fn get_legal_evasions(state: &State) -> MoveList {
+    let mut moves = MoveList::new();
+    gen_legal_moves(&mut moves, state, MoveGenStage::Evasions);
+    moves
+}
+
+/// Checks if the side to move has a legal en passant capture.
+pub fn can_capture_en_passant(state: &State) -> bool {
+    if state.irreversible_data.en_passant_square.is_none() {
+        return false;
+    }
+    let active_color = state.active_color;
+    let enemy_pieces_bb = state
+        .bb_manager
+        .get_all_pieces_bb_off(active_color.opposite());
+    let mut moves = MoveList::new();
+    gen_legal_en_passant(
         &mut moves,
-        Queen,
-        state
-            .bb_manager
-            .get_colored_piece_bb(Queen, state.active_color),
-        friendly_pieces_bb,
+        state,
+        get_kings_square(state, active_color),
+        state.bb_manager.get_all_pieces_bb(),
         enemy_pieces_bb,
     );
-    get_slider_moves(
-        &mut moves,
-        Bishop,
-        state
-            .bb_manager
-            .get_colored_piece_bb(Bishop, state.active_color),
+    !moves.is_empty()
+}
+
+/// Appends the legal moves of the given stage to `moves`.
+pub fn gen_legal_moves(moves: &mut MoveList, state: &State, stage: MoveGenStage) {
+    let active_color = state.active_color;
+    let friendly_pieces_bb = state.bb_manager.get_all_pieces_bb_off(active_color);
+    let enemy_pieces_bb = state
+        .bb_manager
+        .get_all_pieces_bb_off(active_color.opposite());
+    let occupancy_bb = friendly_pieces_bb | enemy_pieces_bb;
+
+    let king_square = get_kings_square(state, active_color);
+    let checkers_bb = get_attackers_to_square(state, king_square, occupancy_bb) & enemy_pieces_bb;
+    // Without a check there is nothing to evade.
+    if stage == MoveGenStage::Evasions && checkers_bb.is_empty() {
+        return;
+    }
+    let pinned_bb = get_pinned_pieces(state, king_square, friendly_pieces_bb, enemy_pieces_bb);
+
+    // The squares pieces may move to in this stage. Pawns get their own mask, since promotions are split up.
+    let (stage_mask, pawn_stage_mask, promotion_pieces): (BitBoard, BitBoard, &[Piece]) =
+        match stage {
+            MoveGenStage::All => (ALL_SQUARES_BB, ALL_SQUARES_BB, &PROMOTABLE_PIECES),
+            MoveGenStage::Captures => (
+                enemy_pieces_bb,
+                enemy_pieces_bb | PROMOTION_RANKS_BB,
+                &QUEEN_PROMOTION,
+            ),
+            MoveGenStage::Quiets => (
+                !occupancy_bb,
+                !occupancy_bb | PROMOTION_RANKS_BB,
+                &UNDER_PROMOTIONS,
+            ),
+            MoveGenStage::Evasions => {
+                let evasion_mask = get_evasion_mask(king_square, checkers_bb);
+                (evasion_mask, evasion_mask, &PROMOTABLE_PIECES)
+            }
+        };
+    // The king escapes by moving away, so it is not bound to the evasion squares.
+    let king_stage_mask = match stage {
+        MoveGenStage::Evasions => ALL_SQUARES_BB,
+        _ => stage_mask,
+    };
+
+    gen_legal_king_moves(
+        moves,
+        state,
+        king_square,
         friendly_pieces_bb,
         enemy_pieces_bb,
+        king_stage_mask,
     );
-    get_slider_moves(
-        &mut moves,
-        Rook,
-        state
-            .bb_manager
-            .get_colored_piece_bb(Rook, state.active_color),
+
+    // In a double check, only the king can move.
+    if checkers_bb.count_ones() > 1 {
+        return;
+    }
+
+    // All squares a non king piece may move to.
+    let mut target_mask = !friendly_pieces_bb;
+    if checkers_bb.is_empty() {
+        // We can only castle if we are not in check.
+        if stage != MoveGenStage::Captures {
+            gen_castles(moves, state, occupancy_bb)
+        }
+    } else {
+        // In a single check, we need to capture the checker or block the check.
+        target_mask &= get_evasion_mask(king_square, checkers_bb);
+    }
+    let pawn_target_mask = target_mask & pawn_stage_mask;
+    target_mask &= stage_mask;
+
+    // Pinned knights can never move, since they always leave the line of the pin.
+    iterate_over_bitboard_for_non_slider(
+        moves,
+        KNIGHT_MOVES,
+        state.bb_manager.get_colored_piece_bb(Knight, active_color) & !pinned_bb,
+        !target_mask,
+    );
+
+    // Pawns that are not pinned only have to respect the target mask.
+    // The en passant capture is generated separately, since it is the only move that removes a piece
+    // from a square it did not move to.
+    let pawn_bb = state.bb_manager.get_colored_piece_bb(Pawn, active_color);
+    gen_pawn_moves(
+        moves,
+        pawn_bb & !pinned_bb,
         friendly_pieces_bb,
         enemy_pieces_bb,
+        None,
+        active_color,
+        pawn_target_mask,
+        promotion_pieces,
     );
+    for square in pawn_bb & pinned_bb {
+        gen_pawn_moves(
+            moves,
+            BitBoard::new_from_square(square),
+            friendly_pieces_bb,
+            enemy_pieces_bb,
+            None,
+            active_color,
+            pawn_target_mask & SQUARES_LINE[king_square as usize][square as usize],
+            promotion_pieces,
+        );
+    }
+    if stage != MoveGenStage::Quiets {
+        gen_legal_en_passant(moves, state, king_square, occupancy_bb, enemy_pieces_bb);
+    }
 
-    moves
+    // Same for the sliders, but pinned ones may still move along the pin.
+    for piece_type in [Queen, Bishop, Rook] {
+        let piece_bb = state
+            .bb_manager
+            .get_colored_piece_bb(piece_type, active_color);
+        get_slider_moves(
+            moves,
+            piece_type,
+            piece_bb & !pinned_bb,
+            friendly_pieces_bb,
+            enemy_pieces_bb,
+            target_mask,
+        );
+        for square in piece_bb & pinned_bb {
+            get_slider_moves(
+                moves,
+                piece_type,
+                BitBoard::new_from_square(square),
+                friendly_pieces_bb,
+                enemy_pieces_bb,
+                target_mask & SQUARES_LINE[king_square as usize][square as usize],
+            );
+        }
+    }
+}
+
+// ------------------------------------
+// Legal move gen helpers
+// ------------------------------------
+
+/// Returns the squares a piece other than the king can move to in order to capture the checker or block the check.
+/// In a double check, no such square exists.
+fn get_evasion_mask(king_square: Square, checkers_bb: BitBoard) -> BitBoard {
+    let mut checkers_iter_bb = checkers_bb;
+    match (checkers_iter_bb.next(), checkers_iter_bb.next()) {
+        (Some(checker_square), None) => {
+            SQUARES_BETWEEN[king_square as usize][checker_square as usize]
+                | BitBoard::new_from_square(checker_square)
+        }
+        _ => BitBoard::new(),
+    }
+}
+
+/// Returns a bitboard with all friendly pieces that are pinned to the king.
+fn get_pinned_pieces(
+    state: &State,
+    king_square: Square,
+    friendly_pieces_bb: BitBoard,
+    enemy_pieces_bb: BitBoard,
+) -> BitBoard {
+    let bb_manager = &state.bb_manager;
+    let occupancy_bb = friendly_pieces_bb | enemy_pieces_bb;
+
+    // Pretend the king is a slider that can look through its own pieces.
+    // Every enemy slider it can see this way is a potential pinner.
+    let straight_snipers_bb =
+        get_slider_moves_at_square(Rook, king_square, BitBoard::new(), enemy_pieces_bb)
+            & (bb_manager.get_piece_bb(Rook) | bb_manager.get_piece_bb(Queen));
+    let diagonal_snipers_bb =
+        get_slider_moves_at_square(Bishop, king_square, BitBoard::new(), enemy_pieces_bb)
+            & (bb_manager.get_piece_bb(Bishop) | bb_manager.get_piece_bb(Queen));
+
+    let mut pinned_bb = BitBoard::new();
+    for sniper_square in (straight_snipers_bb | diagonal_snipers_bb) & enemy_pieces_bb {
+        let blockers_bb =
+            SQUARES_BETWEEN[king_square as usize][sniper_square as usize] & occupancy_bb;
+        // If exactly one of our pieces stands between the sniper and the king, it is pinned.
+        if blockers_bb.count_ones() == 1 && (blockers_bb & friendly_pieces_bb).is_not_empty() {
+            pinned_bb |= blockers_bb;
+        }
+    }
+
+    pinned_bb
+}
+
+fn gen_legal_king_moves(
+    moves: &mut MoveList,
+    state: &State,
+    king_square: Square,
+    friendly_pieces_bb: BitBoard,
+    enemy_pieces_bb: BitBoard,
+    stage_mask: BitBoard,
+) {
+    // Remove the king from the board, so it does not block sliders that attack the squares behind it.
+    let occupancy_without_king_bb =
+        (friendly_pieces_bb | enemy_pieces_bb) & !BitBoard::new_from_square(king_square);
+
+    for to_square in KING_MOVES[king_square as usize] & !friendly_pieces_bb & stage_mask {
+        let attackers_bb =
+            get_attackers_to_square(state, to_square, occupancy_without_king_bb) & enemy_pieces_bb;
+        if attackers_bb.is_empty() {
+            moves.push(Moove::new(king_square, to_square));
+        }
+    }
+}
+
+fn gen_legal_en_passant(
+    moves: &mut MoveList,
+    state: &State,
+    king_square: Square,
+    occupancy_bb: BitBoard,
+    enemy_pieces_bb: BitBoard,
+) {
+    let Some(ep_square) = state.irreversible_data.en_passant_square else {
+        return;
+    };
+    let active_color = state.active_color;
+    let captured_square = back_by_one(ep_square, active_color);
+    let captured_bb = BitBoard::new_from_square(captured_square);
+
+    // Our pawns that attack the en passant square are exactly those an enemy pawn on it would attack.
+    let pawn_bb = PAWN_CAPTURE_MOVES[active_color.opposite() as usize][ep_square as usize]
+        & state.bb_manager.get_colored_piece_bb(Pawn, active_color);
+
+    for from_square in pawn_bb {
+        // The board after the capture.
+        let occupancy_after_bb =
+            (occupancy_bb ^ BitBoard::new_from_square(from_square) ^ captured_bb)
+                | BitBoard::new_from_square(ep_square);
+
+        let attackers_bb = get_attackers_to_square(state, king_square, occupancy_after_bb)
+            & enemy_pieces_bb
+            & !captured_bb;
+        if attackers_bb.is_empty() {
+            moves.push(Moove::new(from_square, ep_square));
+        }
+    }
 }
 
 // ------------------------------------
@@ -96,7 +404,7 @@ pub fn get_pseudo_legal_moves(state: &State) -> Vec<Moove> {
 // ------------------------------------
 
 pub(crate) fn iterate_over_bitboard_for_non_slider(
-    moves: &mut Vec<Moove>,
+    moves: &mut MoveList,
     moves_cache: [BitBoard; SQUARES_AMOUNT],
     piece_bb: BitBoard,
     mask_bitboard: BitBoard,
@@ -117,7 +425,7 @@ pub(crate) fn iterate_over_bitboard_for_non_slider(
     }
 }
 
-pub fn convert_bitboard_to_moves(moves: &mut Vec<Moove>, square: Square, moves_bitboard: BitBoard) {
+pub fn convert_bitboard_to_moves(moves: &mut MoveList, square: Square, moves_bitboard: BitBoard) {
     // generate all the moves
     for to_square in moves_bitboard {
         moves.push(Moove::new(square, to_square))
@@ -1,8 +1,7 @@
 use crate::backend::types::moove::Moove;
+use crate::backend::types::move_list::MoveList;
 use crate::backend::types::bitboard::BitBoard;
-use crate::backend::game_state::state::State;
-use crate::backend::types::piece::Piece::Pawn;
-use crate::backend::types::piece::{PROMOTABLE_PIECES, Side};
+use crate::backend::types::piece::{Piece, Side};
 use crate::backend::types::square::{Square, get_rank};
 use crate::backend::types::square::{get_file, square_from_rank_and_file};
 
@@ -15,7 +14,7 @@ const WHITE_PAWN_START_RANK_BB: BitBoard = BitBoard { value: 0xff00 };
 const BLACK_PAWN_START_RANK_BB: BitBoard = BitBoard {
     value: 0xff000000000000,
 };
-const PROMOTION_RANKS_BB: BitBoard = BitBoard {
+pub(crate) const PROMOTION_RANKS_BB: BitBoard = BitBoard {
     value: (BLACK_PROMOTION_RANK_BB.value | WHITE_PROMOTION_RANK_BB.value),
 };
 const LEFT_SIDE_BB: BitBoard = BitBoard {
@@ -25,15 +24,23 @@ const RIGHT_SIDE_BB: BitBoard = BitBoard {
     value: 0x8080808080808080,
 };
 
+/// Generates all pawn moves for the pawns in `pawn_bb`.
+/// Only moves that end on a square in `target_mask` are generated,
+/// which allows the legal move gen to restrict pinned pawns and to only generate check evasions.
+/// The en passant capture is only generated if an `en_passant_square` is passed.
+/// Promotions are only generated to the pieces in `promotion_pieces`, which allows splitting them between stages.
+#[allow(clippy::too_many_arguments)]
 pub fn gen_pawn_moves(
-    moves: &mut Vec<Moove>,
-    state: &State,
+    moves: &mut MoveList,
+    pawn_bb: BitBoard,
     friendly_pieces_bb: BitBoard,
     enemy_pieces_bb: BitBoard,
+    en_passant_square: Option<Square>,
     active_color: Side,
+    target_mask: BitBoard,
+    promotion_pieces: &[Piece],
 ) {
     let occupancy_bb = friendly_pieces_bb | enemy_pieces_bb;
-    let pawn_bb = state.bb_manager.get_colored_piece_bb(Pawn, active_color);
 
     let rank_offset = match active_color {
         Side::White => -1,
@@ -41,18 +48,34 @@ pub fn gen_pawn_moves(
     };
 
     // single push
-    single_push(moves, active_color, occupancy_bb, pawn_bb, rank_offset);
+    single_push(
+        moves,
+        active_color,
+        occupancy_bb,
+        pawn_bb,
+        rank_offset,
+        target_mask,
+        promotion_pieces,
+    );
 
     // double push
-    double_push(moves, active_color, occupancy_bb, pawn_bb, rank_offset);
+    double_push(
+        moves,
+        active_color,
+        occupancy_bb,
+        pawn_bb,
+        rank_offset,
+        target_mask,
+    );
 
     let mut possible_captures_bb = enemy_pieces_bb;
-    match state.irreversible_data.en_passant_square {
+    match en_passant_square {
         None => {}
         Some(square) => {
             possible_captures_bb.fill_square(square);
         }
     }
+    possible_captures_bb &= target_mask;
     // left captures
     let shift = match active_color {
         Side::White => 7,
@@ -66,6 +89,7 @@ pub fn gen_pawn_moves(
         shift,
         LEFT_SIDE_BB,
         1,
+        promotion_pieces,
     );
 
     // right captures
@@ -81,15 +105,18 @@ pub fn gen_pawn_moves(
         shift,
         RIGHT_SIDE_BB,
         -1,
+        promotion_pieces,
     );
 }
 
 fn single_push(
-    moves: &mut Vec<Moove>,
+    moves: &mut MoveList,
     active_color: Side,
     occupancy_bb: BitBoard,
     pawn_bb: BitBoard,
     rank_offset: i8,
+    target_mask: BitBoard,
+    promotion_pieces: &[Piece],
 ) {
     let mut push_pawn_bb = match active_color {
         Side::White => pawn_bb << 8,
@@ -97,22 +124,30 @@ fn single_push(
     };
     // cant go there if something is there
     push_pawn_bb &= !occupancy_bb;
+    push_pawn_bb &= target_mask;
 
     let no_promotion_push_pawn_bb = push_pawn_bb & !PROMOTION_RANKS_BB;
     pawn_bb_to_moves_no_promotion(moves, no_promotion_push_pawn_bb, 0, rank_offset);
 
     let promotion_push_pawn_bb = push_pawn_bb & PROMOTION_RANKS_BB;
-    pawn_bb_to_moves_promotion(moves, promotion_push_pawn_bb, 0, rank_offset);
+    pawn_bb_to_moves_promotion(
+        moves,
+        promotion_push_pawn_bb,
+        0,
+        rank_offset,
+        promotion_pieces,
+    );
 }
 
 fn double_push(
-    moves: &mut Vec<Moove>,
+    moves: &mut MoveList,
     active_color: Side,
     occupancy_bb: BitBoard,
     pawn_bb: BitBoard,
     rank_offset: i8,
+    target_mask: BitBoard,
 ) {
-    let double_push_bb = match active_color {
+    let mut double_push_bb = match active_color {
         Side::White => {
             (((pawn_bb & WHITE_PAWN_START_RANK_BB) << 8) & !occupancy_bb) << 8 & !occupancy_bb
         }
@@ -120,17 +155,20 @@ fn double_push(
             (((pawn_bb & BLACK_PAWN_START_RANK_BB) >> 8) & !occupancy_bb) >> 8 & !occupancy_bb
         }
     };
+    double_push_bb &= target_mask;
     pawn_bb_to_moves_no_promotion(moves, double_push_bb, 0, 2 * rank_offset);
 }
 
+#[allow(clippy::too_many_arguments)]
 fn one_dir_capture(
-    moves: &mut Vec<Moove>,
+    moves: &mut MoveList,
     enemy_pieces_bb: BitBoard,
     mut pawn_bb: BitBoard,
     rank_offset: i8,
     shift: i32,
     mask: BitBoard,
     file_offset: i8,
+    promotion_pieces: &[Piece],
 ) {
     pawn_bb &= !mask;
 
@@ -145,11 +183,17 @@ fn one_dir_capture(
     pawn_bb_to_moves_no_promotion(moves, capture_no_promotion, file_offset, rank_offset);
 
     let captures_promotion = capture_bb & PROMOTION_RANKS_BB;
-    pawn_bb_to_moves_promotion(moves, captures_promotion, file_offset, rank_offset);
+    pawn_bb_to_moves_promotion(
+        moves,
+        captures_promotion,
+        file_offset,
+        rank_offset,
+        promotion_pieces,
+    );
 }
 
 fn pawn_bb_to_moves_no_promotion(
-    moves: &mut Vec<Moove>,
+    moves: &mut MoveList,
     pawn_bb: BitBoard,
     file_offset: i8,
     rank_offset: i8,
@@ -162,16 +206,17 @@ fn pawn_bb_to_moves_no_promotion(
 }
 
 fn pawn_bb_to_moves_promotion(
-    moves: &mut Vec<Moove>,
+    moves: &mut MoveList,
     pawn_bb: BitBoard,
     file_offset: i8,
     rank_offset: i8,
+    promotion_pieces: &[Piece],
 ) {
     for square in pawn_bb {
         let file = get_file(square);
         let rank = get_rank(square);
         let offset_square = square_from_rank_and_file(rank + rank_offset, file + file_offset);
-        for piece_type in PROMOTABLE_PIECES {
+        for &piece_type in promotion_pieces {
             let moove = Moove::new_promotion(offset_square, square, piece_type);
             moves.push(moove);
         }
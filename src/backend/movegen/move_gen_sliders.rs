@@ -1,23 +1,31 @@
+use crate::backend::caches::{BISHOP_PEXT_INDEX, BISHOP_PEXT_MASK, ROOK_PEXT_INDEX, ROOK_PEXT_MASK};
+#[cfg(pext)]
+use crate::backend::caches::PEXT_TABLE;
+#[cfg(not(pext))]
 use crate::backend::caches::{
-    BISHOP_PEXT_INDEX, BISHOP_PEXT_MASK, PEXT_TABLE, ROOK_PEXT_INDEX, ROOK_PEXT_MASK,
+    BISHOP_MAGIC_SHIFTS, BISHOP_MAGICS, MAGIC_TABLE, ROOK_MAGIC_SHIFTS, ROOK_MAGICS,
 };
-use crate::backend::types::moove::Moove;
+use crate::backend::types::move_list::MoveList;
 use crate::backend::movegen::move_gen::convert_bitboard_to_moves;
 use crate::backend::types::bitboard::BitBoard;
 use crate::backend::types::piece::Piece;
 use crate::backend::types::square::Square;
+#[cfg(pext)]
 use std::arch::x86_64::_pext_u64;
 
+/// Generates the moves for all sliders in `piece_bb` that end on a square in `target_mask`.
 pub fn get_slider_moves(
-    moves: &mut Vec<Moove>,
+    moves: &mut MoveList,
     piece_type: Piece,
     piece_bb: BitBoard,
     friendly_pieces_bb: BitBoard,
     enemy_pieces_bb: BitBoard,
+    target_mask: BitBoard,
 ) {
     for square in piece_bb {
         let moves_for_piece_bb =
-            get_slider_moves_at_square(piece_type, square, friendly_pieces_bb, enemy_pieces_bb);
+            get_slider_moves_at_square(piece_type, square, friendly_pieces_bb, enemy_pieces_bb)
+                & target_mask;
 
         convert_bitboard_to_moves(moves, square, moves_for_piece_bb);
     }
@@ -41,14 +49,9 @@ pub fn get_slider_moves_at_square(
 }
 
 fn get_rook_moves_at_square(square: Square, friendly_bb: BitBoard, enemy_bb: BitBoard) -> BitBoard {
-    let pext_mask = ROOK_PEXT_MASK[square as usize];
-    let pext_index = ROOK_PEXT_INDEX[square as usize];
-
     let occ_bb = friendly_bb | enemy_bb;
 
-    let blockers_index: usize = unsafe { _pext_u64(occ_bb.value, pext_mask.value) as usize };
-
-    let moves = PEXT_TABLE[pext_index + blockers_index];
+    let moves = lookup_rook_moves(square as usize, occ_bb);
     moves & !friendly_bb
 }
 
@@ -57,13 +60,69 @@ fn get_bishop_moves_at_square(
     friendly_bb: BitBoard,
     enemy_bb: BitBoard,
 ) -> BitBoard {
-    let pext_mask = BISHOP_PEXT_MASK[square as usize];
-    let pext_index = BISHOP_PEXT_INDEX[square as usize];
-
     let occ_bb = friendly_bb | enemy_bb;
 
+    let moves = lookup_bishop_moves(square as usize, occ_bb);
+    moves & !friendly_bb
+}
+
+// ----------------------------------------
+// PEXT BACKEND
+// ----------------------------------------
+// Used on x86_64, unless the `magic` feature is enabled. The `pext` cfg is set by the build script.
+
+#[cfg(pext)]
+fn lookup_rook_moves(square: usize, occ_bb: BitBoard) -> BitBoard {
+    let pext_mask = ROOK_PEXT_MASK[square];
+    let pext_index = ROOK_PEXT_INDEX[square];
+
     let blockers_index: usize = unsafe { _pext_u64(occ_bb.value, pext_mask.value) as usize };
 
-    let moves = PEXT_TABLE[pext_index + blockers_index];
-    moves & !friendly_bb
+    PEXT_TABLE[pext_index + blockers_index]
+}
+
+#[cfg(pext)]
+fn lookup_bishop_moves(square: usize, occ_bb: BitBoard) -> BitBoard {
+    let pext_mask = BISHOP_PEXT_MASK[square];
+    let pext_index = BISHOP_PEXT_INDEX[square];
+
+    let blockers_index: usize = unsafe { _pext_u64(occ_bb.value, pext_mask.value) as usize };
+
+    PEXT_TABLE[pext_index + blockers_index]
+}
+
+// ----------------------------------------
+// MAGIC BACKEND
+// ----------------------------------------
+// Used on every other architecture and when the `magic` feature is enabled.
+// It shares the masks and table offsets with the PEXT backend.
+
+#[cfg(not(pext))]
+fn lookup_rook_moves(square: usize, occ_bb: BitBoard) -> BitBoard {
+    let blockers_index = get_magic_index(
+        occ_bb,
+        ROOK_PEXT_MASK[square],
+        ROOK_MAGICS[square],
+        ROOK_MAGIC_SHIFTS[square],
+    );
+
+    MAGIC_TABLE[ROOK_PEXT_INDEX[square] + blockers_index]
+}
+
+#[cfg(not(pext))]
+fn lookup_bishop_moves(square: usize, occ_bb: BitBoard) -> BitBoard {
+    let blockers_index = get_magic_index(
+        occ_bb,
+        BISHOP_PEXT_MASK[square],
+        BISHOP_MAGICS[square],
+        BISHOP_MAGIC_SHIFTS[square],
+    );
+
+    MAGIC_TABLE[BISHOP_PEXT_INDEX[square] + blockers_index]
+}
+
+#[cfg(not(pext))]
+fn get_magic_index(occ_bb: BitBoard, mask: BitBoard, magic: u64, shift: u8) -> usize {
+    let blockers = occ_bb.value & mask.value;
+    (blockers.wrapping_mul(magic) >> shift) as usize
 }
@@ -0,0 +1,411 @@
+use crate::backend::game_state::fen_parser::{FenError, generate_epd_position};
+use crate::backend::game_state::state::State;
+use crate::backend::notation::san::{SanError, parse_san, to_san};
+use crate::backend::notation::uci::parse_uci;
+use crate::backend::types::moove::Moove;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+// Extended Position Description: https://www.chessprogramming.org/Extended_Position_Description
+
+/// The standard allows opcodes of up to 15 characters.
+const MAX_OPCODE_LENGTH: usize = 15;
+
+/// A position with operations, e.g. `r1b1k2r/... w kq - bm Qxf7+; id "WAC.005";`.
+#[derive(Clone, Debug)]
+pub struct Epd {
+    /// The clocks are not part of an epd, so they are 0 and 1.
+    pub state: State,
+    /// The operations in the order they appeared.
+    pub operations: Vec<EpdOperation>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EpdOperation {
+    /// `bm`: One of these moves has to be found.
+    BestMoves(Vec<Moove>),
+    /// `am`: None of these moves may be played.
+    AvoidMoves(Vec<Moove>),
+    /// `id`: The name of the position, e.g. `WAC.001`.
+    Id(String),
+    /// `c0` to `c9`: A comment and its number.
+    Comment(u8, String),
+    /// `ce`: The evaluation in centipawns from the view of the side to move.
+    CentipawnEvaluation(i32),
+    /// `pv`: The principal variation, starting in this position.
+    PrincipalVariation(Vec<Moove>),
+    /// `acd`: The depth of the analysis.
+    AnalysisCountDepth(u32),
+    /// Any other opcode. The operands are kept exactly as they appeared, strings still in quotes,
+    /// so they survive a round trip.
+    Other {
+        opcode: String,
+        operands: Vec<String>,
+    },
+}
+
+impl EpdOperation {
+    pub fn get_opcode(&self) -> String {
+        match self {
+            EpdOperation::BestMoves(_) => "bm".to_string(),
+            EpdOperation::AvoidMoves(_) => "am".to_string(),
+            EpdOperation::Id(_) => "id".to_string(),
+            EpdOperation::Comment(number, _) => format!("c{}", number),
+            EpdOperation::CentipawnEvaluation(_) => "ce".to_string(),
+            EpdOperation::PrincipalVariation(_) => "pv".to_string(),
+            EpdOperation::AnalysisCountDepth(_) => "acd".to_string(),
+            EpdOperation::Other { opcode, .. } => opcode.clone(),
+        }
+    }
+
+    /// Returns the operands as they are written in an epd. Moves are written in san.
+    fn get_operands(&self, state: &State) -> Vec<String> {
+        match self {
+            EpdOperation::BestMoves(moves) | EpdOperation::AvoidMoves(moves) => {
+                moves.iter().map(|moove| to_san(state, *moove)).collect()
+            }
+            EpdOperation::Id(string) | EpdOperation::Comment(_, string) => {
+                vec![format!("\"{}\"", string)]
+            }
+            EpdOperation::CentipawnEvaluation(score) => vec![score.to_string()],
+            EpdOperation::PrincipalVariation(moves) => {
+                let mut state = state.clone();
+                moves
+                    .iter()
+                    .map(|moove| {
+                        let san = to_san(&state, *moove);
+                        state.make_move(*moove);
+                        san
+                    })
+                    .collect()
+            }
+            EpdOperation::AnalysisCountDepth(depth) => vec![depth.to_string()],
+            EpdOperation::Other { operands, .. } => operands.clone(),
+        }
+    }
+}
+
+/// Describes why an epd could not be parsed.
+/// The position is the character index of the operation the error is about, starting at 0.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EpdError {
+    pub position: usize,
+    pub kind: EpdErrorKind,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum EpdErrorKind {
+    /// The four position fields are not valid. The fen error has its own position.
+    InvalidPosition(FenError),
+    /// An opcode has to start with a letter and consist of at most 15 letters, digits and underscores.
+    InvalidOpcode(String),
+    /// Every opcode may appear only once.
+    DuplicateOpcode(String),
+    /// Every operation has to end with a `;`.
+    MissingSemicolon(String),
+    /// A string operand is never closed.
+    UnterminatedString,
+    /// The opcode does not take this many operands.
+    InvalidOperandCount { opcode: String, count: usize },
+    /// The operand of `ce` or `acd` is not a valid number.
+    InvalidNumber { opcode: String, operand: String },
+    /// A move of `bm`, `am` or `pv` can't be played.
+    IllegalMove { opcode: String, error: SanError },
+}
+
+impl Display for EpdError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // A fen error already says where it happened.
+        if !matches!(self.kind, EpdErrorKind::InvalidPosition(_)) {
+            write!(f, "at position {}: ", self.position)?;
+        }
+        match &self.kind {
+            EpdErrorKind::InvalidPosition(error) => write!(f, "invalid position: {}", error),
+            EpdErrorKind::InvalidOpcode(opcode) => write!(f, "{:?} is not a valid opcode", opcode),
+            EpdErrorKind::DuplicateOpcode(opcode) => write!(f, "{} appears more than once", opcode),
+            EpdErrorKind::MissingSemicolon(opcode) => {
+                write!(f, "the {} operation does not end with a ';'", opcode)
+            }
+            EpdErrorKind::UnterminatedString => write!(f, "'\"' is never closed"),
+            EpdErrorKind::InvalidOperandCount { opcode, count } => {
+                write!(f, "{} does not take {} operands", opcode, count)
+            }
+            EpdErrorKind::InvalidNumber { opcode, operand } => {
+                write!(
+                    f,
+                    "the {} operand {:?} is not a valid number",
+                    opcode, operand
+                )
+            }
+            EpdErrorKind::IllegalMove { opcode, error } => write!(f, "{}: {}", opcode, error),
+        }
+    }
+}
+
+impl Error for EpdError {}
+
+impl Epd {
+    /// Creates an epd without operations.
+    pub fn new(state: State) -> Epd {
+        Epd {
+            state,
+            operations: Vec::new(),
+        }
+    }
+
+    /// Returns the first operation with the given opcode.
+    pub fn get_operation(&self, opcode: &str) -> Option<&EpdOperation> {
+        self.operations
+            .iter()
+            .find(|operation| operation.get_opcode() == opcode)
+    }
+
+    /// Returns the `id` of the position, if it has one.
+    pub fn get_id(&self) -> Option<&str> {
+        self.operations
+            .iter()
+            .find_map(|operation| match operation {
+                EpdOperation::Id(id) => Some(id.as_str()),
+                _ => None,
+            })
+    }
+
+    /// Serializes the epd. Moves are written in san, strings in quotes.
+    pub fn to_epd(&self) -> String {
+        let mut epd = generate_epd_position(&self.state);
+        for operation in &self.operations {
+            epd.push(' ');
+            epd.push_str(&operation.get_opcode());
+            for operand in operation.get_operands(&self.state) {
+                epd.push(' ');
+                epd.push_str(&operand);
+            }
+            epd.push(';');
+        }
+        epd
+    }
+}
+
+/// Displays the epd in the format `to_epd` writes.
+impl Display for Epd {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_epd())
+    }
+}
+
+/// Parses a single epd line. Moves may be given in san or uci notation.
+pub fn parse_epd(epd: &str) -> Result<Epd, EpdError> {
+    let chars: Vec<char> = epd.chars().collect();
+    let position_end = get_position_end(&chars);
+
+    let position: String = chars[..position_end].iter().collect();
+    let state = State::try_from_fen(&position).map_err(|error| EpdError {
+        position: 0,
+        kind: EpdErrorKind::InvalidPosition(error),
+    })?;
+
+    let mut epd = Epd::new(state);
+    let mut index = position_end;
+    while let Some((start, opcode, operands)) = read_operation(&chars, &mut index)? {
+        let error = |kind| EpdError {
+            position: start,
+            kind,
+        };
+        if epd.get_operation(&opcode).is_some() {
+            return Err(error(EpdErrorKind::DuplicateOpcode(opcode)));
+        }
+        let operation = parse_operation(&epd.state, opcode, operands).map_err(error)?;
+        epd.operations.push(operation);
+    }
+
+    Ok(epd)
+}
+
+/// Returns the index after the fourth whitespace separated field.
+fn get_position_end(chars: &[char]) -> usize {
+    let mut field_count = 0;
+    let mut is_in_field = false;
+    for (index, char) in chars.iter().enumerate() {
+        match (char.is_whitespace(), is_in_field) {
+            (false, false) => is_in_field = true,
+            (true, true) => {
+                is_in_field = false;
+                field_count += 1;
+                if field_count == 4 {
+                    return index;
+                }
+            }
+            _ => {}
+        }
+    }
+    chars.len()
+}
+
+/// Reads the next `opcode operand...;` and returns its start, opcode and operands.
+/// Returns `None` once only whitespace is left.
+fn read_operation(
+    chars: &[char],
+    index: &mut usize,
+) -> Result<Option<(usize, String, Vec<String>)>, EpdError> {
+    skip_whitespace(chars, index);
+    if *index >= chars.len() {
+        return Ok(None);
+    }
+
+    let start = *index;
+    let opcode = read_while(chars, index, |char| {
+        !char.is_whitespace() && char != ';' && char != '"'
+    });
+    let is_valid_opcode = opcode.len() <= MAX_OPCODE_LENGTH
+        && opcode.starts_with(|char: char| char.is_ascii_alphabetic())
+        && opcode
+            .chars()
+            .all(|char| char.is_ascii_alphanumeric() || char == '_');
+    if !is_valid_opcode {
+        return Err(EpdError {
+            position: start,
+            kind: EpdErrorKind::InvalidOpcode(opcode),
+        });
+    }
+
+    let mut operands = Vec::new();
+    loop {
+        skip_whitespace(chars, index);
+        match chars.get(*index) {
+            None => {
+                return Err(EpdError {
+                    position: start,
+                    kind: EpdErrorKind::MissingSemicolon(opcode),
+                });
+            }
+            Some(';') => {
+                *index += 1;
+                return Ok(Some((start, opcode, operands)));
+            }
+            Some('"') => {
+                let string_start = *index;
+                *index += 1;
+                let string = read_while(chars, index, |char| char != '"');
+                if *index >= chars.len() {
+                    return Err(EpdError {
+                        position: string_start,
+                        kind: EpdErrorKind::UnterminatedString,
+                    });
+                }
+                *index += 1;
+                operands.push(format!("\"{}\"", string));
+            }
+            Some(_) => {
+                operands.push(read_while(chars, index, |char| {
+                    !char.is_whitespace() && char != ';'
+                }));
+            }
+        }
+    }
+}
+
+fn skip_whitespace(chars: &[char], index: &mut usize) {
+    read_while(chars, index, char::is_whitespace);
+}
+
+fn read_while(chars: &[char], index: &mut usize, condition: impl Fn(char) -> bool) -> String {
+    let start = *index;
+    while chars.get(*index).is_some_and(|&char| condition(char)) {
+        *index += 1;
+    }
+    chars[start..*index].iter().collect()
+}
+
+fn parse_operation(
+    state: &State,
+    opcode: String,
+    operands: Vec<String>,
+) -> Result<EpdOperation, EpdErrorKind> {
+    let comment_number = match opcode.as_bytes() {
+        [b'c', digit @ b'0'..=b'9'] => Some(digit - b'0'),
+        _ => None,
+    };
+    let operand_count_error = || EpdErrorKind::InvalidOperandCount {
+        opcode: opcode.clone(),
+        count: operands.len(),
+    };
+
+    let operation = match (opcode.as_str(), comment_number) {
+        ("bm" | "am", _) => {
+            if operands.is_empty() {
+                return Err(operand_count_error());
+            }
+            let moves = operands
+                .iter()
+                .map(|operand| parse_move(state, operand))
+                .collect::<Result<Vec<Moove>, SanError>>()
+                .map_err(|error| EpdErrorKind::IllegalMove {
+                    opcode: opcode.clone(),
+                    error,
+                })?;
+            if opcode == "bm" {
+                EpdOperation::BestMoves(moves)
+            } else {
+                EpdOperation::AvoidMoves(moves)
+            }
+        }
+        ("pv", _) => {
+            let mut state = state.clone();
+            let mut moves = Vec::new();
+            for operand in &operands {
+                let moove =
+                    parse_move(&state, operand).map_err(|error| EpdErrorKind::IllegalMove {
+                        opcode: opcode.clone(),
+                        error,
+                    })?;
+                state.make_move(moove);
+                moves.push(moove);
+            }
+            EpdOperation::PrincipalVariation(moves)
+        }
+        ("id", _) | (_, Some(_)) => {
+            let [operand] = operands.as_slice() else {
+                return Err(operand_count_error());
+            };
+            let string = unquote(operand).to_string();
+            match comment_number {
+                Some(number) => EpdOperation::Comment(number, string),
+                None => EpdOperation::Id(string),
+            }
+        }
+        ("ce", _) => EpdOperation::CentipawnEvaluation(parse_number(&opcode, &operands)?),
+        ("acd", _) => EpdOperation::AnalysisCountDepth(parse_number(&opcode, &operands)?),
+        _ => EpdOperation::Other { opcode, operands },
+    };
+    Ok(operation)
+}
+
+/// Parses a move in san, or in uci notation if that fails.
+fn parse_move(state: &State, operand: &str) -> Result<Moove, SanError> {
+    parse_san(state, operand).or_else(|error| {
+        parse_uci(state, operand).ok_or(error)
+    })
+}
+
+fn parse_number<T: std::str::FromStr>(
+    opcode: &str,
+    operands: &[String],
+) -> Result<T, EpdErrorKind> {
+    let [operand] = operands else {
+        return Err(EpdErrorKind::InvalidOperandCount {
+            opcode: opcode.to_string(),
+            count: operands.len(),
+        });
+    };
+    operand.parse().map_err(|_| EpdErrorKind::InvalidNumber {
+        opcode: opcode.to_string(),
+        operand: operand.clone(),
+    })
+}
+
+fn unquote(operand: &str) -> &str {
+    operand
+        .strip_prefix('"')
+        .and_then(|operand| operand.strip_suffix('"'))
+        .unwrap_or(operand)
+}
@@ -0,0 +1,651 @@
+use crate::backend::game_state::fen_parser::{FenError, STARTING_POSITION_FEN};
+use crate::backend::game_state::state::{State, Undo};
+use crate::backend::notation::san::{SanError, parse_san, to_san};
+use crate::backend::types::moove::Moove;
+use crate::backend::types::piece::Side;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+use std::io::BufRead;
+
+// Portable Game Notation: https://www.saremba.de/chessgml/standards/pgn/pgn-complete.htm
+
+/// The export format asks for lines of at most 79 characters.
+const MAX_LINE_LENGTH: usize = 79;
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+/// A single game: its tag pairs, the position it started from and the moves of the main line.
+/// Comments, NAGs and variations are kept as `annotations` of the move they follow.
+#[derive(Clone, Debug)]
+pub struct PgnGame {
+    /// The tag pairs in the order they appeared, e.g. `("White", "Carlsen, Magnus")`.
+    pub tags: Vec<(String, String)>,
+    pub starting_state: State,
+    pub moves: Vec<Moove>,
+    /// The annotations of `moves[i]` are at index `i`. Missing entries count as empty.
+    pub annotations: Vec<PgnAnnotations>,
+    /// Comments before the first move.
+    pub comments: Vec<String>,
+    /// One of `1-0`, `0-1`, `1/2-1/2` or `*` for unfinished games.
+    pub result: String,
+}
+
+/// A sequence of moves that branches off from another line. It is stored like the main line of a game.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PgnVariation {
+    pub moves: Vec<Moove>,
+    /// The annotations of `moves[i]` are at index `i`. Missing entries count as empty.
+    pub annotations: Vec<PgnAnnotations>,
+    /// Comments before the first move.
+    pub comments: Vec<String>,
+}
+
+/// Everything written after a move, apart from the move itself.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PgnAnnotations {
+    /// Numeric annotation glyphs, e.g. 6 for `$6`. The suffixes `!`, `?`, `!!`, `??`, `!?` and `?!` are stored as 1 to 6.
+    pub nags: Vec<u8>,
+    /// The comments following the move, without braces and with all whitespace collapsed to single spaces.
+    pub comments: Vec<String>,
+    /// Alternatives to the move. Each one starts from the position before the move.
+    pub variations: Vec<PgnVariation>,
+}
+
+/// Describes why a game could not be read. Lines and columns start at 1.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PgnError {
+    pub line: usize,
+    pub column: usize,
+    pub kind: PgnErrorKind,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum PgnErrorKind {
+    /// A tag pair is not of the form `[Name "Value"]`.
+    InvalidTag,
+    /// A string, comment or variation is never closed.
+    Unterminated(char),
+    /// A character that can't appear at this point, e.g. a `)` without a matching `(`.
+    UnexpectedCharacter(char),
+    /// The `FEN` tag does not contain a valid fen.
+    InvalidFen(FenError),
+    /// A move can't be played in the position it appears in, no matter if it is part of the main line or a variation.
+    IllegalMove(SanError),
+    /// Reading from the underlying reader failed.
+    Io(String),
+}
+
+impl Display for PgnError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            PgnErrorKind::InvalidTag => write!(f, "invalid tag pair"),
+            PgnErrorKind::Unterminated(char) => write!(f, "{:?} is never closed", char),
+            PgnErrorKind::UnexpectedCharacter(char) => write!(f, "unexpected {:?}", char),
+            PgnErrorKind::InvalidFen(error) => write!(f, "invalid FEN tag: {}", error),
+            PgnErrorKind::IllegalMove(error) => write!(f, "{}", error),
+            PgnErrorKind::Io(error) => write!(f, "could not read the input: {}", error),
+        }
+    }
+}
+
+impl Error for PgnError {}
+
+impl PgnGame {
+    /// Creates a game without tags and moves, starting from the given position.
+    pub fn new(starting_state: State) -> PgnGame {
+        PgnGame {
+            tags: Vec::new(),
+            starting_state,
+            moves: Vec::new(),
+            annotations: Vec::new(),
+            comments: Vec::new(),
+            result: "*".to_string(),
+        }
+    }
+
+    /// Returns the value of the first tag with the given name.
+    pub fn get_tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag_name, _)| tag_name == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Returns the position after all moves of the main line.
+    pub fn get_final_state(&self) -> State {
+        let mut state = self.starting_state.clone();
+        for moove in &self.moves {
+            state.make_move(*moove);
+        }
+        state
+    }
+
+    /// Serializes the game to pgn.
+    /// If the game does not start from the starting position, the `SetUp` and `FEN` tags are added if missing.
+    pub fn to_pgn(&self) -> String {
+        let mut pgn = String::new();
+
+        let mut tags = self.tags.clone();
+        let starting_fen = self.starting_state.to_fen();
+        if starting_fen != STARTING_POSITION_FEN && self.get_tag("FEN").is_none() {
+            tags.push(("SetUp".to_string(), "1".to_string()));
+            tags.push(("FEN".to_string(), starting_fen));
+        }
+        for (name, value) in &tags {
+            let escaped_value = value.replace('\\', "\\\\").replace('"', "\\\"");
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escaped_value));
+        }
+        if !tags.is_empty() {
+            pgn.push('\n');
+        }
+
+        let mut tokens = Vec::new();
+        push_line_tokens(
+            &mut tokens,
+            &self.starting_state,
+            &self.moves,
+            &self.annotations,
+            &self.comments,
+        );
+        tokens.push(self.result.clone());
+
+        let mut line_length = 0;
+        for token in tokens {
+            if line_length > 0 && line_length + 1 + token.len() > MAX_LINE_LENGTH {
+                pgn.push('\n');
+                line_length = 0;
+            } else if line_length > 0 {
+                pgn.push(' ');
+                line_length += 1;
+            }
+            pgn.push_str(&token);
+            line_length += token.len();
+        }
+        pgn.push('\n');
+
+        pgn
+    }
+}
+
+/// Appends the movetext of a line, including its annotations and nested variations, starting from `state`.
+fn push_line_tokens(
+    tokens: &mut Vec<String>,
+    state: &State,
+    moves: &[Moove],
+    annotations: &[PgnAnnotations],
+    comments: &[String],
+) {
+    push_comment_tokens(tokens, comments);
+
+    let mut state = state.clone();
+    // Black moves need a move number of their own at the start of a line and after comments and variations.
+    let mut needs_move_number = true;
+    for (index, moove) in moves.iter().enumerate() {
+        if state.active_color == Side::White {
+            tokens.push(format!("{}.", state.full_move_number));
+        } else if needs_move_number {
+            tokens.push(format!("{}...", state.full_move_number));
+        }
+        tokens.push(to_san(&state, *moove));
+        needs_move_number = false;
+
+        if let Some(annotations) = annotations.get(index) {
+            for nag in &annotations.nags {
+                tokens.push(format!("${}", nag));
+            }
+            push_comment_tokens(tokens, &annotations.comments);
+            // Variations replace the move, so they start from the position before it.
+            for variation in &annotations.variations {
+                let first_token = tokens.len();
+                push_line_tokens(
+                    tokens,
+                    &state,
+                    &variation.moves,
+                    &variation.annotations,
+                    &variation.comments,
+                );
+                if tokens.len() == first_token {
+                    tokens.push(String::new());
+                }
+                tokens[first_token].insert(0, '(');
+                tokens.last_mut().unwrap().push(')');
+            }
+            needs_move_number =
+                !annotations.comments.is_empty() || !annotations.variations.is_empty();
+        }
+        state.make_move(*moove);
+    }
+}
+
+/// Appends every word of the comments as its own token, so long comments can be wrapped like the moves.
+fn push_comment_tokens(tokens: &mut Vec<String>, comments: &[String]) {
+    for comment in comments {
+        let first_token = tokens.len();
+        tokens.extend(comment.split_whitespace().map(str::to_string));
+        if tokens.len() == first_token {
+            tokens.push(String::new());
+        }
+        tokens[first_token].insert(0, '{');
+        tokens.last_mut().unwrap().push('}');
+    }
+}
+
+/// Returns the NAG that a move suffix like `!?` stands for.
+fn get_suffix_nag(suffix: &str) -> Option<u8> {
+    match suffix {
+        "!" => Some(1),
+        "?" => Some(2),
+        "!!" => Some(3),
+        "??" => Some(4),
+        "!?" => Some(5),
+        "?!" => Some(6),
+        _ => None,
+    }
+}
+
+/// Displays the game as pgn.
+impl Display for PgnGame {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_pgn())
+    }
+}
+
+/// Reads all games from a string. Stops at the first error.
+pub fn parse_pgn(pgn: &str) -> Result<Vec<PgnGame>, PgnError> {
+    PgnReader::new(pgn.as_bytes()).collect()
+}
+
+/// Reads games one after the other from any `BufRead`, so even huge databases never have to fit into memory.
+/// After an error, the reader skips to the next game, so one broken game does not spoil the rest.
+pub struct PgnReader<R: BufRead> {
+    reader: R,
+    line: Vec<char>,
+    line_number: usize,
+    column_index: usize,
+    is_at_end: bool,
+    /// Whether the tag pairs of the current game were already read. Used to find the next game after an error.
+    is_in_movetext: bool,
+}
+
+impl<R: BufRead> PgnReader<R> {
+    pub fn new(reader: R) -> PgnReader<R> {
+        PgnReader {
+            reader,
+            line: Vec::new(),
+            line_number: 0,
+            column_index: 0,
+            is_at_end: false,
+            is_in_movetext: false,
+        }
+    }
+
+    // -------------------
+    // Character level
+    // -------------------
+
+    fn peek(&mut self) -> Result<Option<char>, PgnError> {
+        while self.column_index >= self.line.len() {
+            if self.is_at_end {
+                return Ok(None);
+            }
+            self.read_line()?;
+        }
+        Ok(Some(self.line[self.column_index]))
+    }
+
+    fn next_char(&mut self) -> Result<Option<char>, PgnError> {
+        let char = self.peek()?;
+        self.column_index += 1;
+        Ok(char)
+    }
+
+    fn read_line(&mut self) -> Result<(), PgnError> {
+        let mut line = String::new();
+        let bytes_read = self
+            .reader
+            .read_line(&mut line)
+            .map_err(|error| self.error(PgnErrorKind::Io(error.to_string())))?;
+        if bytes_read == 0 {
+            self.is_at_end = true;
+        }
+        self.line = line.chars().collect();
+        self.line_number += 1;
+        self.column_index = 0;
+        Ok(())
+    }
+
+    /// Returns the line and column of the next character.
+    fn get_position(&mut self) -> Result<(usize, usize), PgnError> {
+        self.peek()?;
+        Ok((self.line_number, self.column_index + 1))
+    }
+
+    fn error(&self, kind: PgnErrorKind) -> PgnError {
+        PgnError {
+            line: self.line_number,
+            column: self.column_index + 1,
+            kind,
+        }
+    }
+
+    fn error_at(position: (usize, usize), kind: PgnErrorKind) -> PgnError {
+        PgnError {
+            line: position.0,
+            column: position.1,
+            kind,
+        }
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), PgnError> {
+        while let Some(char) = self.peek()? {
+            if !char.is_whitespace() {
+                break;
+            }
+            self.column_index += 1;
+        }
+        Ok(())
+    }
+
+    fn skip_rest_of_line(&mut self) {
+        self.column_index = self.line.len();
+    }
+
+    /// Reads until whitespace or a character that has a meaning of its own.
+    fn read_symbol(&mut self) -> Result<String, PgnError> {
+        let mut symbol = String::new();
+        while let Some(char) = self.peek()? {
+            if char.is_whitespace() || "[]{}();$".contains(char) {
+                break;
+            }
+            symbol.push(char);
+            self.column_index += 1;
+        }
+        Ok(symbol)
+    }
+
+    // -------------------
+    // Game level
+    // -------------------
+
+    fn read_game(&mut self) -> Result<Option<PgnGame>, PgnError> {
+        self.skip_whitespace()?;
+        if self.peek()?.is_none() {
+            return Ok(None);
+        }
+
+        self.is_in_movetext = false;
+        let mut tags = Vec::new();
+        while self.peek()? == Some('[') {
+            tags.push(self.read_tag()?);
+            self.skip_whitespace()?;
+        }
+
+        let fen_tag = tags.iter().find(|(name, _)| name == "FEN");
+        let starting_state = match fen_tag {
+            None => State::new_from_fen(STARTING_POSITION_FEN),
+            Some((_, fen)) => State::try_from_fen(fen)
+                .map_err(|error| self.error(PgnErrorKind::InvalidFen(error)))?,
+        };
+
+        let mut game = PgnGame::new(starting_state);
+        game.tags = tags;
+        if let Some(result) = game.get_tag("Result") {
+            game.result = result.to_string();
+        }
+        self.read_movetext(&mut game)?;
+
+        Ok(Some(game))
+    }
+
+    fn read_tag(&mut self) -> Result<(String, String), PgnError> {
+        let tag_position = self.get_position()?;
+        let invalid_tag = || Self::error_at(tag_position, PgnErrorKind::InvalidTag);
+
+        // Skip the '['.
+        self.next_char()?;
+        self.skip_whitespace()?;
+        let name = self.read_symbol()?;
+        if name.is_empty() || name.contains('"') {
+            return Err(invalid_tag());
+        }
+
+        self.skip_whitespace()?;
+        if self.next_char()? != Some('"') {
+            return Err(invalid_tag());
+        }
+        let mut value = String::new();
+        loop {
+            // A string has to end on the line it started on.
+            if self.column_index >= self.line.len() {
+                return Err(Self::error_at(
+                    tag_position,
+                    PgnErrorKind::Unterminated('"'),
+                ));
+            }
+            match self.next_char()? {
+                Some('"') => break,
+                Some('\\') => match self.next_char()? {
+                    Some(char @ ('"' | '\\')) => value.push(char),
+                    _ => return Err(invalid_tag()),
+                },
+                Some(char) => value.push(char),
+                None => {
+                    return Err(Self::error_at(
+                        tag_position,
+                        PgnErrorKind::Unterminated('"'),
+                    ));
+                }
+            }
+        }
+
+        self.skip_whitespace()?;
+        if self.next_char()? != Some(']') {
+            return Err(invalid_tag());
+        }
+        Ok((name, value))
+    }
+
+    fn read_movetext(&mut self, game: &mut PgnGame) -> Result<(), PgnError> {
+        self.is_in_movetext = true;
+        let mut state = game.starting_state.clone();
+        let mut main_line = PgnVariation::default();
+
+        if let Some(result) = self.read_line_of_moves(&mut state, &mut main_line, None)? {
+            game.result = result;
+        }
+        game.moves = main_line.moves;
+        game.annotations = main_line.annotations;
+        game.comments = main_line.comments;
+        Ok(())
+    }
+
+    /// Reads moves, comments, NAGs and nested variations into `line`, playing the moves on `state`.
+    /// `opening_position` is where the `(` of a variation is, or `None` for the main line.
+    /// Returns the result if the main line ends with one.
+    fn read_line_of_moves(
+        &mut self,
+        state: &mut State,
+        line: &mut PgnVariation,
+        opening_position: Option<(usize, usize)>,
+    ) -> Result<Option<String>, PgnError> {
+        // The last move and what is needed to take it back. Variations start from the position before it.
+        let mut last_move: Option<(Moove, Undo)> = None;
+
+        loop {
+            self.skip_whitespace()?;
+            let position = self.get_position()?;
+            let char = self.peek()?;
+
+            // The end of the input or the next game end the main line, but not a variation.
+            if char.is_none() || char == Some('[') {
+                return match opening_position {
+                    None => Ok(None),
+                    Some(opening_position) => Err(Self::error_at(
+                        opening_position,
+                        PgnErrorKind::Unterminated('('),
+                    )),
+                };
+            }
+            let char = char.unwrap();
+
+            match char {
+                '{' => {
+                    let comment = self.read_comment()?;
+                    match line.annotations.last_mut() {
+                        Some(annotations) => annotations.comments.push(comment),
+                        None => line.comments.push(comment),
+                    }
+                }
+                ';' => {
+                    let comment = self.read_rest_of_line();
+                    match line.annotations.last_mut() {
+                        Some(annotations) => annotations.comments.push(comment),
+                        None => line.comments.push(comment),
+                    }
+                }
+                // Lines starting with '%' are escaped and must be ignored.
+                '%' if position.1 == 1 => self.skip_rest_of_line(),
+                // A variation is an alternative to the last move.
+                '(' => {
+                    let Some((moove, undo)) = last_move else {
+                        return Err(Self::error_at(
+                            position,
+                            PgnErrorKind::UnexpectedCharacter(char),
+                        ));
+                    };
+                    self.next_char()?;
+                    let mut variation_state = state.clone();
+                    variation_state.unmake_move(moove, undo);
+                    let mut variation = PgnVariation::default();
+                    self.read_line_of_moves(&mut variation_state, &mut variation, Some(position))?;
+                    line.annotations
+                        .last_mut()
+                        .unwrap()
+                        .variations
+                        .push(variation);
+                }
+                ')' if opening_position.is_some() => {
+                    self.next_char()?;
+                    return Ok(None);
+                }
+                ')' | ']' | '}' => {
+                    return Err(Self::error_at(
+                        position,
+                        PgnErrorKind::UnexpectedCharacter(char),
+                    ));
+                }
+                // Numeric annotation glyphs like `$1`.
+                '$' => {
+                    self.next_char()?;
+                    let symbol = self.read_symbol()?;
+                    if let (Ok(nag), Some(annotations)) =
+                        (symbol.parse::<u8>(), line.annotations.last_mut())
+                    {
+                        annotations.nags.push(nag);
+                    }
+                }
+                _ => {
+                    let symbol = self.read_symbol()?;
+                    if RESULTS.contains(&symbol.as_str()) {
+                        // Only the main line can end with a result.
+                        return match opening_position {
+                            None => Ok(Some(symbol)),
+                            Some(opening_position) => Err(Self::error_at(
+                                opening_position,
+                                PgnErrorKind::Unterminated('('),
+                            )),
+                        };
+                    }
+
+                    // Move numbers like `12.` or `12...` can be glued to the move, as in `1.e4`.
+                    // Digits are only a move number if dots follow, since `0-0` is castling.
+                    let san = symbol.trim_start_matches(|char: char| char.is_ascii_digit());
+                    let san = if san.starts_with('.') {
+                        san
+                    } else {
+                        symbol.as_str()
+                    };
+                    let san = san.trim_start_matches('.');
+                    // Annotations like `!?` can be glued to the move or stand on their own.
+                    let move_text = san.trim_end_matches(['!', '?']);
+                    let suffix_nag = get_suffix_nag(&san[move_text.len()..]);
+                    if move_text.is_empty() {
+                        if let (Some(nag), Some(annotations)) =
+                            (suffix_nag, line.annotations.last_mut())
+                        {
+                            annotations.nags.push(nag);
+                        }
+                        continue;
+                    }
+
+                    let moove = parse_san(state, move_text).map_err(|error| {
+                        Self::error_at(position, PgnErrorKind::IllegalMove(error))
+                    })?;
+                    last_move = Some((moove, state.make_move(moove)));
+                    line.moves.push(moove);
+                    line.annotations.push(PgnAnnotations {
+                        nags: suffix_nag.into_iter().collect(),
+                        ..PgnAnnotations::default()
+                    });
+                }
+            }
+        }
+    }
+
+    /// Reads a `{...}` comment and returns its text with all whitespace collapsed to single spaces.
+    fn read_comment(&mut self) -> Result<String, PgnError> {
+        let position = self.get_position()?;
+        // Skip the '{'.
+        self.next_char()?;
+        let mut comment = String::new();
+        loop {
+            match self.next_char()? {
+                Some('}') => return Ok(comment.split_whitespace().collect::<Vec<_>>().join(" ")),
+                Some(char) => comment.push(char),
+                None => return Err(Self::error_at(position, PgnErrorKind::Unterminated('{'))),
+            }
+        }
+    }
+
+    /// Reads a `;` comment, which lasts until the end of the line.
+    fn read_rest_of_line(&mut self) -> String {
+        let comment: String = self.line[self.column_index + 1..].iter().collect();
+        self.skip_rest_of_line();
+        comment.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Skips the rest of a broken game, up to the first tag pair after its movetext.
+    fn skip_to_next_game(&mut self) {
+        let mut has_seen_movetext = self.is_in_movetext;
+        let mut previous_line_was_empty = false;
+        loop {
+            self.skip_rest_of_line();
+            if self.is_at_end || self.read_line().is_err() {
+                self.is_at_end = true;
+                return;
+            }
+
+            let line: String = self.line.iter().collect();
+            let line = line.trim();
+            if line.starts_with('[') && previous_line_was_empty && has_seen_movetext {
+                return;
+            }
+            has_seen_movetext |= !line.is_empty() && !line.starts_with('[');
+            previous_line_was_empty = line.is_empty();
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for PgnReader<R> {
+    type Item = Result<PgnGame, PgnError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.read_game() {
+            Ok(game) => game.map(Ok),
+            Err(error) => {
+                self.skip_to_next_game();
+                Some(Err(error))
+            }
+        }
+    }
+}
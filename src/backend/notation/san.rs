@@ -0,0 +1,270 @@
+use crate::backend::game_state::fen_parser::{piece_from_fen_char, piece_to_fen_char};
+use crate::backend::game_state::state::State;
+use crate::backend::movegen::check_decider::is_in_check;
+use crate::backend::movegen::move_gen::get_legal_moves;
+use crate::backend::types::moove::{CastleType, Moove};
+use crate::backend::types::piece::Piece::{King, Pawn};
+use crate::backend::types::piece::{Piece, Side};
+use crate::backend::types::square::{Square, get_file, get_rank, square_to_string};
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+// Standard Algebraic Notation: https://www.chessprogramming.org/Algebraic_Chess_Notation#Standard_Algebraic_Notation_.28SAN.29
+
+/// Describes why a san string could not be turned into a move.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SanError {
+    /// The string is not san at all, e.g. it is empty or contains characters that make no sense.
+    InvalidSyntax(String),
+    /// The string is valid san, but no legal move in the position matches it.
+    IllegalMove(String),
+    /// More than one legal move matches the string, so it needs more disambiguation.
+    AmbiguousMove(String),
+}
+
+impl Display for SanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SanError::InvalidSyntax(san) => write!(f, "{:?} is not a valid san move", san),
+            SanError::IllegalMove(san) => write!(f, "{:?} is not a legal move", san),
+            SanError::AmbiguousMove(san) => write!(f, "{:?} matches more than one move", san),
+        }
+    }
+}
+
+impl Error for SanError {}
+
+/// Converts a legal move into standard algebraic notation, e.g. `Nbd7`, `exd5`, `e8=Q+` or `O-O-O#`.
+pub fn to_san(state: &State, moove: Moove) -> String {
+    let from = moove.get_from();
+    let to = moove.get_to();
+    let moved_piece = state
+        .bb_manager
+        .get_piece_at_square(from)
+        .expect("There is no piece on the from square of the move");
+    let is_capture = state.bb_manager.get_piece_at_square(to).is_some()
+        || (moved_piece == Pawn && get_file(from) != get_file(to));
+
+    let mut san = String::new();
+    if moved_piece == King && moove.is_castle() {
+        san.push_str(match moove.get_castle_type() {
+            CastleType::Short => "O-O",
+            CastleType::Long => "O-O-O",
+        });
+    } else if moved_piece == Pawn {
+        if is_capture {
+            san.push(file_to_char(get_file(from)));
+            san.push('x');
+        }
+        san.push_str(&square_to_string(to));
+        if let Some(promotion_type) = moove.get_promotion_type() {
+            san.push('=');
+            san.push(piece_to_fen_char(promotion_type, Side::White));
+        }
+    } else {
+        san.push(piece_to_fen_char(moved_piece, Side::White));
+        san.push_str(&get_disambiguation(state, moove, moved_piece));
+        if is_capture {
+            san.push('x');
+        }
+        san.push_str(&square_to_string(to));
+    }
+
+    let next_state = state.make_move_copy(moove);
+    if is_in_check(&next_state, next_state.active_color) {
+        if get_legal_moves(&next_state).is_empty() {
+            san.push('#');
+        } else {
+            san.push('+');
+        }
+    }
+
+    san
+}
+
+/// Finds the legal move that matches the san string.
+///
+/// Some common sloppy forms are accepted as well:
+/// - A missing or superfluous `x` for captures, e.g. `Nd5` instead of `Nxd5`.
+/// - Castling with zeros instead of the letter O, e.g. `0-0`.
+/// - Promotions without the `=`, e.g. `e8Q`.
+/// - Too much disambiguation, e.g. `Ng1f3` or `e2e4`.
+/// - Check and checkmate markers as well as annotations like `!?` are ignored, even if they are wrong.
+pub fn parse_san(state: &State, san: &str) -> Result<Moove, SanError> {
+    let trimmed_san = san.trim().trim_end_matches(['+', '#', '!', '?']);
+
+    let castle_type = match trimmed_san {
+        "O-O" | "0-0" => Some(CastleType::Short),
+        "O-O-O" | "0-0-0" => Some(CastleType::Long),
+        _ => None,
+    };
+    if let Some(castle_type) = castle_type {
+        return get_legal_moves(state)
+            .into_iter()
+            .find(|moove| {
+                state.bb_manager.get_piece_at_square(moove.get_from()) == Some(King)
+                    && moove.is_castle()
+                    && moove.get_castle_type() == castle_type
+            })
+            .ok_or_else(|| SanError::IllegalMove(san.to_string()));
+    }
+
+    let pattern =
+        parse_san_pattern(trimmed_san).ok_or_else(|| SanError::InvalidSyntax(san.to_string()))?;
+
+    let mut matching_moves = get_legal_moves(state).into_iter().filter(|moove| {
+        let from = moove.get_from();
+        let moved_piece = state.bb_manager.get_piece_at_square(from);
+        pattern.piece.is_none_or(|piece| Some(piece) == moved_piece)
+            && moove.get_to() == pattern.to
+            && moove.get_promotion_type() == pattern.promotion_type
+            && pattern.from_file.is_none_or(|file| file == get_file(from))
+            && pattern.from_rank.is_none_or(|rank| rank == get_rank(from))
+            // Castling has to be written as such, even though it is a king move. Only uci style `e1g1` is fine.
+            && !(pattern.piece == Some(King) && moove.is_castle())
+    });
+
+    match (matching_moves.next(), matching_moves.next()) {
+        (Some(moove), None) => Ok(moove),
+        (None, _) => Err(SanError::IllegalMove(san.to_string())),
+        (Some(_), Some(_)) => Err(SanError::AmbiguousMove(san.to_string())),
+    }
+}
+
+/// Everything a (non castling) san string says about its move.
+/// `piece` is `None` if the from square is fully given without a piece letter, as in `e2e4`.
+/// Any piece may then make the move, which allows parsing uci style moves.
+struct SanPattern {
+    piece: Option<Piece>,
+    from_file: Option<i8>,
+    from_rank: Option<i8>,
+    to: Square,
+    promotion_type: Option<Piece>,
+}
+
+fn parse_san_pattern(san: &str) -> Option<SanPattern> {
+    let mut chars: Vec<char> = san
+        .chars()
+        .filter(|char| !matches!(char, 'x' | ':' | '-'))
+        .collect();
+
+    // Only upper case letters are pieces, since a lower case `b` is a file.
+    let piece = match chars.first() {
+        Some(char @ ('K' | 'Q' | 'R' | 'B' | 'N')) => {
+            let (piece, _) = piece_from_fen_char(*char)?;
+            chars.remove(0);
+            Some(piece)
+        }
+        _ => None,
+    };
+
+    // A promotion is the only thing that can come after the destination rank.
+    let mut promotion_type = None;
+    if let Some(last_char) = chars.last()
+        && !last_char.is_ascii_digit()
+    {
+        let (promotion_piece, _) = piece_from_fen_char(*last_char)?;
+        if matches!(promotion_piece, King | Pawn) {
+            return None;
+        }
+        promotion_type = Some(promotion_piece);
+        chars.pop();
+        if chars.last() == Some(&'=') {
+            chars.pop();
+        }
+    }
+
+    // The destination are the last two characters, everything in front of them is disambiguation.
+    if chars.len() < 2 {
+        return None;
+    }
+    let rank = char_to_rank(chars.pop()?)?;
+    let file = char_to_file(chars.pop()?)?;
+    let to = file as Square + rank as Square * 8;
+
+    let mut from_file = None;
+    let mut from_rank = None;
+    for char in chars {
+        if let Some(file) = char_to_file(char)
+            && from_file.is_none()
+            && from_rank.is_none()
+        {
+            from_file = Some(file);
+        } else if let Some(rank) = char_to_rank(char)
+            && from_rank.is_none()
+        {
+            from_rank = Some(rank);
+        } else {
+            return None;
+        }
+    }
+
+    // Without a piece letter, it's a pawn move, unless the whole from square is given.
+    let piece = match (piece, from_file, from_rank) {
+        (None, Some(_), Some(_)) => None,
+        (None, _, _) => Some(Pawn),
+        (piece, _, _) => piece,
+    };
+
+    Some(SanPattern {
+        piece,
+        from_file,
+        from_rank,
+        to,
+        promotion_type,
+    })
+}
+
+/// Returns the file, the rank or the whole square of the from square, whatever is needed to tell the move apart
+/// from moves of other pieces of the same type to the same square.
+fn get_disambiguation(state: &State, moove: Moove, moved_piece: Piece) -> String {
+    let from = moove.get_from();
+    let other_froms: Vec<Square> = get_legal_moves(state)
+        .into_iter()
+        .filter(|other| {
+            other.get_to() == moove.get_to()
+                && other.get_from() != from
+                && state.bb_manager.get_piece_at_square(other.get_from()) == Some(moved_piece)
+        })
+        .map(|other| other.get_from())
+        .collect();
+
+    if other_froms.is_empty() {
+        return String::new();
+    }
+    if other_froms
+        .iter()
+        .all(|other| get_file(*other) != get_file(from))
+    {
+        return file_to_char(get_file(from)).to_string();
+    }
+    if other_froms
+        .iter()
+        .all(|other| get_rank(*other) != get_rank(from))
+    {
+        return rank_to_char(get_rank(from)).to_string();
+    }
+    square_to_string(from)
+}
+
+fn file_to_char(file: i8) -> char {
+    (b'a' + file as u8) as char
+}
+
+fn rank_to_char(rank: i8) -> char {
+    (b'1' + rank as u8) as char
+}
+
+fn char_to_file(char: char) -> Option<i8> {
+    match char {
+        'a'..='h' => Some(char as i8 - 'a' as i8),
+        _ => None,
+    }
+}
+
+fn char_to_rank(char: char) -> Option<i8> {
+    match char {
+        '1'..='8' => Some(char as i8 - '1' as i8),
+        _ => None,
+    }
+}
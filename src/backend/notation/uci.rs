@@ -0,0 +1,33 @@
+use crate::backend::game_state::castling_config::get_castle_squares_of_move;
+use crate::backend::game_state::state::State;
+use crate::backend::movegen::move_gen::get_legal_moves;
+use crate::backend::types::moove::Moove;
+use crate::backend::types::piece::Piece::King;
+
+// The long algebraic notation uci uses: https://backscattering.de/chess/uci/#move
+// Castles are written as the king moving two squares, e.g. `e1g1`.
+// With the `UCI_Chess960` option, they are written as the king capturing its own rook instead, e.g. `e1h1`,
+// since the king may not move at all or only by one square in Chess960.
+
+/// Converts a legal move into uci notation.
+/// `Moove` itself prints standard castles as `e1g1`, this prints them as `e1h1` if `chess960` is set.
+pub fn to_uci(state: &State, moove: Moove, chess960: bool) -> String {
+    let is_standard_castle = moove.is_castle()
+        && !moove.is_king_takes_rook()
+        && state.bb_manager.get_piece_at_square(moove.get_from()) == Some(King);
+    if !chess960 || !is_standard_castle {
+        return moove.to_string();
+    }
+
+    let castle_squares = get_castle_squares_of_move(moove);
+    Moove::new_king_takes_rook(castle_squares.king_from, castle_squares.rook_from).to_string()
+}
+
+/// Finds the legal move that matches the uci string.
+/// Castles are accepted in both notations, so this works no matter if `UCI_Chess960` is set.
+/// This is unambiguous, since a king can neither move two squares nor capture its own rook otherwise.
+pub fn parse_uci(state: &State, uci: &str) -> Option<Moove> {
+    get_legal_moves(state)
+        .into_iter()
+        .find(|&moove| moove.to_string() == uci || to_uci(state, moove, true) == uci)
+}
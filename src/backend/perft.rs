@@ -1,27 +1,326 @@
-use crate::backend::movegen::check_decider::is_in_check;
-use crate::backend::movegen::move_gen::get_pseudo_legal_moves;
+use crate::backend::movegen::check_decider::get_kings_square;
+use crate::backend::movegen::move_gen::get_legal_moves;
 use crate::backend::game_state::state::State;
+use crate::backend::types::moove::Moove;
+use crate::backend::types::piece::Piece;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::thread;
+
+/// If there are fewer root moves than this many per thread, the work is split at ply 2 instead.
+/// Otherwise, a few expensive root moves could keep one thread busy while the others are idle.
+const MIN_ROOT_MOVES_PER_THREAD: usize = 4;
+/// The depth is stored in the lowest byte of an entry, the node count in the rest.
+const PERFT_DEPTH_BITS: u32 = 8;
 
 pub fn perft(state: &State, depth: u8) -> u64 {
-    // PERFT: I would love to make this work, but it does not atm.
-    // let moves = get_moves(game_state);
-    // if depth == 1 {
-    //     return moves.len() as u64;
-    // }
+    // Walk the tree on a single copy of the state, making and unmaking moves in place.
+    let mut state = state.clone();
+    perft_in_place(&mut state, depth)
+}
+
+/// Runs perft for every root move on its own and returns the node count per move, sorted the same way perftree does.
+/// This is what `divide` in other engines prints and is the first step when hunting down a move gen bug.
+pub fn perft_divide(state: &State, depth: u8) -> Vec<(Moove, u64)> {
+    let mut state = state.clone();
+
+    let mut moves = get_legal_moves(&state);
+    moves.sort();
+
+    let mut nodes_per_move = Vec::with_capacity(moves.len());
+    for chess_move in moves {
+        let undo = state.make_move(chess_move);
+        let nodes = perft_in_place(&mut state, depth.saturating_sub(1));
+        state.unmake_move(chess_move, undo);
+        nodes_per_move.push((chess_move, nodes));
+    }
+
+    nodes_per_move
+}
+
+/// Same as `perft`, but the subtrees are counted on `threads` threads.
+pub fn perft_parallel(state: &State, depth: u8, threads: usize) -> u64 {
     if depth == 0 {
         return 1;
     }
+    perft_divide_parallel(state, depth, threads)
+        .iter()
+        .map(|(_, nodes)| nodes)
+        .sum()
+}
+
+/// Same as `perft_divide`, but the subtrees are counted on `threads` threads.
+pub fn perft_divide_parallel(state: &State, depth: u8, threads: usize) -> Vec<(Moove, u64)> {
+    perft_divide_stoppable(state, depth, threads, &AtomicBool::new(false)).unwrap()
+}
+
+/// Same as `perft_divide_parallel`, but gives up once `stop` is set and returns `None` then.
+/// The flag is checked before every subtree, so it takes a moment until the threads notice it.
+pub fn perft_divide_stoppable(
+    state: &State,
+    depth: u8,
+    threads: usize,
+    stop: &AtomicBool,
+) -> Option<Vec<(Moove, u64)>> {
+    let mut root_moves: Vec<Moove> = get_legal_moves(state).into_iter().collect();
+    root_moves.sort();
+    let threads = threads.max(1);
+
+    // Every task is a root move and, if the work is split at ply 2, one of the replies to it.
+    let mut tasks: Vec<(usize, Option<Moove>)> = Vec::new();
+    let split_at_ply_two = depth >= 3 && root_moves.len() < threads * MIN_ROOT_MOVES_PER_THREAD;
+    let mut split_state = state.clone();
+    for (root_index, &root_move) in root_moves.iter().enumerate() {
+        if !split_at_ply_two {
+            tasks.push((root_index, None));
+            continue;
+        }
+
+        let undo = split_state.make_move(root_move);
+        let replies = get_legal_moves(&split_state);
+        split_state.unmake_move(root_move, undo);
+        // A root move without replies still has to show up with its node count of 0.
+        if replies.is_empty() {
+            tasks.push((root_index, None));
+        }
+        tasks.extend(replies.into_iter().map(|reply| (root_index, Some(reply))));
+    }
 
-    let moves = get_pseudo_legal_moves(state);
-    let mut nodes = 0;
-    for chess_move in moves {
-        let next_state = state.make_move(chess_move);
-        // If we are in check after making the move -> skip.
-        if is_in_check(&next_state, next_state.active_color.opposite()) {
+    // Threads take the next task until none are left. This balances the load better than splitting the list upfront.
+    let next_task = AtomicUsize::new(0);
+    let mut nodes_per_move = vec![0; root_moves.len()];
+    thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads.min(tasks.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut state = state.clone();
+                    let mut nodes_per_move = vec![0; root_moves.len()];
+                    while !stop.load(Ordering::Relaxed) {
+                        let task_index = next_task.fetch_add(1, Ordering::Relaxed);
+                        let Some(&(root_index, reply)) = tasks.get(task_index) else {
+                            break;
+                        };
+                        nodes_per_move[root_index] +=
+                            perft_task(&mut state, root_moves[root_index], reply, depth);
+                    }
+                    nodes_per_move
+                })
+            })
+            .collect();
+
+        for worker in workers {
+            let worker_nodes_per_move = worker.join().unwrap();
+            for (nodes, worker_nodes) in nodes_per_move.iter_mut().zip(worker_nodes_per_move) {
+                *nodes += worker_nodes;
+            }
+        }
+    });
+
+    if stop.load(Ordering::Relaxed) {
+        return None;
+    }
+    Some(root_moves.into_iter().zip(nodes_per_move).collect())
+}
+
+/// Counts the nodes below the root move, or below the reply to it if there is one.
+fn perft_task(state: &mut State, root_move: Moove, reply: Option<Moove>, depth: u8) -> u64 {
+    let root_undo = state.make_move(root_move);
+    let nodes = match reply {
+        Some(reply) => {
+            let reply_undo = state.make_move(reply);
+            let nodes = perft_in_place(state, depth.saturating_sub(2));
+            state.unmake_move(reply, reply_undo);
+            nodes
+        }
+        None => perft_in_place(state, depth.saturating_sub(1)),
+    };
+    state.unmake_move(root_move, root_undo);
+    nodes
+}
+
+/// What kind of moves were played at the last ply, like in the tables on https://www.chessprogramming.org/Perft_Results
+/// If a category is off, the bug is most likely in the move gen for exactly that kind of move.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PerftStats {
+    pub nodes: u64,
+    /// Includes en passant captures.
+    pub captures: u64,
+    pub en_passants: u64,
+    pub castles: u64,
+    pub promotions: u64,
+    /// Includes discovered and double checks.
+    pub checks: u64,
+    /// Checks that are not given by the moved piece.
+    pub discovered_checks: u64,
+    pub double_checks: u64,
+    pub checkmates: u64,
+}
+
+/// Same as `perft`, but also counts what kind of moves were played at the last ply.
+/// This is a lot slower than `perft`, since every move at the last ply has to be made.
+pub fn perft_stats(state: &State, depth: u8) -> PerftStats {
+    let mut stats = PerftStats::default();
+    if depth == 0 {
+        stats.nodes = 1;
+        return stats;
+    }
+
+    let mut state = state.clone();
+    perft_stats_in_place(&mut state, depth, &mut stats);
+    stats
+}
+
+fn perft_stats_in_place(state: &mut State, depth: u8, stats: &mut PerftStats) {
+    for chess_move in get_legal_moves(state) {
+        if depth == 1 {
+            count_leaf_move(state, chess_move, stats);
             continue;
         }
 
-        nodes += perft(&next_state, depth - 1);
+        let undo = state.make_move(chess_move);
+        perft_stats_in_place(state, depth - 1, stats);
+        state.unmake_move(chess_move, undo);
+    }
+}
+
+fn count_leaf_move(state: &mut State, chess_move: Moove, stats: &mut PerftStats) {
+    let moved_piece = state
+        .bb_manager
+        .get_piece_at_square(chess_move.get_from())
+        .unwrap();
+    // Pawns can only move diagonally onto an empty square if they capture en passant.
+    let is_en_passant = moved_piece == Piece::Pawn
+        && state.irreversible_data.en_passant_square == Some(chess_move.get_to())
+        && chess_move.get_from() % 8 != chess_move.get_to() % 8;
+
+    let undo = state.make_move(chess_move);
+    stats.nodes += 1;
+    if state.irreversible_data.captured_piece.is_some() {
+        stats.captures += 1;
+    }
+    if is_en_passant {
+        stats.en_passants += 1;
+    }
+    let is_castle = moved_piece == Piece::King && chess_move.is_castle();
+    if is_castle {
+        stats.castles += 1;
+    }
+    if chess_move.get_promotion_type().is_some() {
+        stats.promotions += 1;
+    }
+
+    let king_square = get_kings_square(state, state.active_color);
+    let checkers_bb = state.attackers_to(king_square, state.bb_manager.get_all_pieces_bb())
+        & state
+            .bb_manager
+            .get_all_pieces_bb_off(state.active_color.opposite());
+    if checkers_bb.is_not_empty() {
+        stats.checks += 1;
+        if checkers_bb.count_ones() > 1 {
+            stats.double_checks += 1;
+        } else if !checkers_bb.get_square(chess_move.get_to()) && !is_castle {
+            // When castling, the rook is the moved piece that gives check.
+            stats.discovered_checks += 1;
+        }
+        if get_legal_moves(state).is_empty() {
+            stats.checkmates += 1;
+        }
+    }
+
+    state.unmake_move(chess_move, undo);
+}
+
+/// Same as `perft`, but node counts of subtrees are cached in a table of `table_mb` megabytes.
+/// Transpositions are common in perft, so this pays off quickly for deeper searches.
+pub fn perft_hashed(state: &State, depth: u8, table_mb: usize) -> u64 {
+    let table = PerftTable::new(table_mb);
+    let mut state = state.clone();
+    perft_hashed_in_place(&mut state, depth, &table)
+}
+
+fn perft_hashed_in_place(state: &mut State, depth: u8, table: &PerftTable) -> u64 {
+    // Bulk counting is cheaper than a table lookup.
+    if depth <= 1 {
+        return perft_in_place(state, depth);
+    }
+    if let Some(nodes) = table.probe(state.zobrist_key, depth) {
+        return nodes;
+    }
+
+    let mut nodes = 0;
+    for chess_move in get_legal_moves(state) {
+        let undo = state.make_move(chess_move);
+        nodes += perft_hashed_in_place(state, depth - 1, table);
+        state.unmake_move(chess_move, undo);
+    }
+
+    table.store(state.zobrist_key, depth, nodes);
+    nodes
+}
+
+/// Caches node counts by zobrist key and depth.
+/// The full key is stored, so index collisions are detected and never lead to a wrong count.
+/// Like the transposition table, the key is stored xor-ed with the data, so it can be shared between threads.
+struct PerftTable {
+    entries: Vec<PerftEntry>,
+}
+
+#[derive(Default)]
+struct PerftEntry {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl PerftTable {
+    fn new(size_mb: usize) -> PerftTable {
+        let entry_count = size_mb * 1024 * 1024 / size_of::<PerftEntry>();
+        let entry_count = match entry_count {
+            0 => 1,
+            count => 1 << count.ilog2(),
+        };
+
+        let mut entries = Vec::new();
+        entries.resize_with(entry_count, PerftEntry::default);
+        PerftTable { entries }
+    }
+
+    fn probe(&self, key: u64, depth: u8) -> Option<u64> {
+        let entry = self.get_entry(key);
+        let data = entry.data.load(Ordering::Relaxed);
+        let entry_key = entry.key.load(Ordering::Relaxed) ^ data;
+
+        let is_hit = entry_key == key && data as u8 == depth;
+        is_hit.then_some(data >> PERFT_DEPTH_BITS)
+    }
+
+    /// Always replaces the old entry.
+    fn store(&self, key: u64, depth: u8, nodes: u64) {
+        let entry = self.get_entry(key);
+        let data = nodes << PERFT_DEPTH_BITS | depth as u64;
+        entry.key.store(key ^ data, Ordering::Relaxed);
+        entry.data.store(data, Ordering::Relaxed);
+    }
+
+    fn get_entry(&self, key: u64) -> &PerftEntry {
+        &self.entries[key as usize & (self.entries.len() - 1)]
+    }
+}
+
+fn perft_in_place(state: &mut State, depth: u8) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = get_legal_moves(state);
+    // Since all generated moves are legal, we don't need to make them at the last ply (bulk counting).
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for chess_move in moves {
+        let undo = state.make_move(chess_move);
+        nodes += perft_in_place(state, depth - 1);
+        state.unmake_move(chess_move, undo);
     }
 
     nodes
@@ -0,0 +1,134 @@
+use crate::backend::game_state::state::State;
+use crate::backend::movegen::move_gen::get_legal_moves;
+use crate::backend::notation::uci::to_uci;
+use crate::backend::perft::perft_divide_parallel;
+use crate::backend::types::moove::Moove;
+use std::collections::BTreeMap;
+
+/// A divide result in uci notation, e.g. `("e2e4", 600)`. Strings are used so that moves that Mouse does not consider
+/// legal, or can not even parse, can still be compared.
+/// In Chess960 positions, castles are written as the king capturing its own rook, like uci engines do with `UCI_Chess960`.
+pub type Divide = Vec<(String, u64)>;
+
+/// The result of comparing the divide of a single position with a reference.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Bisection {
+    /// The moves that lead from the starting position to this one.
+    pub moves: Vec<Moove>,
+    pub fen: String,
+    pub depth: u8,
+    /// Moves only the reference generates, with the reference node count.
+    pub missing_moves: Divide,
+    /// Moves only Mouse generates, with our node count.
+    pub extra_moves: Divide,
+    /// Moves both generate, but with different node counts: (move, ours, reference).
+    pub count_mismatches: Vec<(String, u64, u64)>,
+}
+
+impl Bisection {
+    /// Returns true if Mouse and the reference agree on everything.
+    pub fn is_match(&self) -> bool {
+        self.missing_moves.is_empty()
+            && self.extra_moves.is_empty()
+            && self.count_mismatches.is_empty()
+    }
+
+    /// Returns true if the bisection ended in a position where Mouse and the reference disagree on the legal moves.
+    /// This position is as small as the bug gets.
+    pub fn is_minimal(&self) -> bool {
+        !self.missing_moves.is_empty() || !self.extra_moves.is_empty()
+    }
+}
+
+/// Finds the position in which a move gen bug happens, by comparing divide results with a reference.
+///
+/// Starting at `state`, the divide of both is compared. As long as all moves match, the first move with a different
+/// node count is made and the comparison continues one ply deeper, until the two disagree on the legal moves.
+/// This is what one would do by hand with perftree.
+///
+/// Our divides are counted on `threads` threads.
+/// `reference` returns the divide of the given position at the given depth.
+/// It may return `None` if it can not look that deep into the tree, e.g. when it's read from a file.
+/// The bisection then ends at the last position that could be compared.
+pub fn bisect<E>(
+    state: &State,
+    depth: u8,
+    threads: usize,
+    mut reference: impl FnMut(&State, u8) -> Result<Option<Divide>, E>,
+) -> Result<Option<Bisection>, E> {
+    let mut state = state.clone();
+    let mut depth = depth;
+    let mut moves = Vec::new();
+    let chess960 = state.castling_config.is_chess960();
+
+    let Some(mut reference_divide) = reference(&state, depth)? else {
+        return Ok(None);
+    };
+    loop {
+        let bisection =
+            compare_divides(&state, depth, threads, chess960, &moves, &reference_divide);
+        if bisection.is_minimal() || depth <= 1 {
+            return Ok(Some(bisection));
+        }
+        let Some((uci_move, _, _)) = bisection.count_mismatches.first() else {
+            return Ok(Some(bisection));
+        };
+
+        // Both generate this move, so it has to be legal here.
+        let next_move = get_legal_moves(&state)
+            .into_iter()
+            .find(|&moove| to_uci(&state, moove, chess960) == *uci_move)
+            .unwrap();
+        state.make_move(next_move);
+        match reference(&state, depth - 1)? {
+            Some(next_divide) => reference_divide = next_divide,
+            None => return Ok(Some(bisection)),
+        }
+        moves.push(next_move);
+        depth -= 1;
+    }
+}
+
+fn compare_divides(
+    state: &State,
+    depth: u8,
+    threads: usize,
+    chess960: bool,
+    moves: &[Moove],
+    reference_divide: &Divide,
+) -> Bisection {
+    let ours: BTreeMap<String, u64> = perft_divide_parallel(state, depth, threads)
+        .into_iter()
+        .map(|(moove, nodes)| (to_uci(state, moove, chess960), nodes))
+        .collect();
+    let theirs: BTreeMap<String, u64> = reference_divide.iter().cloned().collect();
+
+    let mut bisection = Bisection {
+        moves: moves.to_vec(),
+        fen: state.to_fen(),
+        depth,
+        missing_moves: Vec::new(),
+        extra_moves: Vec::new(),
+        count_mismatches: Vec::new(),
+    };
+    for (uci_move, &reference_nodes) in &theirs {
+        match ours.get(uci_move) {
+            None => bisection
+                .missing_moves
+                .push((uci_move.clone(), reference_nodes)),
+            Some(&nodes) if nodes != reference_nodes => {
+                bisection
+                    .count_mismatches
+                    .push((uci_move.clone(), nodes, reference_nodes))
+            }
+            Some(_) => {}
+        }
+    }
+    for (uci_move, &nodes) in &ours {
+        if !theirs.contains_key(uci_move) {
+            bisection.extra_moves.push((uci_move.clone(), nodes));
+        }
+    }
+
+    bisection
+}
@@ -0,0 +1,408 @@
+use crate::backend::eval::evaluation::evaluate;
+use crate::backend::game_state::state::State;
+use crate::backend::movegen::check_decider::is_in_check;
+use crate::backend::movegen::move_gen::{get_legal_captures, get_legal_evasions, get_legal_moves};
+use crate::backend::search::move_ordering::order_moves;
+use crate::backend::search::time_manager::{Clock, TimeManager};
+use crate::backend::search::transposition_table::{Bound, TranspositionTable};
+use crate::backend::types::moove::Moove;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// Iterative deepening: https://www.chessprogramming.org/Iterative_Deepening
+// Negamax with alpha-beta pruning: https://www.chessprogramming.org/Alpha-Beta
+// Principal variation search: https://www.chessprogramming.org/Principal_Variation_Search
+// Quiescence search: https://www.chessprogramming.org/Quiescence_Search
+
+/// The deepest the search can ever go, including quiescence search.
+pub const MAX_PLY: usize = 128;
+/// The score of being checkmated right now. Mates further away score closer to 0, so shorter mates are preferred.
+pub const MATE_SCORE: i32 = 32_000;
+/// Every score with a larger absolute value is a mate.
+const MATE_THRESHOLD: i32 = MATE_SCORE - MAX_PLY as i32;
+const INFINITY: i32 = MATE_SCORE + 1;
+const DRAW_SCORE: i32 = 0;
+
+/// Checking the clock is not free, so it's only done every this many nodes.
+const NODES_BETWEEN_STOP_CHECKS: u64 = 1024;
+
+/// Restricts how long the search runs. It stops as soon as any of the limits is reached.
+/// If no limit is set, the search only stops once the `stop` flag is set or `MAX_PLY` is reached.
+#[derive(Clone, Debug, Default)]
+pub struct SearchLimits {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    /// A fixed time for this move. Takes precedence over `clock`.
+    pub move_time: Option<Duration>,
+    /// The clock of the side to move. The `TimeManager` decides how much of it is used.
+    pub clock: Option<Clock>,
+    /// Time that is lost outside of the search, e.g. due to communicating with the gui. It is never used for searching.
+    pub move_overhead: Duration,
+    /// While this flag is set, the search ponders on the opponent's time and ignores `move_time` and `clock`.
+    /// Clearing it, which is what `ponderhit` does, starts the clock.
+    pub ponder: Option<Arc<AtomicBool>>,
+}
+
+/// The result of the last completed iteration.
+#[derive(Clone, Debug)]
+pub struct SearchResult {
+    /// `None` if there is no legal move.
+    pub best_move: Option<Moove>,
+    /// The score in centipawns from the point of view of the side to move. See `get_mate_distance` for mates.
+    pub score: i32,
+    /// The principal variation, the line both sides are expected to play. Starts with the best move.
+    pub pv: Vec<Moove>,
+    pub depth: u8,
+    /// The number of nodes searched in total, including unfinished iterations.
+    pub nodes: u64,
+    pub elapsed: Duration,
+}
+
+/// Returns in how many moves (not plies) the side to move mates, negative if it gets mated.
+/// Returns `None` if the score is not a mate score.
+pub fn get_mate_distance(score: i32) -> Option<i32> {
+    if score.abs() <= MATE_THRESHOLD {
+        return None;
+    }
+    let plies = MATE_SCORE - score.abs();
+    let moves = (plies + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+/// Searches the position with iterative deepening until one of the limits is reached or `stop` is set.
+/// `on_iteration` is called after every completed iteration, which allows printing uci info lines.
+pub fn search(
+    state: &State,
+    limits: &SearchLimits,
+    tt: &TranspositionTable,
+    stop: &AtomicBool,
+    mut on_iteration: impl FnMut(&SearchResult),
+) -> SearchResult {
+    tt.new_search();
+    let mut searcher = Searcher {
+        state: state.clone(),
+        limits: limits.clone(),
+        time_manager: TimeManager::new(limits),
+        tt,
+        stop,
+        start: Instant::now(),
+        clock_start: Instant::now(),
+        is_pondering: limits.ponder.is_some(),
+        nodes: 0,
+        is_stopped: false,
+        pv_table: [[None; MAX_PLY]; MAX_PLY],
+        pv_length: [0; MAX_PLY],
+        previous_pv: Vec::new(),
+        previous_pv_plies: 0,
+    };
+
+    // If not even the first iteration finishes, any legal move is better than none.
+    let mut result = SearchResult {
+        best_move: get_legal_moves(state).into_iter().next(),
+        score: 0,
+        pv: Vec::new(),
+        depth: 0,
+        nodes: 0,
+        elapsed: Duration::ZERO,
+    };
+
+    let max_depth = limits.depth.map_or(MAX_PLY - 1, |depth| depth as usize);
+    for depth in 1..=max_depth.min(MAX_PLY - 1) {
+        let score = searcher.negamax(depth as i32, 0, -INFINITY, INFINITY);
+        if searcher.is_stopped {
+            break;
+        }
+
+        let pv: Vec<Moove> = searcher.pv_table[0][..searcher.pv_length[0]]
+            .iter()
+            .flatten()
+            .copied()
+            .collect();
+        result = SearchResult {
+            best_move: pv.first().copied().or(result.best_move),
+            score,
+            pv: pv.clone(),
+            depth: depth as u8,
+            nodes: searcher.nodes,
+            elapsed: searcher.start.elapsed(),
+        };
+        searcher.previous_pv = pv;
+        on_iteration(&result);
+
+        // There is nothing left to find if there is no legal move at all or a forced mate was found.
+        // Only a mate for us that fits within this depth is final. A mate from the tt may have come from a deeper
+        // search and being mated is worth searching on, since a deeper search might find a longer defense.
+        let is_final_mate = score > MATE_THRESHOLD && MATE_SCORE - score <= depth as i32;
+        if result.best_move.is_none() || is_final_mate {
+            break;
+        }
+        searcher
+            .time_manager
+            .on_iteration_finished(result.best_move, score);
+        let clock_elapsed = searcher.get_clock_elapsed();
+        if clock_elapsed
+            .is_some_and(|elapsed| !searcher.time_manager.should_start_iteration(elapsed))
+        {
+            break;
+        }
+    }
+
+    result.nodes = searcher.nodes;
+    result.elapsed = searcher.start.elapsed();
+    result
+}
+
+struct Searcher<'a> {
+    state: State,
+    limits: SearchLimits,
+    time_manager: TimeManager,
+    tt: &'a TranspositionTable,
+    stop: &'a AtomicBool,
+    start: Instant,
+    /// When the time limits started to count. This is later than `start` if the search was pondering.
+    clock_start: Instant,
+    is_pondering: bool,
+    nodes: u64,
+    /// Set once a limit was reached. The iteration that was running is then thrown away.
+    is_stopped: bool,
+    /// The triangular pv table: https://www.chessprogramming.org/Triangular_PV-Table
+    /// Row `ply` holds the best line found from that ply on, starting at index `ply`.
+    pv_table: [[Option<Moove>; MAX_PLY]; MAX_PLY],
+    pv_length: [usize; MAX_PLY],
+    /// The pv of the previous iteration. Its moves are searched first.
+    previous_pv: Vec<Moove>,
+    /// How many moves of the line that leads to the current node are the start of `previous_pv`.
+    /// The node is on the previous pv if this equals its ply.
+    previous_pv_plies: usize,
+}
+
+impl Searcher<'_> {
+    fn negamax(&mut self, depth: i32, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv_length[ply] = ply;
+        if self.should_stop() {
+            return DRAW_SCORE;
+        }
+        if depth <= 0 || ply >= MAX_PLY - 1 {
+            return self.quiescence(ply, alpha, beta);
+        }
+        self.nodes += 1;
+
+        // Repeating a position or running into the fifty move rule ends the game in a draw.
+        if ply > 0 && (self.state.half_move_clock >= 100 || self.state.repetition_count() > 1) {
+            return DRAW_SCORE;
+        }
+
+        // If this position was already searched at least as deep, its result can be reused.
+        // This is not done at the root, since the root has to come up with a best move and a pv.
+        let tt_entry = self.tt.probe(self.state.zobrist_key);
+        if let Some(tt_entry) = tt_entry
+            && ply > 0
+            && tt_entry.depth as i32 >= depth
+        {
+            let score = score_from_tt(tt_entry.score, ply);
+            let is_usable = match tt_entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if is_usable {
+                return score;
+            }
+        }
+
+        let mut moves = get_legal_moves(&self.state);
+        if moves.is_empty() {
+            return if is_in_check(&self.state, self.state.active_color) {
+                -MATE_SCORE + ply as i32
+            } else {
+                DRAW_SCORE
+            };
+        }
+        // The previous pv only knows about this node if the moves that lead here are the start of it.
+        let is_on_previous_pv = self.previous_pv_plies == ply;
+        let pv_move = match is_on_previous_pv {
+            true => self.previous_pv.get(ply).copied(),
+            false => None,
+        };
+        let hash_move = tt_entry.and_then(|tt_entry| tt_entry.best_move).or(pv_move);
+        order_moves(&self.state, &mut moves, hash_move);
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        for (index, moove) in moves.into_iter().enumerate() {
+            let undo = self.state.make_move(moove);
+            if is_on_previous_pv && pv_move == Some(moove) {
+                self.previous_pv_plies = ply + 1;
+            }
+            let score = if index == 0 {
+                -self.negamax(depth - 1, ply + 1, -beta, -alpha)
+            } else {
+                // Assume the first move was the best and only prove that this one is worse with a null window.
+                // If that fails, it has to be searched again with the full window.
+                let score = -self.negamax(depth - 1, ply + 1, -alpha - 1, -alpha);
+                if score > alpha && score < beta {
+                    -self.negamax(depth - 1, ply + 1, -beta, -alpha)
+                } else {
+                    score
+                }
+            };
+            self.state.unmake_move(moove, undo);
+            self.previous_pv_plies = self.previous_pv_plies.min(ply);
+
+            if self.is_stopped {
+                return DRAW_SCORE;
+            }
+            if score > best_score {
+                best_score = score;
+                best_move = Some(moove);
+            }
+            if score > alpha {
+                alpha = score;
+                self.update_pv(ply, moove);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            // None of the moves was good enough, so there is no telling which one is the best.
+            best_move = None;
+            Bound::Upper
+        };
+        self.tt.store(
+            self.state.zobrist_key,
+            depth as u8,
+            bound,
+            score_to_tt(best_score, ply),
+            best_move,
+        );
+
+        best_score
+    }
+
+    /// Only searches captures and queen promotions until the position is quiet,
+    /// so the evaluation is never called in the middle of an exchange.
+    fn quiescence(&mut self, ply: usize, mut alpha: i32, beta: i32) -> i32 {
+        self.pv_length[ply] = ply;
+        if self.should_stop() {
+            return DRAW_SCORE;
+        }
+        self.nodes += 1;
+        if ply >= MAX_PLY - 1 {
+            return evaluate(&self.state);
+        }
+
+        // In check, every evasion has to be looked at and standing pat is not an option.
+        let is_in_check = is_in_check(&self.state, self.state.active_color);
+        let mut best_score = -INFINITY;
+        let mut moves = if is_in_check {
+            let moves = get_legal_evasions(&self.state);
+            if moves.is_empty() {
+                return -MATE_SCORE + ply as i32;
+            }
+            moves
+        } else {
+            // Standing pat: The side to move can usually do at least as well as the static evaluation.
+            best_score = evaluate(&self.state);
+            if best_score >= beta {
+                return best_score;
+            }
+            alpha = alpha.max(best_score);
+            get_legal_captures(&self.state)
+        };
+        order_moves(&self.state, &mut moves, None);
+
+        for moove in moves {
+            let undo = self.state.make_move(moove);
+            let score = -self.quiescence(ply + 1, -beta, -alpha);
+            self.state.unmake_move(moove, undo);
+
+            if self.is_stopped {
+                return DRAW_SCORE;
+            }
+            if score > best_score {
+                best_score = score;
+            }
+            if score > alpha {
+                alpha = score;
+                self.update_pv(ply, moove);
+            }
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        best_score
+    }
+
+    /// Makes `moove` followed by the pv of the next ply the new pv of this ply.
+    fn update_pv(&mut self, ply: usize, moove: Moove) {
+        self.pv_table[ply][ply] = Some(moove);
+        let child_pv_length = self.pv_length[ply + 1];
+        for next_ply in ply + 1..child_pv_length {
+            self.pv_table[ply][next_ply] = self.pv_table[ply + 1][next_ply];
+        }
+        self.pv_length[ply] = child_pv_length.max(ply + 1);
+    }
+
+    /// The time that counts against the time limits. `None` while pondering, since the clock only starts on `ponderhit`.
+    fn get_clock_elapsed(&mut self) -> Option<Duration> {
+        if self.is_pondering {
+            let ponder = self.limits.ponder.as_ref();
+            if ponder.is_some_and(|ponder| ponder.load(Ordering::Relaxed)) {
+                return None;
+            }
+            self.is_pondering = false;
+            self.clock_start = Instant::now();
+        }
+        Some(self.clock_start.elapsed())
+    }
+
+    fn should_stop(&mut self) -> bool {
+        if self.is_stopped {
+            return true;
+        }
+        if !self.nodes.is_multiple_of(NODES_BETWEEN_STOP_CHECKS) {
+            return false;
+        }
+
+        let is_out_of_nodes = self.limits.nodes.is_some_and(|nodes| self.nodes >= nodes);
+        let is_out_of_time = self
+            .get_clock_elapsed()
+            .is_some_and(|elapsed| self.time_manager.is_out_of_time(elapsed));
+        self.is_stopped = is_out_of_nodes || is_out_of_time || self.stop.load(Ordering::Relaxed);
+        self.is_stopped
+    }
+}
+
+/// Mate scores depend on the distance to the root, but the tt entry might be used at a different ply.
+/// So they are stored as the distance to the mate from the position itself.
+fn score_to_tt(score: i32, ply: usize) -> i16 {
+    let score = if score > MATE_THRESHOLD {
+        score + ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    };
+    score as i16
+}
+
+/// The inverse of `score_to_tt`.
+fn score_from_tt(score: i16, ply: usize) -> i32 {
+    let score = score as i32;
+    if score > MATE_THRESHOLD {
+        score - ply as i32
+    } else if score < -MATE_THRESHOLD {
+        score + ply as i32
+    } else {
+        score
+    }
+}
@@ -0,0 +1,4 @@
+pub mod alpha_beta;
+pub mod move_ordering;
+pub mod transposition_table;
+pub mod time_manager;
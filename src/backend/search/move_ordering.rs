@@ -0,0 +1,61 @@
+use crate::backend::game_state::state::State;
+use crate::backend::types::moove::Moove;
+use crate::backend::types::move_list::MoveList;
+use crate::backend::types::piece::Piece;
+use crate::backend::types::square::back_by_one;
+
+// Searching the best move first makes alpha-beta cut off far more branches.
+// https://www.chessprogramming.org/Move_Ordering
+
+const PV_MOVE_SCORE: i32 = 1_000_000;
+const CAPTURE_SCORE: i32 = 100_000;
+const PROMOTION_SCORE: i32 = 90_000;
+
+/// Rough piece values, only used to order moves. Indexed by `Piece`.
+const ORDERING_VALUES: [i32; 6] = [500, 320, 330, 900, 2_000, 100];
+
+/// Scores every move and sorts the list, so the most promising moves come first:
+/// 1. The move of the principal variation of the previous iteration.
+/// 2. Captures, the most valuable victim first and among those the least valuable attacker first (MVV-LVA).
+/// 3. Promotions.
+/// 4. Everything else in the order the move gen produced it.
+pub fn order_moves(state: &State, moves: &mut MoveList, pv_move: Option<Moove>) {
+    for index in 0..moves.len() {
+        let moove = moves.get(index);
+        moves.set_score(index, score_move(state, moove, pv_move));
+    }
+    moves.sort_by_score();
+}
+
+fn score_move(state: &State, moove: Moove, pv_move: Option<Moove>) -> i32 {
+    if pv_move == Some(moove) {
+        return PV_MOVE_SCORE;
+    }
+
+    let mut score = 0;
+    let attacker = state.bb_manager.get_piece_at_square(moove.get_from());
+    if let Some(victim) = get_captured_piece(state, moove, attacker) {
+        let attacker_value = attacker.map_or(0, |attacker| ORDERING_VALUES[attacker as usize]);
+        score += CAPTURE_SCORE + 10 * ORDERING_VALUES[victim as usize] - attacker_value;
+    }
+    if let Some(promotion_type) = moove.get_promotion_type() {
+        score += PROMOTION_SCORE + ORDERING_VALUES[promotion_type as usize];
+    }
+    score
+}
+
+/// Returns the piece the move captures, including pawns captured en passant.
+fn get_captured_piece(state: &State, moove: Moove, attacker: Option<Piece>) -> Option<Piece> {
+    // Chess960 castles look like the king capturing its own rook.
+    if moove.is_king_takes_rook() {
+        return None;
+    }
+    let is_en_passant = attacker == Some(Piece::Pawn)
+        && state.irreversible_data.en_passant_square == Some(moove.get_to());
+    if is_en_passant {
+        return state
+            .bb_manager
+            .get_piece_at_square(back_by_one(moove.get_to(), state.active_color));
+    }
+    state.bb_manager.get_piece_at_square(moove.get_to())
+}
@@ -0,0 +1,133 @@
+use crate::backend::search::alpha_beta::SearchLimits;
+use crate::backend::types::moove::Moove;
+use std::time::Duration;
+
+// Time management: https://www.chessprogramming.org/Time_Management
+
+/// Without `movestogo`, the remaining time is planned for this many moves.
+const DEFAULT_MOVES_TO_GO: u32 = 30;
+/// Only this much of the increment is counted as available, since some of it is used up by the move overhead.
+const INCREMENT_PERCENT: u32 = 75;
+/// The hard limit is this many times the soft limit...
+const HARD_LIMIT_FACTOR: u32 = 4;
+/// ... but never more than this share of the remaining time.
+const MAX_TIME_PERCENT: u32 = 50;
+
+/// How much the soft limit is scaled, in percent, depending on how many iterations in a row found the same best move.
+/// A best move that keeps changing means that the search is unsure and deserves more time.
+const STABILITY_PERCENTS: [u32; 5] = [200, 120, 100, 85, 75];
+/// Score drops smaller than this are just noise.
+const SCORE_DROP_THRESHOLD: i32 = 20;
+/// A score drop of this much or more doubles the soft limit.
+const MAX_SCORE_DROP: i32 = 120;
+
+/// The clock of the side to move.
+#[derive(Clone, Debug, Default)]
+pub struct Clock {
+    pub time_left: Duration,
+    pub increment: Duration,
+    /// The number of moves until the next time control. `None` if the rest of the game has to be played in `time_left`.
+    pub moves_to_go: Option<u32>,
+}
+
+/// Decides how long the search may take.
+///
+/// There are two limits: The soft limit is checked after every iteration. Once it's reached, no new iteration is started.
+/// The hard limit is checked during the search and aborts it right away.
+/// The soft limit is scaled up or down depending on how stable the results of the previous iterations were.
+///
+/// This never looks at the clock itself, the elapsed time is always passed in. This keeps it testable.
+#[derive(Clone, Debug)]
+pub struct TimeManager {
+    base_soft_limit: Option<Duration>,
+    hard_limit: Option<Duration>,
+    previous_best_move: Option<Moove>,
+    previous_score: Option<i32>,
+    /// How many iterations in a row found the same best move.
+    stable_iterations: usize,
+    /// The current scaling of the soft limit in percent.
+    soft_limit_percent: u32,
+}
+
+impl TimeManager {
+    pub fn new(limits: &SearchLimits) -> TimeManager {
+        let (base_soft_limit, hard_limit) = match (limits.move_time, &limits.clock) {
+            // A fixed time per move should be used completely, so there is only a hard limit.
+            (Some(move_time), _) => (None, Some(move_time.saturating_sub(limits.move_overhead))),
+            (None, Some(clock)) => {
+                let (soft_limit, hard_limit) = get_clock_limits(clock, limits.move_overhead);
+                (Some(soft_limit), Some(hard_limit))
+            }
+            (None, None) => (None, None),
+        };
+
+        TimeManager {
+            base_soft_limit,
+            hard_limit,
+            previous_best_move: None,
+            previous_score: None,
+            stable_iterations: 0,
+            soft_limit_percent: 100,
+        }
+    }
+
+    /// The soft limit after scaling it. It never exceeds the hard limit.
+    pub fn get_soft_limit(&self) -> Option<Duration> {
+        let soft_limit = self.base_soft_limit? * self.soft_limit_percent / 100;
+        Some(match self.hard_limit {
+            Some(hard_limit) => soft_limit.min(hard_limit),
+            None => soft_limit,
+        })
+    }
+
+    pub fn get_hard_limit(&self) -> Option<Duration> {
+        self.hard_limit
+    }
+
+    /// Has to be called after every finished iteration, so the soft limit can adapt.
+    pub fn on_iteration_finished(&mut self, best_move: Option<Moove>, score: i32) {
+        if best_move == self.previous_best_move {
+            self.stable_iterations += 1;
+        } else {
+            self.stable_iterations = 0;
+        }
+        let stability_percent =
+            STABILITY_PERCENTS[self.stable_iterations.min(STABILITY_PERCENTS.len() - 1)];
+
+        // A dropping score means that the best move has a problem. It's worth spending time on finding a better one.
+        let score_drop = self
+            .previous_score
+            .map_or(0, |previous_score| previous_score - score);
+        let score_drop_percent = match score_drop {
+            drop if drop < SCORE_DROP_THRESHOLD => 100,
+            drop => 100 + (100 * drop.min(MAX_SCORE_DROP) / MAX_SCORE_DROP) as u32,
+        };
+
+        self.soft_limit_percent = stability_percent * score_drop_percent / 100;
+        self.previous_best_move = best_move;
+        self.previous_score = Some(score);
+    }
+
+    /// Returns false if the soft limit is reached, so another iteration is unlikely to finish in time.
+    pub fn should_start_iteration(&self, elapsed: Duration) -> bool {
+        self.get_soft_limit()
+            .is_none_or(|soft_limit| elapsed < soft_limit)
+    }
+
+    /// Returns true once the hard limit is reached and the search has to stop immediately.
+    pub fn is_out_of_time(&self, elapsed: Duration) -> bool {
+        self.hard_limit
+            .is_some_and(|hard_limit| elapsed >= hard_limit)
+    }
+}
+
+/// Splits the remaining time evenly over the moves until the next time control.
+fn get_clock_limits(clock: &Clock, move_overhead: Duration) -> (Duration, Duration) {
+    // The overhead is time lost outside of the search, e.g. while sending the move to the gui.
+    let available = clock.time_left.saturating_sub(move_overhead);
+    let moves_to_go = clock.moves_to_go.unwrap_or(DEFAULT_MOVES_TO_GO).max(1);
+
+    let soft_limit = available / moves_to_go + clock.increment * INCREMENT_PERCENT / 100;
+    let hard_limit = (soft_limit * HARD_LIMIT_FACTOR).min(available * MAX_TIME_PERCENT / 100);
+    (soft_limit.min(hard_limit), hard_limit)
+}
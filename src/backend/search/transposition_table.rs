@@ -0,0 +1,229 @@
+use crate::backend::types::moove::Moove;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+
+// Transposition table: https://www.chessprogramming.org/Transposition_Table
+// Lockless hashing: https://www.chessprogramming.org/Shared_Hash_Table#Lockless
+
+pub const DEFAULT_TT_SIZE_MB: usize = 16;
+
+/// A move of 0 would be a1a1, which is never legal. It is used to store that there is no best move.
+const NO_MOVE_BITS: u64 = 0;
+/// Ages wrap around after this many searches. It has to fit into the 6 bits reserved for it.
+const AGE_COUNT: u8 = 64;
+/// How many entries `hashfull` looks at.
+const HASHFULL_SAMPLE_SIZE: usize = 1000;
+
+/// What the stored score says about the real score of the position.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum Bound {
+    /// The score is exact.
+    Exact,
+    /// The search failed high, the real score is at least this high.
+    Lower,
+    /// The search failed low, the real score is at most this high.
+    Upper,
+}
+
+impl Bound {
+    /// 0 is reserved for empty slots.
+    fn to_bits(self) -> u64 {
+        match self {
+            Bound::Exact => 1,
+            Bound::Lower => 2,
+            Bound::Upper => 3,
+        }
+    }
+
+    fn from_bits(bits: u64) -> Option<Bound> {
+        match bits {
+            1 => Some(Bound::Exact),
+            2 => Some(Bound::Lower),
+            3 => Some(Bound::Upper),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TTEntry {
+    pub best_move: Option<Moove>,
+    pub score: i16,
+    pub depth: u8,
+    pub bound: Bound,
+    /// The age of the search that stored this entry. Entries of older searches are replaced first.
+    pub age: u8,
+}
+
+impl TTEntry {
+    /// Packs the entry into 48 bits:
+    /// 16 bits for the move, 16 for the score, 8 for the depth, 2 for the bound and 6 for the age.
+    fn to_bits(self) -> u64 {
+        let move_bits = self
+            .best_move
+            .map_or(NO_MOVE_BITS, |moove| moove.to_bits() as u64);
+        move_bits
+            | (self.score as u16 as u64) << 16
+            | (self.depth as u64) << 32
+            | self.bound.to_bits() << 40
+            | (self.age as u64) << 42
+    }
+
+    /// Returns `None` if the bits belong to an empty slot.
+    fn from_bits(bits: u64) -> Option<TTEntry> {
+        let bound = Bound::from_bits((bits >> 40) & 0b11)?;
+        let move_bits = bits & 0xffff;
+        Some(TTEntry {
+            best_move: (move_bits != NO_MOVE_BITS).then(|| Moove::from_bits(move_bits as u16)),
+            score: (bits >> 16) as u16 as i16,
+            depth: (bits >> 32) as u8,
+            bound,
+            age: ((bits >> 42) & 0b11_1111) as u8,
+        })
+    }
+}
+
+/// A single entry. The key is stored xor-ed with the data, so an entry that was torn by two threads writing
+/// at the same time no longer matches its key and is simply treated as a miss.
+#[derive(Default)]
+struct Slot {
+    key: AtomicU64,
+    data: AtomicU64,
+}
+
+impl Slot {
+    fn load(&self) -> (u64, u64) {
+        let key = self.key.load(Ordering::Relaxed);
+        let data = self.data.load(Ordering::Relaxed);
+        (key ^ data, data)
+    }
+
+    fn store(&self, key: u64, data: u64) {
+        self.key.store(key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+}
+
+/// Every key maps to one bucket with two slots.
+/// Deep entries are expensive to recompute, so the first slot only gets replaced by entries that are at least as deep
+/// or when its entry is from an older search. Everything else goes into the second slot.
+#[derive(Default)]
+struct Bucket {
+    depth_preferred: Slot,
+    always_replace: Slot,
+}
+
+/// A hash table from zobrist keys to search results. All methods take `&self`, so it can be shared between threads.
+pub struct TranspositionTable {
+    buckets: Vec<Bucket>,
+    age: AtomicU8,
+}
+
+impl TranspositionTable {
+    /// Creates a table that uses at most `size_mb` megabytes. The bucket count is rounded down to a power of two.
+    pub fn new(size_mb: usize) -> TranspositionTable {
+        let mut tt = TranspositionTable {
+            buckets: Vec::new(),
+            age: AtomicU8::new(0),
+        };
+        tt.resize(size_mb);
+        tt
+    }
+
+    /// Resizes the table to use at most `size_mb` megabytes. This clears all entries.
+    pub fn resize(&mut self, size_mb: usize) {
+        let bucket_count = size_mb * 1024 * 1024 / size_of::<Bucket>();
+        // Rounding down to a power of two allows indexing with a mask instead of a modulo.
+        let bucket_count = match bucket_count {
+            0 => 1,
+            count => 1 << count.ilog2(),
+        };
+
+        self.buckets = Vec::new();
+        self.buckets.resize_with(bucket_count, Bucket::default);
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// Removes all entries.
+    pub fn clear(&self) {
+        for bucket in &self.buckets {
+            bucket.depth_preferred.store(0, 0);
+            bucket.always_replace.store(0, 0);
+        }
+        self.age.store(0, Ordering::Relaxed);
+    }
+
+    /// The size of the table in bytes.
+    pub fn size_in_bytes(&self) -> usize {
+        self.buckets.len() * size_of::<Bucket>()
+    }
+
+    /// Has to be called before every search, so the entries of earlier searches are replaced first.
+    pub fn new_search(&self) {
+        let age = (self.age.load(Ordering::Relaxed) + 1) % AGE_COUNT;
+        self.age.store(age, Ordering::Relaxed);
+    }
+
+    pub fn probe(&self, key: u64) -> Option<TTEntry> {
+        let bucket = self.get_bucket(key);
+        [&bucket.depth_preferred, &bucket.always_replace]
+            .into_iter()
+            .find_map(|slot| {
+                let (slot_key, data) = slot.load();
+                if slot_key != key {
+                    return None;
+                }
+                TTEntry::from_bits(data)
+            })
+    }
+
+    pub fn store(&self, key: u64, depth: u8, bound: Bound, score: i16, best_move: Option<Moove>) {
+        let bucket = self.get_bucket(key);
+        let age = self.age.load(Ordering::Relaxed);
+
+        let (old_key, old_data) = bucket.depth_preferred.load();
+        let old_entry = TTEntry::from_bits(old_data);
+        let slot = match old_entry {
+            None => &bucket.depth_preferred,
+            Some(old_entry)
+                if old_key == key || old_entry.age != age || depth >= old_entry.depth =>
+            {
+                &bucket.depth_preferred
+            }
+            Some(_) => &bucket.always_replace,
+        };
+
+        // A search that failed low does not know a best move. Keep the one of an earlier search in that case.
+        let best_move =
+            best_move.or_else(|| self.probe(key).and_then(|old_entry| old_entry.best_move));
+
+        let entry = TTEntry {
+            best_move,
+            score,
+            depth,
+            bound,
+            age,
+        };
+        slot.store(key, entry.to_bits());
+    }
+
+    /// Returns how full the table is in permille, by looking at a sample of the entries.
+    /// Only entries of the current search count, as the others will be replaced anyway.
+    pub fn hashfull(&self) -> u16 {
+        let age = self.age.load(Ordering::Relaxed);
+        let sample_size = HASHFULL_SAMPLE_SIZE.min(self.buckets.len() * 2);
+
+        let used = self
+            .buckets
+            .iter()
+            .flat_map(|bucket| [&bucket.depth_preferred, &bucket.always_replace])
+            .take(sample_size)
+            .filter_map(|slot| TTEntry::from_bits(slot.load().1))
+            .filter(|entry| entry.age == age)
+            .count();
+        (used * 1000 / sample_size) as u16
+    }
+
+    fn get_bucket(&self, key: u64) -> &Bucket {
+        &self.buckets[key as usize & (self.buckets.len() - 1)]
+    }
+}
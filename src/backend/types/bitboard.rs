@@ -9,7 +9,7 @@ use crate::backend::types::square::Square;
 ///
 /// # Fields
 /// - `value` (`u64`): The underlying 64-bit integer used to store the board's game_state.
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct BitBoard {
     pub value: u64,
 }
@@ -23,7 +23,7 @@ impl BitBoard {
     }
 
     /// Converts a given `Square` into a corresponding bitboard.
-    fn new_from_square(square: Square) -> BitBoard {
+    pub fn new_from_square(square: Square) -> BitBoard {
         BitBoard { value: 1 << square }
     }
 
@@ -37,6 +37,11 @@ impl BitBoard {
         self.value != 0
     }
 
+    /// Returns the amount of occupied squares.
+    pub fn count_ones(&self) -> u32 {
+        self.value.count_ones()
+    }
+
     /// Checks if a given square is occupied.
     pub fn get_square(&self, square: Square) -> bool {
         let index = Self::new_from_square(square);
@@ -1,4 +1,5 @@
 pub mod bitboard;
 pub mod moove;
+pub mod move_list;
 pub mod piece;
 pub mod square;
\ No newline at end of file
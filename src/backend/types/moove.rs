@@ -1,9 +1,12 @@
 use std::cmp::Ordering;
 use std::fmt::{Display, Formatter};
 use crate::backend::types::piece::{Piece, PROMOTABLE_PIECES};
-use crate::backend::types::square::{get_file, square_to_string, Square};
+use crate::backend::types::square::{square_to_string, Square};
 
-#[derive(Copy, Clone)]
+const PROMOTION_FLAG: u16 = 1 << 14;
+const KING_TAKES_ROOK_FLAG: u16 = 1 << 15;
+
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub enum CastleType {
     Long,
     Short,
@@ -24,7 +27,8 @@ impl CastleType {
 /// The first six are for the from index, the next six for the to index, leaving us with 4 bits remaining.
 /// Two of those are used to encode the type of promotion piece. Either Rook, Knight, Bishop, or Queen
 /// The next stores whether promotion has occurred
-/// One bit and thus three possible data points free!
+/// The last one marks Chess960 castles, which are encoded as the king capturing its own rook.
+/// Standard castles keep the usual encoding of the king moving two squares, e.g. e1g1.
 #[derive(Copy, Clone, Debug, Eq, PartialEq)]
 pub struct Moove {
     bitfield: u16
@@ -39,7 +43,23 @@ impl Moove {
     }
 
     pub fn new_promotion(from: Square, to: Square, promotion_type: Piece) -> Moove {
-        Moove { bitfield: from as u16 | ((to as u16) << 6) | (promotion_type as u16) << 12 | 1 << 14 }
+        Moove { bitfield: from as u16 | ((to as u16) << 6) | (promotion_type as u16) << 12 | PROMOTION_FLAG }
+    }
+
+    /// Creates a castle that is encoded as the king moving onto the square of its own rook.
+    /// This is needed in Chess960, where the king may not move at all or only by one square when castling.
+    pub const fn new_king_takes_rook(king_square: Square, rook_square: Square) -> Moove {
+        Moove { bitfield: king_square as u16 | ((rook_square as u16) << 6) | KING_TAKES_ROOK_FLAG }
+    }
+
+    /// Returns the raw 16 bit encoding, e.g. to store the move in the transposition table.
+    pub const fn to_bits(&self) -> u16 {
+        self.bitfield
+    }
+
+    /// The inverse of `to_bits`. This does not check if the bits form a valid move.
+    pub const fn from_bits(bitfield: u16) -> Moove {
+        Moove { bitfield }
     }
 
     pub fn get_from(&self) -> Square {
@@ -53,8 +73,7 @@ impl Moove {
     }
 
     pub fn get_promotion_type(&self) -> Option<Piece> {
-        let promo_mask = 0b0100_0000_0000_0000u16;
-        if (self.bitfield & promo_mask) == 0 {
+        if (self.bitfield & PROMOTION_FLAG) == 0 {
             return None;
         }
 
@@ -69,13 +88,21 @@ impl Moove {
         self.get_from().abs_diff(self.get_to()) == 16
     }
 
-    /// This assumes that the moved piece is a king and only checks if the file changed by 2.
+    /// This assumes that the moved piece is a king.
+    /// It is either a Chess960 castle or the file changed by 2.
     pub fn is_castle(&self) -> bool {
-        self.get_from().abs_diff(self.get_to()) == 2
+        self.is_king_takes_rook() || self.get_from().abs_diff(self.get_to()) == 2
     }
 
+    /// Checks if this is a castle created with `new_king_takes_rook`. The to square is then the rook's square.
+    pub fn is_king_takes_rook(&self) -> bool {
+        (self.bitfield & KING_TAKES_ROOK_FLAG) != 0
+    }
+
+    /// This assumes that the move is a castle.
+    /// The king and the rook both move towards the h file when castling short, no matter how the move is encoded.
     pub fn get_castle_type(&self) -> CastleType {
-        if get_file(self.get_to()) == 6 {
+        if self.get_to() > self.get_from() {
             CastleType::Short
         } else {
             CastleType::Long
@@ -112,25 +139,13 @@ impl PartialOrd for Moove {
 }
 
 impl Ord for Moove {
+    /// Orders by the from square, then by the to square.
+    /// The promotion and the flags break ties, so only equal moves compare as equal.
     fn cmp(&self, other: &Self) -> Ordering {
-        let own_from = self.get_from();
-        let other_from = other.get_from();
-
-        let own_to = self.get_to();
-        let other_to = self.get_to();
-
-        if own_from > other_from {
-            return Ordering::Greater;
-        } else if own_from < other_from {
-            return Ordering::Less;
-        }
-
-        if own_to > other_to {
-            return Ordering::Greater;
-        } else if own_to < other_to {
-            return Ordering::Less;
-        }
-        Ordering::Equal
+        self.get_from()
+            .cmp(&other.get_from())
+            .then_with(|| self.get_to().cmp(&other.get_to()))
+            .then_with(|| self.bitfield.cmp(&other.bitfield))
     }
 }
 
@@ -0,0 +1,168 @@
+use crate::backend::types::moove::Moove;
+use std::fmt::{Debug, Formatter};
+use std::ops::Index;
+
+/// No legal chess position has more than 218 moves, 256 leaves some room for pseudo legal moves.
+pub const MAX_MOVES: usize = 256;
+
+/// A list of moves with a fixed capacity that lives entirely on the stack.
+/// The move gen writes into it, so generating moves never allocates.
+///
+/// Every move has a score slot next to it, which can be used for move ordering.
+/// If it is not used, all scores simply stay 0.
+#[derive(Clone)]
+pub struct MoveList {
+    moves: [Moove; MAX_MOVES],
+    scores: [i32; MAX_MOVES],
+    len: usize,
+}
+
+impl MoveList {
+    /// Creates a new empty `MoveList`.
+    /// This can't be converted to a default variant because I need it to be const.
+    #[allow(clippy::new_without_default)]
+    pub const fn new() -> MoveList {
+        MoveList {
+            moves: [Moove::new(0, 0); MAX_MOVES],
+            scores: [0; MAX_MOVES],
+            len: 0,
+        }
+    }
+
+    /// Appends a move with a score of 0.
+    pub fn push(&mut self, moove: Moove) {
+        self.push_with_score(moove, 0);
+    }
+
+    /// Appends a move together with its score.
+    pub fn push_with_score(&mut self, moove: Moove, score: i32) {
+        debug_assert!(self.len < MAX_MOVES, "MoveList is full");
+        self.moves[self.len] = moove;
+        self.scores[self.len] = score;
+        self.len += 1;
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn get(&self, index: usize) -> Moove {
+        self.as_slice()[index]
+    }
+
+    pub fn get_score(&self, index: usize) -> i32 {
+        self.scores[..self.len][index]
+    }
+
+    pub fn set_score(&mut self, index: usize, score: i32) {
+        self.scores[..self.len][index] = score;
+    }
+
+    pub fn contains(&self, moove: Moove) -> bool {
+        self.as_slice().contains(&moove)
+    }
+
+    pub fn as_slice(&self) -> &[Moove] {
+        &self.moves[..self.len]
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Moove> {
+        self.as_slice().iter()
+    }
+
+    /// Sorts the moves by their `Ord` implementation. The scores stay attached to their moves.
+    pub fn sort(&mut self) {
+        self.insertion_sort_by(|list, a, b| list.moves[a] < list.moves[b]);
+    }
+
+    /// Sorts the moves by their score, highest score first. Moves with equal scores keep their order.
+    pub fn sort_by_score(&mut self) {
+        self.insertion_sort_by(|list, a, b| list.scores[a] > list.scores[b]);
+    }
+
+    /// Keeps only the moves for which `keep` returns true. The scores stay attached to their moves.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Moove) -> bool) {
+        let mut new_len = 0;
+        for index in 0..self.len {
+            if keep(&self.moves[index]) {
+                self.moves[new_len] = self.moves[index];
+                self.scores[new_len] = self.scores[index];
+                new_len += 1;
+            }
+        }
+        self.len = new_len;
+    }
+
+    /// Move lists are short, so a simple (and stable) insertion sort is good enough.
+    /// `is_before(list, a, b)` returns true if the entry at index `a` belongs in front of the one at index `b`.
+    fn insertion_sort_by(&mut self, is_before: impl Fn(&MoveList, usize, usize) -> bool) {
+        for index in 1..self.len {
+            let mut current = index;
+            while current > 0 && is_before(self, current, current - 1) {
+                self.moves.swap(current, current - 1);
+                self.scores.swap(current, current - 1);
+                current -= 1;
+            }
+        }
+    }
+}
+
+impl Index<usize> for MoveList {
+    type Output = Moove;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.as_slice()[index]
+    }
+}
+
+impl<'a> IntoIterator for &'a MoveList {
+    type Item = &'a Moove;
+    type IntoIter = std::slice::Iter<'a, Moove>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl IntoIterator for MoveList {
+    type Item = Moove;
+    type IntoIter = MoveListIntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        MoveListIntoIter {
+            move_list: self,
+            index: 0,
+        }
+    }
+}
+
+/// Iterates over the moves of a `MoveList` by value.
+pub struct MoveListIntoIter {
+    move_list: MoveList,
+    index: usize,
+}
+
+impl Iterator for MoveListIntoIter {
+    type Item = Moove;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.index >= self.move_list.len {
+            return None;
+        }
+
+        let moove = self.move_list.moves[self.index];
+        self.index += 1;
+        Some(moove)
+    }
+}
+
+/// Only prints the moves that are actually in the list.
+impl Debug for MoveList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
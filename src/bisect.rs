@@ -0,0 +1,198 @@
+use crate::get_perft_thread_count;
+use mouse::State;
+use mouse::backend::perft_bisect::{Bisection, Divide, bisect};
+use mouse::try_moove_from_uci_notation;
+use std::io::{BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+// --------------------------------------------- //
+// BISECT
+// Automates what one would do by hand with perftree: Compare divide results with a reference and descend into the
+// first move with a different node count until the broken position is found.
+// --------------------------------------------- //
+
+const BISECT_USAGE: &str =
+    "usage: Mouse bisect <depth> <fen> (--engine <path> | --divide-file <path>)
+A divide file only holds the divide of <fen>, so only the root is compared with it.";
+
+/// Where the expected divide results come from.
+enum Reference {
+    /// Any uci engine that supports `go perft`, e.g. Stockfish. It's asked for every position on the way down.
+    Engine(UciEngine),
+    /// A file with the divide of the starting position. Since there is nothing to compare deeper positions with,
+    /// the bisection stops after one step.
+    DivideFile(Divide),
+}
+
+/// Runs `Mouse bisect` and returns the exit code: 0 if no bug was found, 1 if one was found and 2 on errors.
+pub fn run_bisect(arguments: &[String]) -> i32 {
+    match try_run_bisect(arguments) {
+        Ok(bisection) => {
+            print_bisection(&bisection);
+            if bisection.is_match() { 0 } else { 1 }
+        }
+        Err(error) => {
+            eprintln!("{}", error);
+            2
+        }
+    }
+}
+
+fn try_run_bisect(arguments: &[String]) -> Result<Bisection, String> {
+    let [depth, fen, flag, path] = arguments else {
+        return Err(BISECT_USAGE.to_string());
+    };
+    let depth = depth
+        .parse::<u8>()
+        .map_err(|_| format!("invalid depth {}\n{}", depth, BISECT_USAGE))?;
+    let state = State::try_from_fen(fen).map_err(|error| format!("invalid fen: {}", error))?;
+
+    let mut reference = match flag.as_str() {
+        "--engine" => {
+            Reference::Engine(UciEngine::start(path, state.castling_config.is_chess960())?)
+        }
+        "--divide-file" => {
+            let content = std::fs::read_to_string(path)
+                .map_err(|error| format!("could not read {}: {}", path, error))?;
+            Reference::DivideFile(parse_divide(&content))
+        }
+        _ => return Err(BISECT_USAGE.to_string()),
+    };
+
+    let root_key = state.zobrist_key;
+    let threads = get_perft_thread_count();
+    let bisection = bisect(
+        &state,
+        depth,
+        threads,
+        |state, depth| match &mut reference {
+            Reference::Engine(engine) => engine.divide(state, depth).map(Some),
+            Reference::DivideFile(divide) => {
+                Ok((state.zobrist_key == root_key).then(|| divide.clone()))
+            }
+        },
+    )?;
+    bisection.ok_or_else(|| "the reference did not return a divide".to_string())
+}
+
+fn print_bisection(bisection: &Bisection) {
+    if bisection.is_match() {
+        println!("No mismatch found at depth {}.", bisection.depth);
+        return;
+    }
+
+    let moves: Vec<String> = bisection
+        .moves
+        .iter()
+        .map(|moove| moove.to_string())
+        .collect();
+    println!("Position: {}", bisection.fen);
+    println!("Moves from the start: {}", moves.join(" "));
+    println!("Depth: {}", bisection.depth);
+    for (uci_move, nodes) in &bisection.missing_moves {
+        println!("Missing move: {} (reference: {})", uci_move, nodes);
+    }
+    for (uci_move, nodes) in &bisection.extra_moves {
+        println!("Extra move: {} (ours: {})", uci_move, nodes);
+    }
+    for (uci_move, nodes, reference_nodes) in &bisection.count_mismatches {
+        println!(
+            "Different count: {} (ours: {}, reference: {})",
+            uci_move, nodes, reference_nodes
+        );
+    }
+    // Without missing or extra moves, the reference could not look any deeper.
+    if !bisection.is_minimal() {
+        println!(
+            "Continue from the position after the first of these moves to get closer to the bug."
+        );
+    }
+}
+
+/// Reads divide output. Both the format of `go perft` (`e2e4: 20`) and perftree (`e2e4 20`) work.
+/// Lines that don't start with a move, like the total, are ignored.
+fn parse_divide(content: &str) -> Divide {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut tokens = line
+                .split(|character: char| character == ':' || character.is_whitespace())
+                .filter(|token| !token.is_empty());
+            let uci_move = tokens.next()?;
+            try_moove_from_uci_notation(uci_move).ok()?;
+            let nodes = tokens.next()?.parse::<u64>().ok()?;
+            Some((uci_move.to_string(), nodes))
+        })
+        .collect()
+}
+
+struct UciEngine {
+    process: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl UciEngine {
+    /// Starts the engine. With `chess960`, it's told to expect Chess960 castling and to write castles like Mouse does.
+    fn start(path: &str, chess960: bool) -> Result<UciEngine, String> {
+        let mut process = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .map_err(|error| format!("could not start {}: {}", path, error))?;
+        let stdin = process.stdin.take().unwrap();
+        let stdout = BufReader::new(process.stdout.take().unwrap());
+
+        let mut engine = UciEngine {
+            process,
+            stdin,
+            stdout,
+        };
+        engine.send("uci")?;
+        engine.read_until(|line| line == "uciok")?;
+        if chess960 {
+            engine.send("setoption name UCI_Chess960 value true")?;
+        }
+        Ok(engine)
+    }
+
+    fn divide(&mut self, state: &State, depth: u8) -> Result<Divide, String> {
+        self.send(&format!("position fen {}", state.to_fen()))?;
+        self.send(&format!("go perft {}", depth))?;
+        let lines = self.read_until(|line| line.starts_with("Nodes searched"))?;
+        Ok(parse_divide(&lines.join("\n")))
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.stdin, "{}", command)
+            .and_then(|_| self.stdin.flush())
+            .map_err(|error| format!("could not talk to the reference engine: {}", error))
+    }
+
+    /// Returns all lines before the one `is_last_line` matches.
+    fn read_until(&mut self, is_last_line: impl Fn(&str) -> bool) -> Result<Vec<String>, String> {
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            let bytes = self
+                .stdout
+                .read_line(&mut line)
+                .map_err(|error| format!("could not talk to the reference engine: {}", error))?;
+            if bytes == 0 {
+                return Err("the reference engine quit unexpectedly".to_string());
+            }
+            let line = line.trim();
+            if is_last_line(line) {
+                return Ok(lines);
+            }
+            lines.push(line.to_string());
+        }
+    }
+}
+
+impl Drop for UciEngine {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.process.wait();
+    }
+}
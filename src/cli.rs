@@ -0,0 +1,296 @@
+use crate::{bisect, get_perft_thread_count, uci};
+use mouse::backend::perft::{perft_divide_parallel, perft_parallel};
+use mouse::moove::Moove;
+use mouse::{
+    DEFAULT_TT_SIZE_MB, SearchLimits, State, TranspositionTable, get_legal_moves, parse_san,
+    parse_uci, search, to_san,
+};
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+// --------------------------------------------- //
+// COMMAND LINE
+// Everything is parsed by hand, so the binary stays free of dependencies.
+// --------------------------------------------- //
+
+const USAGE: &str = "usage: Mouse [<command> [<arguments>]]
+
+Without a command, Mouse speaks uci over stdin and stdout.
+
+Commands:
+  uci                                  Speak uci over stdin and stdout.
+  perft <depth> [--fen <fen>] [--moves <move>...] [--divide] [--threads <count>]
+                                       Count the leaf nodes of the move tree.
+  perftree <depth> <fen> [<moves>]     The entry point for https://github.com/agausmann/perftree.
+  bench [<depth>]                      Search a fixed set of positions and print the nodes and nps.
+  fen <fen>                            Check a fen and print it in its normalized form.
+  moves [--fen <fen>] [--moves <move>...] [--san]
+                                       List the legal moves in uci or san notation.
+  bisect <depth> <fen> (--engine <path> | --divide-file <path>)
+                                       Find the position in which the move gen differs from a reference.
+                                       A divide file is only compared at the root, an engine at every ply.
+  help                                 Print this message.
+
+Moves can be given in uci or san notation. The fen defaults to the starting position.";
+
+/// Exit code for a wrong command line, like a missing argument or an invalid fen.
+const USAGE_ERROR: i32 = 2;
+
+const DEFAULT_BENCH_DEPTH: u8 = 6;
+/// A mix of openings, middlegames and endgames, so a change to the search shows up in the node count.
+const BENCH_FENS: [&str; 8] = [
+    "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+    "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+    "8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1",
+];
+
+/// Runs the command given on the command line and returns the exit code.
+pub fn run(arguments: &[String]) -> i32 {
+    let Some((command, arguments)) = arguments.split_first() else {
+        uci::run_uci();
+        return 0;
+    };
+
+    let result = match command.as_str() {
+        "uci" => {
+            uci::run_uci();
+            Ok(0)
+        }
+        "perft" => run_perft(arguments),
+        "perftree" => run_perftree_debug(arguments),
+        "bench" => run_bench(arguments),
+        "fen" => run_fen(arguments),
+        "moves" => run_moves(arguments),
+        "bisect" => Ok(bisect::run_bisect(arguments)),
+        "help" | "-h" | "--help" => {
+            println!("{}", USAGE);
+            Ok(0)
+        }
+        _ => Err(format!("unknown command {}", command)),
+    };
+
+    result.unwrap_or_else(|error| {
+        eprintln!("{}\n\n{}", error, USAGE);
+        USAGE_ERROR
+    })
+}
+
+/// The options `perft` and `moves` share.
+struct Options {
+    state: State,
+    divide: bool,
+    san: bool,
+    threads: usize,
+}
+
+/// Parses `[--fen <fen>] [--moves <move>...] [--divide] [--san] [--threads <count>]` in any order.
+/// The moves are applied after the whole command line was read, so `--moves` may come before `--fen`.
+fn parse_options(arguments: &[String], allowed_flags: &[&str]) -> Result<Options, String> {
+    let mut fen = "startpos";
+    let mut moves: Vec<&str> = Vec::new();
+    let mut divide = false;
+    let mut san = false;
+    let mut threads = get_perft_thread_count();
+
+    let mut arguments = arguments.iter().peekable();
+    while let Some(flag) = arguments.next() {
+        if !allowed_flags.contains(&flag.as_str()) {
+            return Err(format!("unexpected argument {}", flag));
+        }
+        match flag.as_str() {
+            "--fen" => {
+                fen = arguments
+                    .next()
+                    .ok_or("expected a fen after --fen")?
+                    .as_str();
+            }
+            "--moves" => {
+                // A single argument like "e2e4 e7e5" works as well as one argument per move.
+                while let Some(argument) = arguments.next_if(|argument| !argument.starts_with("--"))
+                {
+                    moves.extend(argument.split_whitespace());
+                }
+            }
+            "--threads" => {
+                threads = arguments
+                    .next()
+                    .and_then(|threads| threads.parse::<usize>().ok())
+                    .filter(|&threads| threads > 0)
+                    .ok_or("expected a positive thread count after --threads")?;
+            }
+            "--divide" => divide = true,
+            "--san" => san = true,
+            _ => unreachable!(),
+        }
+    }
+
+    Ok(Options {
+        state: parse_state(fen, &moves)?,
+        divide,
+        san,
+        threads,
+    })
+}
+
+fn parse_state(fen: &str, moves: &[&str]) -> Result<State, String> {
+    let mut state = State::try_from_fen(fen).map_err(|error| format!("invalid fen: {}", error))?;
+    for notation in moves {
+        let moove = parse_legal_move(&state, notation)
+            .ok_or_else(|| format!("illegal move {} in {}", notation, state.to_fen()))?;
+        state.make_move(moove);
+    }
+    Ok(state)
+}
+
+/// Accepts both uci and san notation. Castles may be written as the king capturing its own rook, as in Chess960.
+fn parse_legal_move(state: &State, notation: &str) -> Option<Moove> {
+    parse_uci(state, notation).or_else(|| parse_san(state, notation).ok())
+}
+
+fn parse_depth(depth: Option<&String>) -> Result<u8, String> {
+    let depth = depth.ok_or("expected a depth")?;
+    depth
+        .parse::<u8>()
+        .map_err(|_| format!("invalid depth {}", depth))
+}
+
+/// `perft <depth> [--fen <fen>] [--moves <move>...] [--divide] [--threads <count>]`
+fn run_perft(arguments: &[String]) -> Result<i32, String> {
+    let depth = parse_depth(arguments.first())?;
+    let options = parse_options(
+        &arguments[1..],
+        &["--fen", "--moves", "--divide", "--threads"],
+    )?;
+
+    let now = Instant::now();
+    let nodes = if options.divide {
+        let mut nodes = 0;
+        for (moove, nodes_for_this_move) in
+            perft_divide_parallel(&options.state, depth, options.threads)
+        {
+            nodes += nodes_for_this_move;
+            println!("{}: {}", moove, nodes_for_this_move);
+        }
+        println!();
+        nodes
+    } else {
+        perft_parallel(&options.state, depth, options.threads)
+    };
+    let elapsed = now.elapsed();
+
+    println!("Nodes searched: {}", nodes);
+    println!("Time: {}ms", elapsed.as_millis());
+    println!(
+        "Nodes per second: {:.0}",
+        nodes as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    Ok(0)
+}
+
+// --------------------------------------------- //
+// PERFTREE DEBUGGING
+// https://github.com/agausmann/perftree
+// --------------------------------------------- //
+
+/// `perftree <depth> <fen> [<moves>]`, where the moves are a single argument separated by spaces.
+fn run_perftree_debug(arguments: &[String]) -> Result<i32, String> {
+    let depth = parse_depth(arguments.first())?;
+    let fen = arguments.get(1).ok_or("expected a fen")?;
+    let moves: Vec<&str> = arguments[2..]
+        .iter()
+        .flat_map(|moves| moves.split_whitespace())
+        .collect();
+    let state = parse_state(fen, &moves)?;
+
+    root_debug_perft(&state, depth);
+    Ok(0)
+}
+
+pub fn root_debug_perft(root_state: &State, depth: u8) -> u64 {
+    // Total nodes searched.
+    let mut nodes = 0;
+
+    for (chess_move, nodes_for_this_position) in
+        perft_divide_parallel(root_state, depth, get_perft_thread_count())
+    {
+        nodes += nodes_for_this_position;
+        // print info for https://github.com/agausmann/perftree
+        println!("{} {:?}", chess_move, nodes_for_this_position);
+    }
+
+    println!();
+    println!("{:?}", nodes);
+    nodes
+}
+
+/// `bench [<depth>]`
+/// The last line has the format OpenBench expects: `<nodes> nodes <nps> nps`.
+fn run_bench(arguments: &[String]) -> Result<i32, String> {
+    let depth = match arguments {
+        [] => DEFAULT_BENCH_DEPTH,
+        [depth] => parse_depth(Some(depth))?,
+        _ => return Err(format!("unexpected argument {}", arguments[1])),
+    };
+
+    let tt = TranspositionTable::new(DEFAULT_TT_SIZE_MB);
+    let stop = AtomicBool::new(false);
+    let limits = SearchLimits {
+        depth: Some(depth),
+        ..SearchLimits::default()
+    };
+
+    let mut nodes = 0;
+    let mut elapsed = Duration::ZERO;
+    for fen in BENCH_FENS {
+        // Every position starts with an empty table, so the node count does not depend on the order.
+        tt.clear();
+        let result = search(&State::new_from_fen(fen), &limits, &tt, &stop, |_| {});
+        println!("{}: {} nodes", fen, result.nodes);
+        nodes += result.nodes;
+        elapsed += result.elapsed;
+    }
+
+    println!();
+    println!(
+        "{} nodes {:.0} nps",
+        nodes,
+        nodes as f64 / elapsed.as_secs_f64().max(f64::EPSILON)
+    );
+    Ok(0)
+}
+
+/// `fen <fen>` prints the fen the way Mouse writes it. The exit code is 1 if the fen is invalid.
+/// The fields may be given as separate arguments, so quoting is optional.
+fn run_fen(arguments: &[String]) -> Result<i32, String> {
+    if arguments.is_empty() {
+        return Err("expected a fen".to_string());
+    }
+    match State::try_from_fen(&arguments.join(" ")) {
+        Ok(state) => {
+            println!("{}", state.to_fen());
+            Ok(0)
+        }
+        Err(error) => {
+            eprintln!("invalid fen: {}", error);
+            Ok(1)
+        }
+    }
+}
+
+/// `moves [--fen <fen>] [--moves <move>...] [--san]` prints one legal move per line.
+fn run_moves(arguments: &[String]) -> Result<i32, String> {
+    let options = parse_options(arguments, &["--fen", "--moves", "--san"])?;
+    for moove in get_legal_moves(&options.state) {
+        if options.san {
+            println!("{}", to_san(&options.state, moove));
+        } else {
+            println!("{}", moove);
+        }
+    }
+    Ok(0)
+}
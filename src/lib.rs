@@ -1,8 +1,32 @@
 pub mod backend;
 
 pub use backend::types::moove;
-pub use backend::movegen::move_gen::get_pseudo_legal_moves;
+pub use backend::types::move_list;
+pub use backend::movegen::move_gen::{
+    MoveGenStage, get_legal_captures, get_legal_evasions, get_legal_moves, get_legal_quiets,
+    get_pseudo_legal_moves,
+};
+pub use backend::movegen::check_decider::is_in_check;
+pub use backend::game_state::fen_parser::{
+    FenError, FenField, UciNotationError, try_moove_from_uci_notation,
+    try_square_from_uci_notation,
+};
 pub use backend::types::bitboard;
-pub use backend::game_state::state::State;
+pub use backend::game_state::state::{State, Undo};
+pub use backend::game_state::game_status::{GameStatus, Outcome, Termination};
+pub use backend::notation::epd::{Epd, EpdError, EpdErrorKind, EpdOperation, parse_epd};
+pub use backend::notation::pgn::{
+    PgnAnnotations, PgnError, PgnErrorKind, PgnGame, PgnReader, PgnVariation, parse_pgn,
+};
+pub use backend::notation::san::{SanError, parse_san, to_san};
+pub use backend::notation::uci::{parse_uci, to_uci};
+pub use backend::eval::evaluation::evaluate;
+pub use backend::search::alpha_beta::{
+    MATE_SCORE, SearchLimits, SearchResult, get_mate_distance, search,
+};
+pub use backend::search::time_manager::{Clock, TimeManager};
+pub use backend::search::transposition_table::{
+    Bound, DEFAULT_TT_SIZE_MB, TTEntry, TranspositionTable,
+};
 pub use backend::types::piece;
 pub use backend::types::square;
@@ -1,72 +1,18 @@
-use crate::backend::movegen::check_decider::is_in_check;
-use crate::backend::movegen::move_gen::get_pseudo_legal_moves;
-use crate::backend::perft::perft;
-use crate::backend::game_state::fen_parser::moove_from_uci_notation;
-use crate::backend::game_state::state::State;
-use std::env;
-use std::env::Args;
+mod bisect;
+mod cli;
+mod uci;
 
-mod backend;
+use std::env;
+use std::process;
+use std::thread;
 
 fn main() {
-    let args = env::args();
-    run_perftree_debug(args);
+    // Without arguments, we talk uci with a gui, since that is how guis start engines.
+    let arguments: Vec<String> = env::args().skip(1).collect();
+    process::exit(cli::run(&arguments));
 }
 
-// --------------------------------------------- //
-// PERFTREE DEBUGGING
-// https://github.com/agausmann/perftree
-// --------------------------------------------- //
-
-pub fn run_perftree_debug(mut input: Args) {
-    // Remove the first useless input.
-    input.next();
-
-    let depth = input.next().unwrap();
-    let depth = depth.parse::<i32>().unwrap();
-
-    let fen = &input.next().unwrap();
-    let mut state = State::new_from_fen(fen);
-
-    for mooves in input {
-        // Code golfing
-        mooves
-            .split_whitespace()
-            .map(moove_from_uci_notation)
-            .for_each(|moove| {
-                state = state.make_move(moove);
-            });
-    }
-
-    root_debug_perft(&mut state, depth as u8);
-}
-
-pub fn root_debug_perft(root_state: &mut State, depth: u8) -> u64 {
-    // Total nodes searched.
-    let mut nodes = 0;
-
-    // Generate all root moves.
-    let mut moves = get_pseudo_legal_moves(root_state);
-    // Sort them in the same way as perftree does
-    moves.sort();
-    for chess_move in moves {
-        let state = root_state.make_move(chess_move);
-        // If we are in check after making the move -> skip.
-        if is_in_check(&state, state.active_color.opposite()) {
-            // game_state.unmake_move(chess_move);
-            continue;
-        }
-
-        // Recursively calculate nodes for this position.
-        let nodes_for_this_position = perft(&state, depth - 1);
-        nodes += nodes_for_this_position;
-        // print info for https://github.com/agausmann/perftree
-        println!("{} {:?}", chess_move, nodes_for_this_position);
-
-        // game_state.unmake_move(chess_move);
-    }
-
-    println!();
-    println!("{:?}", nodes);
-    nodes
+/// Perft uses all cores the os gives us.
+pub fn get_perft_thread_count() -> usize {
+    thread::available_parallelism().map_or(1, |threads| threads.get())
 }
@@ -0,0 +1,415 @@
+use crate::get_perft_thread_count;
+use mouse::backend::perft::perft_divide_stoppable;
+use mouse::moove::Moove;
+use mouse::piece::Side;
+use mouse::{
+    Clock, DEFAULT_TT_SIZE_MB, SearchLimits, SearchResult, State, TranspositionTable,
+    get_mate_distance, parse_uci, search, to_uci,
+};
+use std::io::BufRead;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+// --------------------------------------------- //
+// UCI
+// https://backscattering.de/chess/uci/
+// --------------------------------------------- //
+
+const ENGINE_NAME: &str = "Mouse";
+const ENGINE_AUTHOR: &str = "Jan Frase";
+const MAX_TT_SIZE_MB: usize = 65536;
+const DEFAULT_MOVE_OVERHEAD_MS: u64 = 10;
+const MAX_MOVE_OVERHEAD_MS: u64 = 5000;
+
+/// Everything a `go` command can restrict the search with. All times are in milliseconds.
+#[derive(Clone, Debug, Default)]
+pub struct GoArguments {
+    pub depth: Option<u8>,
+    pub nodes: Option<u64>,
+    pub move_time: Option<u64>,
+    pub white_time: Option<u64>,
+    pub black_time: Option<u64>,
+    pub white_increment: Option<u64>,
+    pub black_increment: Option<u64>,
+    pub moves_to_go: Option<u64>,
+    /// Search until `stop` is received.
+    pub infinite: bool,
+    /// Search on the opponent's time. The time limits only start to count on `ponderhit`.
+    pub ponder: bool,
+}
+
+/// Reads uci commands from stdin until `quit` is received or stdin is closed.
+pub fn run_uci() {
+    // The reader gets its own thread, so commands are still read while the engine is busy.
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut uci = Uci::new();
+    for line in receiver {
+        if !uci.handle_command(&line) {
+            break;
+        }
+    }
+    uci.stop_search();
+}
+
+struct Uci {
+    state: State,
+    /// Shared with the search thread. It is only resized while no search is running.
+    tt: Arc<TranspositionTable>,
+    move_overhead: Duration,
+    /// Set by the `UCI_Chess960` option. Castles are then sent as the king capturing its own rook.
+    chess960: bool,
+    /// Tells the search thread to finish as soon as possible.
+    stop: Arc<AtomicBool>,
+    /// Set while the search thread ponders. `ponderhit` clears it.
+    ponder: Arc<AtomicBool>,
+    /// Whether the search thread runs `go perft` instead of a search.
+    is_running_perft: bool,
+    search_thread: Option<JoinHandle<()>>,
+}
+
+impl Uci {
+    fn new() -> Uci {
+        Uci {
+            state: State::new_from_fen("startpos"),
+            tt: Arc::new(TranspositionTable::new(DEFAULT_TT_SIZE_MB)),
+            move_overhead: Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS),
+            chess960: false,
+            stop: Arc::new(AtomicBool::new(false)),
+            ponder: Arc::new(AtomicBool::new(false)),
+            is_running_perft: false,
+            search_thread: None,
+        }
+    }
+
+    /// Executes a single command. Returns false if the engine should quit.
+    fn handle_command(&mut self, line: &str) -> bool {
+        let mut tokens = line.split_whitespace();
+        let Some(command) = tokens.next() else {
+            return true;
+        };
+        let arguments: Vec<&str> = tokens.collect();
+
+        match command {
+            "uci" => {
+                println!("id name {}", ENGINE_NAME);
+                println!("id author {}", ENGINE_AUTHOR);
+                println!(
+                    "option name Hash type spin default {} min 1 max {}",
+                    DEFAULT_TT_SIZE_MB, MAX_TT_SIZE_MB
+                );
+                println!("option name Clear Hash type button");
+                println!(
+                    "option name Move Overhead type spin default {} min 0 max {}",
+                    DEFAULT_MOVE_OVERHEAD_MS, MAX_MOVE_OVERHEAD_MS
+                );
+                println!("option name Ponder type check default false");
+                println!("option name UCI_Chess960 type check default false");
+                println!("uciok");
+            }
+            "isready" => println!("readyok"),
+            "ucinewgame" => {
+                self.stop_search();
+                self.state = State::new_from_fen("startpos");
+                self.tt.clear();
+            }
+            "position" => {
+                self.stop_search();
+                self.handle_position(&arguments);
+            }
+            "go" => {
+                self.stop_search();
+                self.handle_go(&arguments);
+            }
+            "stop" => {
+                self.stop.store(true, Ordering::Relaxed);
+                self.stop_search();
+            }
+            // The opponent played the expected move, so the search goes on, but now on our own time.
+            "ponderhit" => self.ponder.store(false, Ordering::Relaxed),
+            "setoption" => self.handle_setoption(&arguments),
+            "quit" => return false,
+            _ => println!("info string unknown command {}", command),
+        }
+        true
+    }
+
+    /// `position [startpos | fen <fen>] [moves <move>...]`
+    fn handle_position(&mut self, arguments: &[&str]) {
+        let moves_index = arguments
+            .iter()
+            .position(|argument| *argument == "moves")
+            .unwrap_or(arguments.len());
+
+        let state = match arguments.first() {
+            Some(&"startpos") => State::try_from_fen("startpos"),
+            Some(&"fen") => State::try_from_fen(&arguments[1..moves_index].join(" ")),
+            _ => {
+                println!("info string expected startpos or fen");
+                return;
+            }
+        };
+        let mut state = match state {
+            Ok(state) => state,
+            Err(error) => {
+                println!("info string invalid fen: {}", error);
+                return;
+            }
+        };
+
+        for uci_move in arguments.iter().skip(moves_index + 1) {
+            let Some(moove) = parse_legal_move(&state, uci_move) else {
+                // Keep the position up to the last legal move, so the gui notices that something is off.
+                println!("info string illegal move {}", uci_move);
+                break;
+            };
+            state.make_move(moove);
+        }
+
+        self.state = state;
+    }
+
+    /// `go perft <depth>` or `go [depth <x>] [movetime <x>] [wtime <x>] ...`
+    /// Both run on the search thread, so `stop` is still read while they are busy.
+    fn handle_go(&mut self, arguments: &[&str]) {
+        let chess960 = self.chess960;
+        let state = self.state.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        self.stop = stop.clone();
+        self.is_running_perft = arguments.first() == Some(&"perft");
+
+        if self.is_running_perft {
+            match arguments.get(1).and_then(|depth| depth.parse::<u8>().ok()) {
+                Some(depth) => {
+                    self.search_thread = Some(thread::spawn(move || {
+                        print_perft_divide(&state, depth, chess960, &stop);
+                    }));
+                }
+                None => println!("info string expected a depth after perft"),
+            }
+            return;
+        }
+
+        let go_arguments = parse_go_arguments(arguments);
+        let mut limits = get_search_limits(&self.state, &go_arguments, self.move_overhead);
+        if go_arguments.ponder {
+            self.ponder = Arc::new(AtomicBool::new(true));
+            limits.ponder = Some(self.ponder.clone());
+        }
+        let is_infinite = go_arguments.infinite;
+        let tt = self.tt.clone();
+        self.search_thread = Some(thread::spawn(move || {
+            run_search(state, limits, is_infinite, chess960, &tt, stop);
+        }));
+    }
+
+    /// `setoption name <id> [value <x>]`
+    fn handle_setoption(&mut self, arguments: &[&str]) {
+        let value_index = arguments
+            .iter()
+            .position(|argument| *argument == "value")
+            .unwrap_or(arguments.len());
+        let name = arguments.get(1..value_index).unwrap_or_default().join(" ");
+        let value = arguments.get(value_index + 1);
+
+        match name.to_lowercase().as_str() {
+            "hash" => {
+                let Some(size_mb) = value.and_then(|value| value.parse::<usize>().ok()) else {
+                    println!("info string expected a size in MB for Hash");
+                    return;
+                };
+                self.stop_search();
+                // The search thread was joined, so this is the only reference left.
+                Arc::get_mut(&mut self.tt)
+                    .expect("the tt is still shared with a search thread")
+                    .resize(size_mb.clamp(1, MAX_TT_SIZE_MB));
+            }
+            "move overhead" => {
+                let Some(move_overhead) = value.and_then(|value| value.parse::<u64>().ok()) else {
+                    println!("info string expected a time in ms for Move Overhead");
+                    return;
+                };
+                self.move_overhead = Duration::from_millis(move_overhead.min(MAX_MOVE_OVERHEAD_MS));
+            }
+            "clear hash" => {
+                self.stop_search();
+                self.tt.clear();
+            }
+            // Only tells the gui that it may send `go ponder`, there is nothing to set up for it.
+            "ponder" => {}
+            // Chess960 positions are understood either way, this only changes how castles are written.
+            "uci_chess960" => match value.copied() {
+                Some("true") => self.chess960 = true,
+                Some("false") => self.chess960 = false,
+                _ => println!("info string expected true or false for UCI_Chess960"),
+            },
+            _ => println!("info string unknown option {}", name),
+        }
+    }
+
+    /// Stops the running search, if there is one, and waits until it sent its best move.
+    /// A perft is only stopped by `stop` itself. Every other command waits until it is done, so scripts can pipe commands.
+    fn stop_search(&mut self) {
+        if !self.is_running_perft {
+            self.stop.store(true, Ordering::Relaxed);
+        }
+        if let Some(search_thread) = self.search_thread.take() {
+            search_thread.join().unwrap();
+        }
+    }
+}
+
+/// Returns the move if it is a valid uci move and legal in the position.
+/// Castles are accepted both as e1g1 and as e1h1, no matter if `UCI_Chess960` is set.
+fn parse_legal_move(state: &State, uci_move: &str) -> Option<Moove> {
+    parse_uci(state, uci_move)
+}
+
+fn parse_go_arguments(arguments: &[&str]) -> GoArguments {
+    let mut limits = GoArguments::default();
+
+    let mut arguments = arguments.iter();
+    while let Some(argument) = arguments.next() {
+        match *argument {
+            "infinite" => limits.infinite = true,
+            "ponder" => limits.ponder = true,
+            "depth" => limits.depth = parse_next(&mut arguments),
+            "nodes" => limits.nodes = parse_next(&mut arguments),
+            "movetime" => limits.move_time = parse_next(&mut arguments),
+            "wtime" => limits.white_time = parse_next(&mut arguments),
+            "btime" => limits.black_time = parse_next(&mut arguments),
+            "winc" => limits.white_increment = parse_next(&mut arguments),
+            "binc" => limits.black_increment = parse_next(&mut arguments),
+            "movestogo" => limits.moves_to_go = parse_next(&mut arguments),
+            _ => println!("info string ignoring {}", argument),
+        }
+    }
+
+    limits
+}
+
+fn parse_next<T: std::str::FromStr>(arguments: &mut std::slice::Iter<&str>) -> Option<T> {
+    arguments.next().and_then(|argument| argument.parse().ok())
+}
+
+fn print_perft_divide(state: &State, depth: u8, chess960: bool, stop: &AtomicBool) {
+    let Some(divide) = perft_divide_stoppable(state, depth, get_perft_thread_count(), stop) else {
+        println!("info string perft was stopped");
+        return;
+    };
+
+    let mut nodes = 0;
+    for (chess_move, nodes_for_this_move) in divide {
+        nodes += nodes_for_this_move;
+        println!(
+            "{}: {}",
+            to_uci(state, chess_move, chess960),
+            nodes_for_this_move
+        );
+    }
+
+    println!();
+    println!("Nodes searched: {}", nodes);
+}
+
+/// Turns the arguments of `go` into limits for the search. The `TimeManager` takes care of the clock.
+fn get_search_limits(
+    state: &State,
+    go_arguments: &GoArguments,
+    move_overhead: Duration,
+) -> SearchLimits {
+    let (time, increment) = match state.active_color {
+        Side::White => (go_arguments.white_time, go_arguments.white_increment),
+        Side::Black => (go_arguments.black_time, go_arguments.black_increment),
+    };
+    let clock = time.map(|time| Clock {
+        time_left: Duration::from_millis(time),
+        increment: Duration::from_millis(increment.unwrap_or(0)),
+        moves_to_go: go_arguments
+            .moves_to_go
+            .map(|moves_to_go| moves_to_go.min(u32::MAX as u64) as u32),
+    });
+
+    // An infinite search ignores all limits.
+    if go_arguments.infinite {
+        return SearchLimits::default();
+    }
+    SearchLimits {
+        depth: go_arguments.depth,
+        nodes: go_arguments.nodes,
+        move_time: go_arguments.move_time.map(Duration::from_millis),
+        clock,
+        move_overhead,
+        ponder: None,
+    }
+}
+
+/// Runs on its own thread, prints an info line per finished iteration and the best move once it is done.
+fn run_search(
+    state: State,
+    limits: SearchLimits,
+    is_infinite: bool,
+    chess960: bool,
+    tt: &TranspositionTable,
+    stop: Arc<AtomicBool>,
+) {
+    let result = search(&state, &limits, tt, &stop, |result| {
+        print_info(&state, result, chess960, tt)
+    });
+
+    // In infinite mode, the best move may only be sent after `stop`. While pondering, `ponderhit` is enough.
+    let is_pondering = || {
+        let ponder = limits.ponder.as_ref();
+        ponder.is_some_and(|ponder| ponder.load(Ordering::Relaxed))
+    };
+    while (is_infinite || is_pondering()) && !stop.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(1));
+    }
+
+    match result.best_move {
+        Some(best_move) => println!("bestmove {}", to_uci(&state, best_move, chess960)),
+        // There is no move in checkmate or stalemate. `0000` is what uci uses for a null move.
+        None => println!("bestmove 0000"),
+    }
+}
+
+fn print_info(state: &State, result: &SearchResult, chess960: bool, tt: &TranspositionTable) {
+    let score = match get_mate_distance(result.score) {
+        Some(moves) => format!("mate {}", moves),
+        None => format!("cp {}", result.score),
+    };
+    // Writing castles needs the position they are played in, so walk along the pv.
+    let mut pv_state = state.clone();
+    let mut pv: Vec<String> = Vec::new();
+    for &moove in &result.pv {
+        pv.push(to_uci(&pv_state, moove, chess960));
+        pv_state.make_move(moove);
+    }
+    let elapsed_millis = result.elapsed.as_millis();
+    let nodes_per_second = result.nodes as u128 * 1000 / elapsed_millis.max(1);
+
+    println!(
+        "info depth {} score {} nodes {} nps {} hashfull {} time {} pv {}",
+        result.depth,
+        score,
+        result.nodes,
+        nodes_per_second,
+        tt.hashfull(),
+        elapsed_millis,
+        pv.join(" ")
+    );
+}
@@ -1,6 +1,44 @@
-use mouse::State;
-use mouse::backend::perft::perft;
-use perft_fixtures::perft_fixtures::{FAST_PERFT, LONG_PERFT, NORMAL_PERFT, PerftFixture};
+use mouse::backend::perft::{
+    perft, perft_divide, perft_divide_parallel, perft_divide_stoppable, perft_hashed,
+    perft_parallel, perft_stats,
+};
+use mouse::backend::perft_bisect::{Divide, bisect};
+use mouse::bitboard::BitBoard;
+use mouse::moove::Moove;
+use mouse::move_list::MoveList;
+use mouse::piece::Side;
+use mouse::square::{Square, square_to_string};
+use mouse::{
+    Bound, Clock, Epd, EpdErrorKind, EpdOperation, FenError, FenField, GameStatus, MATE_SCORE,
+    Outcome, PgnErrorKind, PgnGame, PgnReader, SanError, SearchLimits, State, Termination,
+    TimeManager, TranspositionTable, evaluate, get_legal_captures, get_legal_evasions,
+    get_legal_moves, get_legal_quiets, get_mate_distance, get_pseudo_legal_moves, is_in_check,
+    parse_epd, parse_pgn, parse_san, parse_uci, search, to_san, to_uci,
+    try_moove_from_uci_notation, try_square_from_uci_notation, UciNotationError,
+};
+use perft_fixtures::perft_fixtures::{
+    FAST_PERFT, LONG_PERFT, NORMAL_PERFT, PerftFixture, PerftStatsFixture,
+};
+use perft_fixtures::perft_suite::{
+    PERFT_SUITE_DIR, PerftSuiteBudget, PerftSuiteErrorKind, PerftSuiteFailure, load_perft_suites,
+    parse_perft_suite, run_perft_suite,
+};
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
+use std::thread;
+use std::time::Duration;
+
+/// Shorthand for moves in the tests, which only use valid notation.
+fn moove_from_uci_notation(uci_notation: &str) -> Moove {
+    try_moove_from_uci_notation(uci_notation).unwrap()
+}
+
+fn square_from_uci_notation(uci_notation: &str) -> Square {
+    try_square_from_uci_notation(uci_notation).unwrap()
+}
 
 #[test]
 fn test_perft_fast() {
@@ -20,6 +58,198 @@ fn test_perft_long() {
     test_perft_fixtures(&LONG_PERFT);
 }
 
+#[test]
+fn test_perft_hashed() {
+    for perft_fixture in FAST_PERFT.iter().chain(NORMAL_PERFT.iter()) {
+        test_hashed_perft_fixture(perft_fixture, 16);
+    }
+}
+
+#[test]
+fn test_perft_hashed_with_tiny_table() {
+    // A table with a single entry, so almost every lookup collides.
+    for perft_fixture in FAST_PERFT.iter() {
+        test_hashed_perft_fixture(perft_fixture, 0);
+    }
+}
+
+#[test]
+#[ignore]
+fn test_perft_hashed_long() {
+    for perft_fixture in LONG_PERFT.iter() {
+        test_hashed_perft_fixture(perft_fixture, 64);
+    }
+}
+
+#[test]
+fn test_perft_stats_fast() {
+    for perft_fixture in FAST_PERFT.iter() {
+        test_perft_stats_fixture(perft_fixture);
+    }
+}
+
+#[test]
+#[ignore]
+fn test_perft_stats_normal() {
+    for perft_fixture in NORMAL_PERFT.iter() {
+        test_perft_stats_fixture(perft_fixture);
+    }
+}
+
+#[test]
+#[ignore]
+fn test_perft_stats_long() {
+    for perft_fixture in LONG_PERFT.iter() {
+        test_perft_stats_fixture(perft_fixture);
+    }
+}
+
+fn test_perft_stats_fixture(perft_fixture: &PerftFixture) {
+    let state = State::new_from_fen(perft_fixture.perft_setup.fen);
+    let stats = perft_stats(&state, perft_fixture.depth);
+    let name = perft_fixture.perft_setup.name;
+
+    assert_eq!(stats.nodes, perft_fixture.expected_nodes, "{}", name);
+    let Some(expected) = &perft_fixture.expected_stats else {
+        return;
+    };
+    let PerftStatsFixture {
+        captures,
+        en_passants,
+        castles,
+        promotions,
+        checks,
+        discovered_checks,
+        double_checks,
+        checkmates,
+    } = *expected;
+    assert_eq!(stats.captures, captures, "captures in {}", name);
+    assert_eq!(stats.en_passants, en_passants, "en passants in {}", name);
+    assert_eq!(stats.castles, castles, "castles in {}", name);
+    assert_eq!(stats.promotions, promotions, "promotions in {}", name);
+    assert_eq!(stats.checks, checks, "checks in {}", name);
+    assert_eq!(
+        stats.discovered_checks, discovered_checks,
+        "discovered checks in {}",
+        name
+    );
+    assert_eq!(
+        stats.double_checks, double_checks,
+        "double checks in {}",
+        name
+    );
+    assert_eq!(stats.checkmates, checkmates, "checkmates in {}", name);
+}
+
+fn test_hashed_perft_fixture(perft_fixture: &PerftFixture, table_mb: usize) {
+    let state = State::new_from_fen(perft_fixture.perft_setup.fen);
+    assert_eq!(
+        perft_hashed(&state, perft_fixture.depth, table_mb),
+        perft_fixture.expected_nodes,
+        "{} with {} MB",
+        perft_fixture.perft_setup.name,
+        table_mb
+    );
+}
+
+#[test]
+fn test_perft_suites() {
+    test_perft_suites_with_budget(PerftSuiteBudget {
+        max_depth: 3,
+        time_limit: None,
+    });
+}
+
+#[test]
+#[ignore]
+fn test_perft_suites_long() {
+    test_perft_suites_with_budget(PerftSuiteBudget {
+        max_depth: 6,
+        time_limit: None,
+    });
+}
+
+fn test_perft_suites_with_budget(budget: PerftSuiteBudget) {
+    let entries = load_perft_suites(Path::new(PERFT_SUITE_DIR)).unwrap();
+    let report = run_perft_suite(&entries, &PerftSuiteBudget::from_env(budget), suite_perft);
+    println!("{}", report);
+    assert!(report.is_success(), "{}", report);
+}
+
+fn suite_perft(fen: &str, depth: u8) -> Result<u64, String> {
+    let state = State::try_from_fen(fen).map_err(|error| error.to_string())?;
+    Ok(perft(&state, depth))
+}
+
+#[test]
+fn test_perft_suite_reports_all_failures() {
+    let suite = "# Comment\n\
+        rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 ;D1 20 ;D2 401 ;D3 8902 ;D4 197281\n\
+        \n\
+        8/8/8/8/8/8/8/8 w - - 0 1 ;D1 0 ;D2 0\n\
+        4k3/8/8/8/8/8/8/4K2R w K - 0 1 ;D2 66 ;D1 14\n";
+    let entries = parse_perft_suite("test.epd", suite).unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[1].line_number, 4);
+    assert_eq!(entries[2].expected_nodes, vec![(2, 66), (1, 14)]);
+
+    let budget = PerftSuiteBudget {
+        max_depth: 3,
+        time_limit: None,
+    };
+    let report = run_perft_suite(&entries, &budget, suite_perft);
+    assert_eq!(report.checked, 6);
+    // D4 is too deep and the invalid position is not tried again at D2.
+    assert_eq!(report.skipped, 2);
+
+    let failures: Vec<String> = report.failures.iter().map(ToString::to_string).collect();
+    assert_eq!(failures.len(), 3);
+    assert!(matches!(
+        report.failures[0],
+        PerftSuiteFailure::InvalidPosition { .. }
+    ));
+    assert!(failures[0].starts_with("test.epd:4: 8/8/8/8/8/8/8/8 w - - 0 1: "));
+    assert_eq!(
+        failures[1],
+        "test.epd:5: 4k3/8/8/8/8/8/8/4K2R w K - 0 1 at depth 1: expected 14, got 15"
+    );
+    assert_eq!(
+        failures[2],
+        "test.epd:2: rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 at depth 2: expected 401, got 400"
+    );
+
+    // Without any time, everything is skipped.
+    let budget = PerftSuiteBudget {
+        max_depth: 3,
+        time_limit: Some(Duration::ZERO),
+    };
+    let report = run_perft_suite(&entries, &budget, suite_perft);
+    assert_eq!((report.checked, report.skipped), (0, 8));
+}
+
+#[test]
+fn test_perft_suite_parse_errors() {
+    for (suite, kind) in [
+        (";D1 20", PerftSuiteErrorKind::MissingFen),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1 ;D1 x",
+            PerftSuiteErrorKind::InvalidNodeCount("D1 x".to_string()),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1 ;D1 5 6",
+            PerftSuiteErrorKind::InvalidNodeCount("D1 5 6".to_string()),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1 ;D1 5 ;D1 5",
+            PerftSuiteErrorKind::DuplicateDepth(1),
+        ),
+    ] {
+        let error = parse_perft_suite("bad.epd", &format!("\n{}", suite)).unwrap_err();
+        assert_eq!(error.line_number, 2);
+        assert_eq!(error.kind, kind);
+    }
+}
+
 fn test_perft_fixtures(perft_fixtures: &[PerftFixture]) {
     for perft_fixture in perft_fixtures {
         test_single_perft_fixture(perft_fixture);
@@ -202,3 +432,2242 @@ fn test_perft_18() {
     let nodes = perft(&state, 1);
     assert_eq!(nodes, 7);
 }
+
+// --------------------------------------------- //
+// LEGAL MOVE GEN
+// --------------------------------------------- //
+#[test]
+fn test_legal_moves_en_passant_discovered_check() {
+    // Capturing en passant would remove both pawns from the fifth rank and expose the king to the rook.
+    let state = State::new_from_fen("8/8/8/KPp4r/8/8/8/7k w - c6 0 1");
+    let moves = get_legal_moves(&state);
+
+    assert!(!moves.iter().any(|moove| moove.to_string() == "b5c6"));
+    assert_eq!(moves.len(), 4);
+}
+
+#[test]
+fn test_legal_moves_en_passant_evasion() {
+    // The double pushed pawn gives check and can be captured en passant.
+    let state = State::new_from_fen("8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1");
+    let moves = get_legal_moves(&state);
+
+    assert!(moves.iter().any(|moove| moove.to_string() == "e4d3"));
+}
+
+#[test]
+fn test_legal_moves_pinned_pieces() {
+    let cases = [
+        // The rook is pinned along the file and may only move along it.
+        (
+            "4r2k/8/8/8/8/8/4R3/4K3 w - - 0 1",
+            ["e2e3", "e2e7", "e2e8"],
+            ["e2d2", "e2a2", "e2h2"],
+        ),
+        // The bishop is pinned along the diagonal and may only move along it.
+        (
+            "4k3/8/8/b7/8/8/3B4/4K3 w - - 0 1",
+            ["d2c3", "d2b4", "d2a5"],
+            ["d2e3", "d2h6", "d2c1"],
+        ),
+    ];
+
+    for (fen, legal_moves, illegal_moves) in cases {
+        let moves: Vec<String> = get_legal_moves(&State::new_from_fen(fen))
+            .iter()
+            .map(|moove| moove.to_string())
+            .collect();
+        for moove in legal_moves {
+            assert!(
+                moves.iter().any(|legal| legal == moove),
+                "{} {}",
+                fen,
+                moove
+            );
+        }
+        for moove in illegal_moves {
+            assert!(
+                !moves.iter().any(|legal| legal == moove),
+                "{} {}",
+                fen,
+                moove
+            );
+        }
+    }
+}
+
+#[test]
+fn test_legal_moves_match_filtered_pseudo_legal_moves() {
+    for perft_fixture in FAST_PERFT {
+        let state = State::new_from_fen(perft_fixture.perft_setup.fen);
+        compare_legal_with_pseudo_legal_moves(&state, 2);
+    }
+}
+
+fn compare_legal_with_pseudo_legal_moves(state: &State, depth: u8) {
+    let mut legal_moves: Vec<Moove> = get_legal_moves(state).into_iter().collect();
+    legal_moves.sort_by_key(|moove| moove.to_string());
+    assert_eq!(legal_moves, get_filtered_pseudo_legal_moves(state));
+
+    if depth > 1 {
+        for moove in legal_moves {
+            compare_legal_with_pseudo_legal_moves(&state.make_move_copy(moove), depth - 1);
+        }
+    }
+}
+
+/// Makes every pseudo legal move and keeps those that do not leave the king in check. Sorted by their notation.
+fn get_filtered_pseudo_legal_moves(state: &State) -> Vec<Moove> {
+    let mut filtered_moves: Vec<Moove> = get_pseudo_legal_moves(state)
+        .into_iter()
+        .filter(|moove| {
+            let next_state = state.make_move_copy(*moove);
+            !is_in_check(&next_state, state.active_color)
+        })
+        .collect();
+    filtered_moves.sort_by_key(|moove| moove.to_string());
+    filtered_moves
+}
+
+#[test]
+fn test_legal_evasions_match_filtered_pseudo_legal_moves() {
+    let fens = [
+        // A rook check that can be blocked by the knight or evaded by the king.
+        "4k3/8/8/8/8/8/3N4/r3K2R w K - 0 1",
+        // A knight check, the checker can be captured by the bishop.
+        "4k3/8/8/8/8/3n4/8/2B1K3 w - - 0 1",
+        // The pinned bishop could block the check on e3, but may not leave the pin.
+        "4r2k/8/8/b7/8/8/3B4/4K3 w - - 0 1",
+        // The double pushed pawn gives check and can be captured en passant.
+        "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+        // Double check by the rook and the knight.
+        "k3r3/8/8/8/8/3n4/8/4K2R w K - 0 1",
+    ];
+    for fen in fens {
+        let state = State::new_from_fen(fen);
+        assert!(is_in_check(&state, state.active_color), "{}", fen);
+        compare_evasions_with_pseudo_legal_moves(&state, 1);
+    }
+
+    // In a double check, only the king may move.
+    let state = State::new_from_fen("k3r3/8/8/8/8/3n4/8/4K2R w K - 0 1");
+    let evasions = get_legal_evasions(&state);
+    assert!(!evasions.is_empty());
+    assert!(
+        evasions
+            .iter()
+            .all(|moove| moove.to_string().starts_with("e1"))
+    );
+
+    for perft_fixture in FAST_PERFT {
+        let state = State::new_from_fen(perft_fixture.perft_setup.fen);
+        compare_evasions_with_pseudo_legal_moves(&state, 3);
+    }
+}
+
+fn compare_evasions_with_pseudo_legal_moves(state: &State, depth: u8) {
+    let mut evasions: Vec<Moove> = get_legal_evasions(state).into_iter().collect();
+    evasions.sort_by_key(|moove| moove.to_string());
+    if is_in_check(state, state.active_color) {
+        assert_eq!(
+            evasions,
+            get_filtered_pseudo_legal_moves(state),
+            "{}",
+            state
+        );
+    } else {
+        // Without a check there is nothing to evade.
+        assert!(evasions.is_empty(), "{}", state);
+    }
+
+    if depth > 1 {
+        for moove in get_legal_moves(state) {
+            compare_evasions_with_pseudo_legal_moves(&state.make_move_copy(moove), depth - 1);
+        }
+    }
+}
+
+#[test]
+fn test_staged_moves_match_legal_moves() {
+    for perft_fixture in FAST_PERFT {
+        let mut state = State::new_from_fen(perft_fixture.perft_setup.fen);
+        compare_staged_with_legal_moves(&mut state, 3);
+    }
+}
+
+fn compare_staged_with_legal_moves(state: &mut State, depth: u8) {
+    let legal_moves = get_legal_moves(state);
+    let captures = get_legal_captures(state);
+    let quiets = get_legal_quiets(state);
+
+    // The stages have to be disjoint and together contain every legal move.
+    let mut staged_moves: Vec<Moove> = captures.iter().chain(&quiets).copied().collect();
+    let mut sorted_legal_moves: Vec<Moove> = legal_moves.iter().copied().collect();
+    staged_moves.sort_by_key(|moove| moove.to_string());
+    sorted_legal_moves.sort_by_key(|moove| moove.to_string());
+    assert_eq!(staged_moves, sorted_legal_moves, "{}", state);
+
+    if depth > 1 {
+        for moove in legal_moves {
+            let undo = state.make_move(moove);
+            compare_staged_with_legal_moves(state, depth - 1);
+            state.unmake_move(moove, undo);
+        }
+    }
+}
+
+#[test]
+fn test_staged_moves_split_promotions() {
+    let state = State::new_from_fen("1n5k/P7/8/8/8/8/8/7K w - - 0 1");
+
+    let captures: Vec<String> = get_legal_captures(&state)
+        .iter()
+        .map(|m| m.to_string())
+        .collect();
+    let quiets: Vec<String> = get_legal_quiets(&state)
+        .iter()
+        .map(|m| m.to_string())
+        .collect();
+
+    assert_eq!(captures, ["a7a8q", "a7b8q"]);
+    assert!(quiets.contains(&"a7a8n".to_string()));
+    assert!(quiets.contains(&"a7b8r".to_string()));
+    assert!(!quiets.contains(&"a7a8q".to_string()));
+}
+
+// --------------------------------------------- //
+// MAKE / UNMAKE MOVE
+// --------------------------------------------- //
+#[test]
+fn test_make_unmake_move_restores_state() {
+    for perft_fixture in FAST_PERFT {
+        let mut state = State::new_from_fen(perft_fixture.perft_setup.fen);
+        check_make_unmake_move(&mut state, 3);
+    }
+}
+
+#[test]
+fn test_make_move_saturates_clocks() {
+    let mut state = State::new_from_fen("4k3/8/8/8/8/8/8/4K3 b - - 65535 65535");
+    let moove = moove_from_uci_notation("e8d8");
+    let undo = state.make_move(moove);
+    assert_eq!(state.half_move_clock, u16::MAX);
+    assert_eq!(state.full_move_number, u16::MAX);
+
+    state.unmake_move(moove, undo);
+    assert_eq!(state.to_fen(), "4k3/8/8/8/8/8/8/4K3 b - - 65535 65535");
+}
+
+fn check_make_unmake_move(state: &mut State, depth: u8) {
+    if depth == 0 {
+        return;
+    }
+
+    for moove in get_legal_moves(state) {
+        let previous_state = state.clone();
+
+        let undo = state.make_move(moove);
+        check_make_unmake_move(state, depth - 1);
+        state.unmake_move(moove, undo);
+
+        assert_eq!(
+            state.bb_manager, previous_state.bb_manager,
+            "Unmaking {}",
+            moove
+        );
+        assert_eq!(
+            state.irreversible_data, previous_state.irreversible_data,
+            "Unmaking {}",
+            moove
+        );
+        assert_eq!(
+            state.active_color, previous_state.active_color,
+            "Unmaking {}",
+            moove
+        );
+        assert_eq!(
+            state.half_move_clock, previous_state.half_move_clock,
+            "Unmaking {}",
+            moove
+        );
+    }
+}
+
+// --------------------------------------------- //
+// ZOBRIST HASHING
+// --------------------------------------------- //
+#[test]
+fn test_zobrist_key_transposition() {
+    let state = State::new_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+    let mut first_state = state.clone();
+    for moove in ["g1f3", "b8c6", "b1c3", "g8f6"] {
+        first_state.make_move(moove_from_uci_notation(moove));
+    }
+
+    let mut second_state = state.clone();
+    for moove in ["b1c3", "g8f6", "g1f3", "b8c6"] {
+        second_state.make_move(moove_from_uci_notation(moove));
+    }
+
+    assert_eq!(first_state.zobrist_key, second_state.zobrist_key);
+    assert_ne!(first_state.zobrist_key, state.zobrist_key);
+
+    let mut positions = HashSet::new();
+    positions.insert(first_state.clone());
+    assert!(positions.contains(&second_state));
+    assert!(!positions.contains(&state));
+
+    // A different position is not equal, even if its key collides.
+    let mut colliding_state = state.clone();
+    colliding_state.zobrist_key = first_state.zobrist_key;
+    assert_ne!(colliding_state, first_state);
+    assert!(!positions.contains(&colliding_state));
+}
+
+#[test]
+fn test_zobrist_key_ignores_uncapturable_en_passant_square() {
+    let state = State::new_from_fen("startpos");
+    let play = |moves: &[&str]| {
+        let mut state = state.clone();
+        for moove in moves {
+            state.make_move(moove_from_uci_notation(moove));
+        }
+        state
+    };
+
+    // The last double push leaves an en passant square behind, but no black pawn can capture on it.
+    let first_state = play(&["e2e4", "e7e5", "g1f3"]);
+    let second_state = play(&["g1f3", "e7e5", "e2e4"]);
+    assert_eq!(first_state.zobrist_key, second_state.zobrist_key);
+    assert_eq!(first_state, second_state);
+    let mut positions = HashSet::new();
+    positions.insert(first_state);
+    assert!(positions.contains(&second_state));
+    // The fen still contains the square.
+    assert_eq!(
+        second_state.to_fen(),
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq e3 0 2"
+    );
+
+    // If the pawn can be captured en passant, the position is a different one.
+    let en_passant_state = play(&["e2e4", "d7d5", "e4e5", "f7f5"]);
+    let without_en_passant_state =
+        State::new_from_fen("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq - 0 3");
+    assert_ne!(
+        en_passant_state.zobrist_key,
+        without_en_passant_state.zobrist_key
+    );
+    assert_ne!(en_passant_state, without_en_passant_state);
+
+    // The same goes for a pawn that could capture, but is pinned.
+    let pinned_state = State::new_from_fen("4k3/8/8/1K1pP2r/8/8/8/8 w - d6 0 1");
+    let unpinned_state = State::new_from_fen("4k3/8/8/1K1pP3/8/8/8/8 w - d6 0 1");
+    assert_eq!(
+        pinned_state.zobrist_key,
+        State::new_from_fen("4k3/8/8/1K1pP2r/8/8/8/8 w - - 0 1").zobrist_key
+    );
+    assert_ne!(
+        unpinned_state.zobrist_key,
+        State::new_from_fen("4k3/8/8/1K1pP3/8/8/8/8 w - - 0 1").zobrist_key
+    );
+}
+
+#[test]
+fn test_zobrist_key_matches_fen() {
+    // Castling, a double pawn push and a capture, compared to the same position parsed from a fen.
+    let mut state =
+        State::new_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    for moove in ["e1g1", "c7c5", "d5c6"] {
+        state.make_move(moove_from_uci_notation(moove));
+    }
+
+    let expected_state =
+        State::new_from_fen("r3k2r/p2pqpb1/bnP1pnp1/4N3/1p2P3/2N2Q1p/PPPBBPPP/R4RK1 b kq - 0 2");
+    assert_eq!(state.zobrist_key, expected_state.zobrist_key);
+}
+
+// --------------------------------------------- //
+// MOVE LIST
+// --------------------------------------------- //
+#[test]
+fn test_move_list_keeps_scores_attached() {
+    let mut moves = MoveList::new();
+    for (index, moove) in ["e2e4", "d2d4", "g1f3", "b1c3"].iter().enumerate() {
+        moves.push_with_score(moove_from_uci_notation(moove), index as i32);
+    }
+
+    moves.sort_by_score();
+    assert_eq!(moves.get(0).to_string(), "b1c3");
+    assert_eq!(moves.get_score(0), 3);
+
+    moves.retain(|moove| moove.to_string() != "g1f3");
+    assert_eq!(moves.len(), 3);
+    assert_eq!(moves.get(1).to_string(), "d2d4");
+    assert_eq!(moves.get_score(1), 1);
+
+    moves.sort();
+    assert_eq!(moves.get(0).to_string(), "b1c3");
+    assert_eq!(moves.get_score(0), 3);
+    assert!(moves.contains(moove_from_uci_notation("e2e4")));
+}
+
+#[test]
+fn test_move_list_sort() {
+    let mut moves = MoveList::new();
+    for moove in ["g1h3", "b1c3", "g1f3", "a7a8q", "b1a3", "a7a8n", "g1e2"] {
+        moves.push(moove_from_uci_notation(moove));
+    }
+
+    moves.sort();
+    let sorted: Vec<String> = moves.iter().map(|moove| moove.to_string()).collect();
+    assert_eq!(
+        sorted,
+        ["b1a3", "b1c3", "g1e2", "g1f3", "g1h3", "a7a8n", "a7a8q"]
+    );
+
+    // Only equal moves compare as equal.
+    for first in moves.iter() {
+        for second in moves.iter() {
+            assert_eq!(first.cmp(second) == Ordering::Equal, first == second);
+            assert_eq!(first.cmp(second), second.cmp(first).reverse());
+        }
+    }
+}
+
+// --------------------------------------------- //
+// FEN PARSING
+// --------------------------------------------- //
+#[test]
+fn test_fen_optional_fields_and_startpos() {
+    let start_state =
+        State::new_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1");
+
+    let without_counters =
+        State::try_from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -");
+    assert_eq!(without_counters.unwrap(), start_state);
+    assert_eq!(State::try_from_fen("startpos").unwrap(), start_state);
+
+    let parsed: State = "4k3/8/8/8/8/8/8/4K3 b - - 12".parse().unwrap();
+    assert_eq!(parsed.half_move_clock, 12);
+}
+
+#[test]
+fn test_fen_errors() {
+    let cases = [
+        ("", FenError::MissingField(FenField::PiecePlacement)),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w",
+            FenError::MissingField(FenField::CastlingRights),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1 extra",
+            FenError::TooManyFields { position: 30 },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4X3 w - - 0 1",
+            FenError::InvalidCharacter {
+                field: FenField::PiecePlacement,
+                character: 'X',
+                position: 17,
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 x - - 0 1",
+            FenError::InvalidCharacter {
+                field: FenField::ActiveColor,
+                character: 'x',
+                position: 20,
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/R3K3 w Qx - 0 1",
+            FenError::InvalidCharacter {
+                field: FenField::CastlingRights,
+                character: 'x',
+                position: 24,
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/8 w - - 0 1",
+            FenError::InvalidRankCount { count: 7 },
+        ),
+        (
+            "4k3/9/8/8/8/8/8/4K3 w - - 0 1",
+            FenError::InvalidCharacter {
+                field: FenField::PiecePlacement,
+                character: '9',
+                position: 4,
+            },
+        ),
+        (
+            "4k4/8/8/8/8/8/8/4K3 w - - 0 1",
+            FenError::InvalidRankLength {
+                rank: 8,
+                position: 2,
+            },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w - - x 1",
+            FenError::InvalidNumber {
+                field: FenField::HalfMoveClock,
+                position: 26,
+            },
+        ),
+        (
+            "4k2P/8/8/8/8/8/8/4K3 w - - 0 1",
+            FenError::PawnOnBackRank { position: 3 },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/p3K3 w - - 0 1",
+            FenError::PawnOnBackRank { position: 16 },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w - e3 0 1",
+            FenError::InvalidEnPassantSquare { position: 24 },
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w K - 0 1",
+            FenError::InvalidCastlingRight {
+                character: 'K',
+                position: 22,
+            },
+        ),
+        (
+            "8/8/8/8/8/8/8/4K3 w - - 0 1",
+            FenError::InvalidKingCount {
+                side: Side::Black,
+                count: 0,
+            },
+        ),
+        ("4k2R/8/8/8/8/8/8/4K3 w - - 0 1", FenError::OpponentInCheck),
+    ];
+
+    for (fen, expected_error) in cases {
+        assert_eq!(
+            State::try_from_fen(fen).unwrap_err(),
+            expected_error,
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn test_uci_notation_errors() {
+    assert_eq!(try_square_from_uci_notation("e4"), Ok(28));
+    assert_eq!(
+        try_moove_from_uci_notation("a7a8q"),
+        Ok(moove_from_uci_notation("a7a8q"))
+    );
+
+    for square in ["", "e", "e9", "i1", "e44"] {
+        assert_eq!(
+            try_square_from_uci_notation(square),
+            Err(UciNotationError::InvalidSquare(square.to_string()))
+        );
+    }
+    for moove in ["", "e2", "e2e9", "e2e4x", "a7a8k", "e2e4qq"] {
+        assert_eq!(
+            try_moove_from_uci_notation(moove),
+            Err(UciNotationError::InvalidMove(moove.to_string()))
+        );
+    }
+}
+
+#[test]
+fn test_fen_round_trip() {
+    for perft_fixture in FAST_PERFT.iter().chain(&NORMAL_PERFT).chain(&LONG_PERFT) {
+        let fen = perft_fixture.perft_setup.fen;
+        let state = State::new_from_fen(fen);
+        assert_eq!(state.to_fen(), fen);
+        assert_eq!(state.to_string(), fen);
+
+        check_fen_round_trip(&mut state.clone(), 2);
+    }
+}
+
+fn check_fen_round_trip(state: &mut State, depth: u8) {
+    let fen = state.to_fen();
+    let parsed_state = State::new_from_fen(&fen);
+    assert_eq!(parsed_state.to_fen(), fen);
+    assert_eq!(parsed_state.zobrist_key, state.zobrist_key, "{}", fen);
+
+    if depth == 0 {
+        return;
+    }
+    for moove in get_legal_moves(state) {
+        let undo = state.make_move(moove);
+        check_fen_round_trip(state, depth - 1);
+        state.unmake_move(moove, undo);
+    }
+}
+
+#[test]
+fn test_move_counters() {
+    let mut state = State::new_from_fen("r3k2r/8/8/8/8/8/4P3/R3K2R w KQkq - 7 12");
+
+    let mut undos = Vec::new();
+    for (uci_move, half_move_clock, full_move_number) in [
+        ("a1a8", 0, 12),
+        ("e8d7", 1, 13),
+        ("e1g1", 2, 13),
+        ("h8h2", 3, 14),
+        ("e2e4", 0, 14),
+    ] {
+        let moove = moove_from_uci_notation(uci_move);
+        undos.push((moove, state.make_move(moove)));
+        assert_eq!(state.half_move_clock, half_move_clock, "{}", uci_move);
+        assert_eq!(state.full_move_number, full_move_number, "{}", uci_move);
+    }
+    assert_eq!(state.to_fen(), "R7/3k4/8/8/4P3/8/7r/5RK1 b - e3 0 14");
+
+    while let Some((moove, undo)) = undos.pop() {
+        state.unmake_move(moove, undo);
+    }
+    assert_eq!(state.to_fen(), "r3k2r/8/8/8/8/8/4P3/R3K2R w KQkq - 7 12");
+}
+
+// --------------------------------------------- //
+// ATTACKS
+// --------------------------------------------- //
+#[test]
+fn test_attackers_to() {
+    let state = State::new_from_fen("4k3/8/8/8/3r4/8/3P4/3RK3 w - - 0 1");
+    let occupancy_bb = state.bb_manager.get_all_pieces_bb();
+    let d3 = square_from_uci_notation("d3");
+
+    let attackers_bb = state.attackers_to(d3, occupancy_bb);
+    assert_eq!(
+        attackers_bb.map(square_to_string).collect::<Vec<_>>(),
+        ["d4"]
+    );
+
+    // Without the pawn on d2, the white rook sees d3 as well.
+    let d2_bb = BitBoard::new_from_square(square_from_uci_notation("d2"));
+    let attackers_bb = state.attackers_to(d3, occupancy_bb & !d2_bb);
+    assert_eq!(
+        attackers_bb.map(square_to_string).collect::<Vec<_>>(),
+        ["d1", "d4"]
+    );
+
+    let attackers_bb = state.attackers_to(square_from_uci_notation("e3"), occupancy_bb);
+    assert_eq!(
+        attackers_bb.map(square_to_string).collect::<Vec<_>>(),
+        ["d2"]
+    );
+}
+
+#[test]
+fn test_attacks_by_and_is_square_attacked() {
+    let state = State::new_from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1");
+
+    let attacks_bb = state.attacks_by(Side::White);
+    // The rook sees the whole a file and b1 to e1, the king adds d2, e2, f2 and f1.
+    assert_eq!(attacks_bb.count_ones(), 7 + 4 + 4);
+    assert!(attacks_bb.get_square(square_from_uci_notation("a8")));
+    assert!(attacks_bb.get_square(square_from_uci_notation("e1")));
+    assert!(!attacks_bb.get_square(square_from_uci_notation("f8")));
+
+    for square in 0..64 {
+        assert_eq!(
+            state.is_square_attacked(square, Side::White),
+            attacks_bb.get_square(square),
+            "{}",
+            square_to_string(square)
+        );
+    }
+    assert!(state.is_square_attacked(square_from_uci_notation("d8"), Side::Black));
+    assert!(!state.is_square_attacked(square_from_uci_notation("d6"), Side::Black));
+}
+
+// --------------------------------------------- //
+// GAME STATUS
+// --------------------------------------------- //
+#[test]
+fn test_game_status_of_positions() {
+    let cases = [
+        (
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            None,
+        ),
+        (
+            "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3",
+            Some((Some(Side::Black), Termination::Checkmate)),
+        ),
+        (
+            "7k/5Q2/6K1/8/8/8/8/8 b - - 0 1",
+            Some((None, Termination::Stalemate)),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+            Some((None, Termination::InsufficientMaterial)),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/4KN2 w - - 0 1",
+            Some((None, Termination::InsufficientMaterial)),
+        ),
+        (
+            "2b1k3/8/8/8/8/8/8/3BKB2 w - - 0 1",
+            Some((None, Termination::InsufficientMaterial)),
+        ),
+        ("3bk3/8/8/8/8/8/8/3BK3 w - - 0 1", None),
+        ("4k3/8/8/8/8/8/8/3NKN2 w - - 0 1", None),
+        ("4k3/8/8/8/8/8/8/R3K3 w - - 99 80", None),
+        (
+            "4k3/8/8/8/8/8/8/R3K3 w - - 100 80",
+            Some((None, Termination::FiftyMoveRule)),
+        ),
+        (
+            "4k3/8/8/8/8/8/8/R3K3 w - - 150 80",
+            Some((None, Termination::SeventyFiveMoveRule)),
+        ),
+        // A checkmate on the last move before the fifty move rule still wins.
+        (
+            "R3k3/8/4K3/8/8/8/8/8 b - - 120 80",
+            Some((Some(Side::White), Termination::Checkmate)),
+        ),
+    ];
+
+    for (fen, expected) in cases {
+        let expected_status = match expected {
+            None => GameStatus::Ongoing,
+            Some((winner, reason)) => GameStatus::Over(Outcome { winner, reason }),
+        };
+        assert_eq!(
+            State::new_from_fen(fen).status(),
+            expected_status,
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn test_game_status_repetitions() {
+    let mut state = State::new_from_fen("startpos");
+    let knight_dance = ["g1f3", "g8f6", "f3g1", "f6g8"];
+
+    // The start position occurs again after every four moves.
+    for repetition in 2..=5 {
+        for uci_move in knight_dance {
+            state.make_move(moove_from_uci_notation(uci_move));
+        }
+        assert_eq!(state.repetition_count(), repetition);
+    }
+
+    let threefold = GameStatus::Over(Outcome {
+        winner: None,
+        reason: Termination::ThreefoldRepetition,
+    });
+    let fivefold = GameStatus::Over(Outcome {
+        winner: None,
+        reason: Termination::FivefoldRepetition,
+    });
+    assert_eq!(state.status_with_history(), fivefold);
+    assert_eq!(state.status(), GameStatus::Ongoing);
+
+    // Unmaking moves forgets the positions again.
+    let mut state = State::new_from_fen("startpos");
+    let mut undos = Vec::new();
+    for _ in 0..2 {
+        for uci_move in knight_dance {
+            let moove = moove_from_uci_notation(uci_move);
+            undos.push((moove, state.make_move(moove)));
+        }
+    }
+    assert_eq!(state.status_with_history(), threefold);
+    let (moove, undo) = undos.pop().unwrap();
+    state.unmake_move(moove, undo);
+    assert_eq!(state.repetition_count(), 2);
+
+    // A pawn move makes all previous positions unreachable.
+    state.make_move(moove_from_uci_notation("e7e6"));
+    for uci_move in ["g1f3", "f6g8", "f3g1", "g8f6"] {
+        state.make_move(moove_from_uci_notation(uci_move));
+    }
+    assert_eq!(state.repetition_count(), 2);
+    assert_eq!(state.status_with_history(), GameStatus::Ongoing);
+
+    // The first occurrence directly follows a double push that no pawn can capture en passant.
+    let mut state = State::new_from_fen("startpos");
+    for uci_move in ["e2e4", "e7e5"] {
+        state.make_move(moove_from_uci_notation(uci_move));
+    }
+    for _ in 0..2 {
+        for uci_move in knight_dance {
+            state.make_move(moove_from_uci_notation(uci_move));
+        }
+    }
+    assert_eq!(state.repetition_count(), 3);
+    assert_eq!(state.status_with_history(), threefold);
+}
+
+// --------------------------------------------- //
+// SAN
+// --------------------------------------------- //
+#[test]
+fn test_san_round_trip() {
+    for perft_fixture in FAST_PERFT {
+        let mut state = State::new_from_fen(perft_fixture.perft_setup.fen);
+        check_san_round_trip(&mut state, 2);
+    }
+}
+
+fn check_san_round_trip(state: &mut State, depth: u8) {
+    let legal_moves = get_legal_moves(state);
+    let mut sans = HashSet::new();
+    for moove in &legal_moves {
+        let san = to_san(state, *moove);
+        assert_eq!(parse_san(state, &san), Ok(*moove), "{} in {}", san, state);
+        assert!(sans.insert(san));
+    }
+
+    if depth > 1 {
+        for moove in legal_moves {
+            let undo = state.make_move(moove);
+            check_san_round_trip(state, depth - 1);
+            state.unmake_move(moove, undo);
+        }
+    }
+}
+
+#[test]
+fn test_to_san() {
+    let kiwipete = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    let cases = [
+        (kiwipete, "e1g1", "O-O"),
+        (kiwipete, "e1c1", "O-O-O"),
+        (kiwipete, "d5e6", "dxe6"),
+        (kiwipete, "e5f7", "Nxf7"),
+        (kiwipete, "e2a6", "Bxa6"),
+        (kiwipete, "c3b1", "Nb1"),
+        // Disambiguation by file, by rank and by both.
+        ("4k3/8/8/8/8/8/4K3/R6R w - - 0 1", "a1d1", "Rad1"),
+        ("4k3/8/8/R7/8/8/8/R3K3 w - - 0 1", "a1a3", "R1a3"),
+        ("6k1/8/8/8/Q6Q/8/8/Q3K3 w - - 0 1", "a4d4", "Qa4d4"),
+        ("6k1/8/8/8/Q6Q/8/8/Q3K3 w - - 0 1", "a1d4", "Q1d4"),
+        ("6k1/8/8/8/Q6Q/8/8/Q3K3 w - - 0 1", "h4d4", "Qhd4"),
+        // Promotions, en passant and checks.
+        ("1n5k/P7/8/8/8/8/8/7K w - - 0 1", "a7b8q", "axb8=Q+"),
+        ("1n5k/P7/8/8/8/8/8/7K w - - 0 1", "a7a8n", "a8=N"),
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1", "e5d6", "exd6"),
+        ("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", "a1a8", "Ra8#"),
+    ];
+
+    for (fen, uci_move, expected_san) in cases {
+        let state = State::new_from_fen(fen);
+        let moove = moove_from_uci_notation(uci_move);
+        assert_eq!(to_san(&state, moove), expected_san, "{}", fen);
+    }
+}
+
+#[test]
+fn test_parse_sloppy_san() {
+    let state =
+        State::new_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1");
+    let cases = [
+        ("0-0", "e1g1"),
+        ("0-0-0", "e1c1"),
+        ("Nf7", "e5f7"),
+        ("Nxf7+", "e5f7"),
+        ("de6", "d5e6"),
+        ("Ne5f7!?", "e5f7"),
+        ("e2a6", "e2a6"),
+        ("Be2xa6", "e2a6"),
+        ("g2-g4", "g2g4"),
+    ];
+    for (san, uci_move) in cases {
+        assert_eq!(
+            parse_san(&state, san),
+            Ok(moove_from_uci_notation(uci_move)),
+            "{}",
+            san
+        );
+    }
+
+    let state = State::new_from_fen("1n5k/P7/8/8/8/8/8/7K w - - 0 1");
+    assert_eq!(
+        parse_san(&state, "e8Q"),
+        Err(SanError::IllegalMove("e8Q".to_string()))
+    );
+    assert_eq!(
+        parse_san(&state, "a8Q"),
+        Ok(moove_from_uci_notation("a7a8q"))
+    );
+    assert_eq!(
+        parse_san(&state, "axb8n"),
+        Ok(moove_from_uci_notation("a7b8n"))
+    );
+
+    assert_eq!(
+        parse_san(&state, "a8"),
+        Err(SanError::IllegalMove("a8".to_string()))
+    );
+    assert_eq!(
+        parse_san(&state, "Zf3"),
+        Err(SanError::InvalidSyntax("Zf3".to_string()))
+    );
+    assert_eq!(
+        parse_san(&state, ""),
+        Err(SanError::InvalidSyntax("".to_string()))
+    );
+
+    let state = State::new_from_fen("4k3/8/8/8/8/8/4K3/R6R w - - 0 1");
+    assert_eq!(
+        parse_san(&state, "Rd1"),
+        Err(SanError::AmbiguousMove("Rd1".to_string()))
+    );
+}
+
+// --------------------------------------------- //
+// PGN
+// --------------------------------------------- //
+const OPERA_GAME_PGN: &str = r#"[Event "A Night at the Opera"]
+[Site "Paris FRA"]
+[Date "1858.??.??"]
+[White "Morphy, Paul"]
+[Black "Duke Karl / Count Isouard"]
+[Result "1-0"]
+
+1. e4 e5 2. Nf3 d6 3. d4 Bg4 {This is a weak move already.} 4. dxe5 Bxf3 5. Qxf3
+dxe5 6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 c6 9. Bg5 $6 (9. Nd5 {is stronger} (9. Be3)
+Qd8 (9... Qb4+)) 9... b5 10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7
+Rxd7 14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+! ; the queen sacrifice
+Nxb8 17. Rd8# 1-0
+"#;
+
+#[test]
+fn test_pgn_read_game() {
+    let games = parse_pgn(OPERA_GAME_PGN).unwrap();
+    assert_eq!(games.len(), 1);
+
+    let game = &games[0];
+    assert_eq!(game.get_tag("White"), Some("Morphy, Paul"));
+    assert_eq!(game.tags.len(), 6);
+    assert_eq!(game.moves.len(), 33);
+    assert_eq!(game.result, "1-0");
+    assert_eq!(
+        game.get_final_state().status(),
+        GameStatus::Over(Outcome {
+            winner: Some(Side::White),
+            reason: Termination::Checkmate,
+        })
+    );
+}
+
+#[test]
+fn test_pgn_write_game() {
+    let game = &parse_pgn(OPERA_GAME_PGN).unwrap()[0];
+    let pgn = game.to_pgn();
+
+    assert!(pgn.starts_with("[Event \"A Night at the Opera\"]\n"));
+    assert!(
+        pgn.contains("\n\n1. e4 e5 2. Nf3 d6 3. d4 Bg4 {This is a weak move already.} 4. dxe5 ")
+    );
+    assert!(pgn.ends_with("16. Qb8+ $1 {the queen sacrifice}\n16... Nxb8 17. Rd8# 1-0\n"));
+    assert!(pgn.lines().all(|line| line.len() <= 79));
+
+    let reparsed_game = &parse_pgn(&pgn).unwrap()[0];
+    assert_eq!(reparsed_game.tags, game.tags);
+    assert_eq!(reparsed_game.moves, game.moves);
+    assert_eq!(reparsed_game.annotations, game.annotations);
+    assert_eq!(reparsed_game.to_pgn(), pgn);
+}
+
+#[test]
+fn test_pgn_variations() {
+    let game = &parse_pgn(OPERA_GAME_PGN).unwrap()[0];
+
+    // 9. Bg5 $6 (9. Nd5 {is stronger} (9. Be3) Qd8 (9... Qb4+))
+    let annotations = &game.annotations[16];
+    assert_eq!(annotations.nags, vec![6]);
+    assert_eq!(annotations.variations.len(), 1);
+
+    let variation = &annotations.variations[0];
+    assert_eq!(
+        variation.moves,
+        vec![
+            moove_from_uci_notation("c3d5"),
+            moove_from_uci_notation("e7d8")
+        ]
+    );
+    assert_eq!(variation.annotations[0].comments, vec!["is stronger"]);
+    assert_eq!(
+        variation.annotations[0].variations[0].moves,
+        vec![moove_from_uci_notation("c1e3")]
+    );
+    assert_eq!(
+        variation.annotations[1].variations[0].moves,
+        vec![moove_from_uci_notation("e7b4")]
+    );
+
+    // Comments before the first move and variations of black moves survive a round trip as well.
+    let pgn = "{Start} 1. e4 e5 (1... c5 2. Nf3 (2. c3 d5 (2... Nf6)) 2... d6) 2. Nf3 *";
+    let game = &parse_pgn(pgn).unwrap()[0];
+    assert_eq!(game.comments, vec!["Start"]);
+    assert_eq!(game.annotations[1].variations[0].moves.len(), 3);
+    assert_eq!(
+        game.to_pgn(),
+        "{Start} 1. e4 e5 (1... c5 2. Nf3 (2. c3 d5 (2... Nf6)) 2... d6) 2. Nf3 *\n"
+    );
+    let reparsed_game = &parse_pgn(&game.to_pgn()).unwrap()[0];
+    assert_eq!(reparsed_game.comments, game.comments);
+    assert_eq!(reparsed_game.moves, game.moves);
+    assert_eq!(reparsed_game.annotations, game.annotations);
+
+    // Moves of variations are checked in the position before the move they replace.
+    let error = parse_pgn("1. e4 e5 (1... e4) *").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 16));
+    assert!(matches!(error.kind, PgnErrorKind::IllegalMove(_)));
+
+    let error = parse_pgn("1. e4 (1. d4 d5 (1... Nf3)) *").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 23));
+    assert!(matches!(error.kind, PgnErrorKind::IllegalMove(_)));
+}
+
+#[test]
+fn test_pgn_fen_tag() {
+    let pgn = "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]\n\n12... Kd7 13.e4 Kc6 *";
+    let game = &parse_pgn(pgn).unwrap()[0];
+    assert_eq!(game.moves.len(), 3);
+    assert_eq!(game.result, "*");
+    assert_eq!(
+        game.get_final_state().to_fen(),
+        "8/8/2k5/8/4P3/8/8/4K3 w - - 1 14"
+    );
+
+    // Written games that don't start at the starting position get the tags added.
+    let mut game = PgnGame::new(State::new_from_fen("4k3/8/8/8/8/8/4P3/4K3 b - - 0 12"));
+    game.moves.push(moove_from_uci_notation("e8d7"));
+    assert_eq!(
+        game.to_pgn(),
+        "[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 12\"]\n\n12... Kd7 *\n"
+    );
+}
+
+#[test]
+fn test_pgn_zero_castling() {
+    // Castling written with zeros must not be mistaken for a move number, neither alone nor glued to one.
+    let pgn = "1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 4.0-0 d6 5. d3 Be6 6. Nc3 Qd7 7. Be3 0-0-0 *";
+    let game = &parse_pgn(pgn).unwrap()[0];
+    assert_eq!(game.moves.len(), 14);
+    assert_eq!(game.moves[6], moove_from_uci_notation("e1g1"));
+    assert_eq!(game.moves[13], moove_from_uci_notation("e8c8"));
+}
+
+#[test]
+fn test_pgn_errors_and_recovery() {
+    let pgn = "[Event \"broken\"]\n\n1. e4 e5 2. Ke3 Nc6 *\n\n[Event \"fine\"]\n\n1. d4 d5 *\n";
+    let results: Vec<_> = PgnReader::new(pgn.as_bytes()).collect();
+    assert_eq!(results.len(), 2);
+
+    let error = results[0].as_ref().unwrap_err();
+    assert_eq!((error.line, error.column), (3, 13));
+    assert!(matches!(error.kind, PgnErrorKind::IllegalMove(_)));
+
+    let game = results[1].as_ref().unwrap();
+    assert_eq!(game.get_tag("Event"), Some("fine"));
+    assert_eq!(game.moves.len(), 2);
+
+    let error = parse_pgn("1. e4 (1. d4 e5").unwrap_err();
+    assert_eq!((error.line, error.column), (1, 7));
+    assert_eq!(error.kind, PgnErrorKind::Unterminated('('));
+
+    let error = parse_pgn("[Event \"x\"\n\n1. e4 *").unwrap_err();
+    assert_eq!(error.kind, PgnErrorKind::InvalidTag);
+}
+
+// --------------------------------------------- //
+// EPD
+// --------------------------------------------- //
+const START_EPD: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
+
+#[test]
+fn test_epd_round_trip() {
+    let wac_001 = "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";";
+    let epd = parse_epd(wac_001).unwrap();
+    assert_eq!(epd.get_id(), Some("WAC.001"));
+    assert_eq!(
+        epd.operations[0],
+        EpdOperation::BestMoves(vec![moove_from_uci_notation("g3g6")])
+    );
+    assert_eq!(epd.to_epd(), wac_001);
+    assert_eq!(epd.to_string(), wac_001);
+}
+
+#[test]
+fn test_epd_operations() {
+    let epd = parse_epd(
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq -  bm Bb5 f1c4 ;am g2g4;ce -35; \
+         acd 20; pv Bb5 a6 Ba4; c0 \"Ruy Lopez\"; c9 \"a; b\"; hmvc 2; noop; id test;",
+    )
+    .unwrap();
+
+    assert_eq!(epd.state.half_move_clock, 0);
+    assert_eq!(epd.state.full_move_number, 1);
+    assert_eq!(
+        epd.operations,
+        vec![
+            EpdOperation::BestMoves(vec![
+                moove_from_uci_notation("f1b5"),
+                moove_from_uci_notation("f1c4")
+            ]),
+            EpdOperation::AvoidMoves(vec![moove_from_uci_notation("g2g4")]),
+            EpdOperation::CentipawnEvaluation(-35),
+            EpdOperation::AnalysisCountDepth(20),
+            EpdOperation::PrincipalVariation(vec![
+                moove_from_uci_notation("f1b5"),
+                moove_from_uci_notation("a7a6"),
+                moove_from_uci_notation("b5a4")
+            ]),
+            EpdOperation::Comment(0, "Ruy Lopez".to_string()),
+            EpdOperation::Comment(9, "a; b".to_string()),
+            EpdOperation::Other {
+                opcode: "hmvc".to_string(),
+                operands: vec!["2".to_string()]
+            },
+            EpdOperation::Other {
+                opcode: "noop".to_string(),
+                operands: vec![]
+            },
+            EpdOperation::Id("test".to_string()),
+        ]
+    );
+    assert_eq!(
+        epd.get_operation("acd"),
+        Some(&EpdOperation::AnalysisCountDepth(20))
+    );
+
+    // Moves are always written in san and strings in quotes.
+    assert_eq!(
+        epd.to_epd(),
+        "r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - bm Bb5 Bc4; am g4; ce -35; acd 20; \
+         pv Bb5 a6 Ba4; c0 \"Ruy Lopez\"; c9 \"a; b\"; hmvc 2; noop; id \"test\";"
+    );
+
+    let mut epd = Epd::new(State::new_from_fen("startpos"));
+    assert_eq!(epd.to_epd(), START_EPD);
+    epd.operations.push(EpdOperation::Id("start".to_string()));
+    assert_eq!(parse_epd(&epd.to_epd()).unwrap().get_id(), Some("start"));
+}
+
+#[test]
+fn test_epd_errors() {
+    let operations_start = START_EPD.len() + 1;
+    for (operations, position, kind) in [
+        ("1bm e4;", 0, EpdErrorKind::InvalidOpcode("1bm".to_string())),
+        ("b-m e4;", 0, EpdErrorKind::InvalidOpcode("b-m".to_string())),
+        ("bm e4", 0, EpdErrorKind::MissingSemicolon("bm".to_string())),
+        (
+            "id \"x\"; id \"y\";",
+            8,
+            EpdErrorKind::DuplicateOpcode("id".to_string()),
+        ),
+        ("c0 \"never closed;", 3, EpdErrorKind::UnterminatedString),
+        (
+            "id a b;",
+            0,
+            EpdErrorKind::InvalidOperandCount {
+                opcode: "id".to_string(),
+                count: 2,
+            },
+        ),
+        (
+            "bm;",
+            0,
+            EpdErrorKind::InvalidOperandCount {
+                opcode: "bm".to_string(),
+                count: 0,
+            },
+        ),
+        (
+            "ce 1.5;",
+            0,
+            EpdErrorKind::InvalidNumber {
+                opcode: "ce".to_string(),
+                operand: "1.5".to_string(),
+            },
+        ),
+        (
+            "acd -1;",
+            0,
+            EpdErrorKind::InvalidNumber {
+                opcode: "acd".to_string(),
+                operand: "-1".to_string(),
+            },
+        ),
+        (
+            "pv e4 e4;",
+            0,
+            EpdErrorKind::IllegalMove {
+                opcode: "pv".to_string(),
+                error: SanError::IllegalMove("e4".to_string()),
+            },
+        ),
+    ] {
+        let error = parse_epd(&format!("{} {}", START_EPD, operations)).unwrap_err();
+        assert_eq!(error.kind, kind, "{}", operations);
+        assert_eq!(
+            error.position,
+            operations_start + position,
+            "{}",
+            operations
+        );
+    }
+
+    let error = parse_epd("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq").unwrap_err();
+    assert_eq!(
+        error.kind,
+        EpdErrorKind::InvalidPosition(FenError::MissingField(FenField::EnPassantSquare))
+    );
+    assert_eq!(
+        error.to_string(),
+        "invalid position: the en passant square field is missing"
+    );
+
+    // A full fen has the clocks where the first opcode should be.
+    let error = parse_epd(&format!("{} 0 1 bm e4;", START_EPD)).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        format!(
+            "at position {}: \"0\" is not a valid opcode",
+            operations_start
+        )
+    );
+}
+
+// --------------------------------------------- //
+// CHESS960
+// --------------------------------------------- //
+fn king_takes_rook(king_square: &str, rook_square: &str) -> Moove {
+    Moove::new_king_takes_rook(
+        square_from_uci_notation(king_square),
+        square_from_uci_notation(rook_square),
+    )
+}
+
+#[test]
+fn test_chess960_castling() {
+    let cases = [
+        // The king stays on g1 and only the rook moves.
+        (
+            "r5kr/8/8/8/8/8/8/R5KR w AHah - 0 1",
+            king_takes_rook("g1", "h1"),
+            "r5kr/8/8/8/8/8/8/R4RK1 b kq - 1 1",
+        ),
+        // The king moves one square and lands where the rook started.
+        (
+            "rk5r/8/8/8/8/8/8/RK5R w AHah - 0 1",
+            king_takes_rook("b1", "a1"),
+            "rk5r/8/8/8/8/8/8/2KR3R b kq - 1 1",
+        ),
+        // The king jumps over the rook.
+        (
+            "1r2k1r1/8/8/8/8/8/8/R3K1R1 b Gb - 0 1",
+            king_takes_rook("e8", "b8"),
+            "2kr2r1/8/8/8/8/8/8/R3K1R1 w K - 1 2",
+        ),
+        // Standard castles keep their encoding, even if the other rook is not in the corner.
+        (
+            "2r1kr2/8/8/8/8/8/PPPPPPPP/R2RK2R w KQk - 0 1",
+            moove_from_uci_notation("e1g1"),
+            "2r1kr2/8/8/8/8/8/PPPPPPPP/R2R1RK1 b k - 1 1",
+        ),
+    ];
+
+    for (fen, moove, expected_fen) in cases {
+        let mut state = State::new_from_fen(fen);
+        assert!(get_legal_moves(&state).contains(moove), "{} {}", fen, moove);
+
+        let undo = state.make_move(moove);
+        assert_eq!(state.to_fen(), expected_fen);
+        assert_eq!(
+            state.zobrist_key,
+            State::new_from_fen(expected_fen).zobrist_key
+        );
+        state.unmake_move(moove, undo);
+        let original_state = State::new_from_fen(fen);
+        assert_eq!(state.to_fen(), original_state.to_fen());
+        assert_eq!(state.zobrist_key, original_state.zobrist_key);
+    }
+}
+
+#[test]
+fn test_chess960_castling_legality() {
+    // The rook on b1 shields c1 from the queen, but it leaves b1 when castling long.
+    let state = State::new_from_fen("3k4/8/8/8/8/8/8/qRK4R w BH - 0 1");
+    let moves = get_legal_moves(&state);
+    assert!(!moves.contains(king_takes_rook("c1", "b1")));
+    assert!(moves.contains(king_takes_rook("c1", "h1")));
+
+    // Pieces between the king and the rook or on their destinations block castling.
+    let state = State::new_from_fen("4k3/8/8/8/8/8/8/RN2K1BR w HA - 0 1");
+    assert!(!get_legal_moves(&state).contains(king_takes_rook("e1", "a1")));
+    assert!(!get_legal_moves(&state).contains(moove_from_uci_notation("e1g1")));
+
+    // A castling rook that is captured or moves loses its castling right, the other one keeps it.
+    let mut state = State::new_from_fen("1r2k1r1/8/8/8/8/8/8/1R2K1R1 w BGbg - 0 1");
+    state.make_move(moove_from_uci_notation("b1b8"));
+    assert_eq!(state.to_fen(), "1R2k1r1/8/8/8/8/8/8/4K1R1 b Kk - 0 1");
+}
+
+#[test]
+fn test_chess960_fen() {
+    // Shredder-FEN and X-FEN describe the same position as the usual fen.
+    let standard_state = State::new_from_fen("startpos");
+    for fen in [
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w HAha - 0 1",
+        "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w AHah - 0 1",
+    ] {
+        let state = State::new_from_fen(fen);
+        assert_eq!(state.to_fen(), standard_state.to_fen());
+        assert_eq!(state.zobrist_key, standard_state.zobrist_key);
+    }
+
+    // KQkq refer to the outermost rooks. Other rooks are written with their file.
+    let cases = [
+        (
+            "nrbqkbrn/pppppppp/8/8/8/8/PPPPPPPP/NRBQKBRN w GBgb - 0 1",
+            "nrbqkbrn/pppppppp/8/8/8/8/PPPPPPPP/NRBQKBRN w KQkq - 0 1",
+        ),
+        (
+            "4k3/8/8/8/8/8/8/R3K1RR w G - 0 1",
+            "4k3/8/8/8/8/8/8/R3K1RR w G - 0 1",
+        ),
+        (
+            "4k3/8/8/8/8/8/8/RR2K2R w BH - 0 1",
+            "4k3/8/8/8/8/8/8/RR2K2R w KB - 0 1",
+        ),
+    ];
+    for (fen, expected_fen) in cases {
+        let state = State::new_from_fen(fen);
+        assert_eq!(state.to_fen(), expected_fen);
+        check_fen_round_trip(&mut state.clone(), 2);
+    }
+
+    let cases = [
+        // There is no rook on the h file.
+        ("4k3/8/8/8/8/8/8/R3K3 w H - 0 1", 'H', 23),
+        // There is no rook to the right of the king.
+        ("4k3/8/8/8/8/8/8/R3K3 w K - 0 1", 'K', 23),
+        // The king is not on the back rank.
+        ("8/4k3/8/8/8/8/8/R3K2R w KQq - 0 1", 'q', 26),
+    ];
+    for (fen, character, position) in cases {
+        assert_eq!(
+            State::try_from_fen(fen).unwrap_err(),
+            FenError::InvalidCastlingRight {
+                character,
+                position
+            },
+            "{}",
+            fen
+        );
+    }
+}
+
+#[test]
+fn test_chess960_notation() {
+    let state = State::new_from_fen("rk5r/8/8/8/8/8/8/RK5R w AHah - 0 1");
+    let castle = king_takes_rook("b1", "a1");
+    assert_eq!(to_san(&state, castle), "O-O-O");
+    assert_eq!(parse_san(&state, "O-O-O"), Ok(castle));
+    assert_eq!(to_uci(&state, castle, false), "b1a1");
+    assert_eq!(to_uci(&state, castle, true), "b1a1");
+    assert_eq!(parse_uci(&state, "b1a1"), Some(castle));
+
+    // Standard castles are written as the king capturing its rook only in Chess960 mode.
+    let state = State::new_from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1");
+    let castle = moove_from_uci_notation("e1g1");
+    assert_eq!(to_uci(&state, castle, false), "e1g1");
+    assert_eq!(to_uci(&state, castle, true), "e1h1");
+    assert_eq!(parse_uci(&state, "e1g1"), Some(castle));
+    assert_eq!(parse_uci(&state, "e1h1"), Some(castle));
+    // A rook moving two squares is no castle.
+    let rook_move = moove_from_uci_notation("a1c1");
+    assert_eq!(to_uci(&state, rook_move, true), "a1c1");
+    assert_eq!(parse_uci(&state, "e1e3"), None);
+}
+
+#[test]
+fn test_uci_chess960_option() {
+    let output = run_uci_session(
+        "uci\nsetoption name UCI_Chess960 value true\n\
+         position fen rk5r/8/8/8/8/8/8/RK5R w AHah - 0 1 moves b1a1\ngo perft 1\n\
+         position fen r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1\ngo perft 1\n\
+         setoption name UCI_Chess960 value false\ngo perft 1\nquit\n",
+    );
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert!(lines.contains(&"option name UCI_Chess960 type check default false"));
+    assert!(!output.contains("illegal move"));
+    let state = State::new_from_fen("rk5r/8/8/8/8/8/8/2KR3R b ah - 1 1");
+    assert!(lines.contains(&format!("Nodes searched: {}", perft(&state, 1)).as_str()));
+    // Without the option, castles are written the usual way again.
+    let castles: Vec<&&str> = lines
+        .iter()
+        .filter(|line| line.starts_with("e1"))
+        .filter(|line| ["e1a1", "e1c1", "e1g1", "e1h1"].contains(&&line[..4]))
+        .collect();
+    assert_eq!(castles, [&"e1a1: 1", &"e1h1: 1", &"e1c1: 1", &"e1g1: 1"]);
+}
+
+// --------------------------------------------- //
+// UCI
+// --------------------------------------------- //
+fn run_uci_session(commands: &str) -> String {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_Mouse"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    engine
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(commands.as_bytes())
+        .unwrap();
+
+    let output = engine.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_uci_handshake_and_perft() {
+    let output =
+        run_uci_session("uci\nisready\nposition startpos moves e2e4 e7e5\ngo perft 2\nquit\n");
+    let lines: Vec<&str> = output.lines().collect();
+
+    assert_eq!(lines[0], "id name Mouse");
+    assert!(lines.contains(&"uciok"));
+    assert!(lines.contains(&"readyok"));
+    assert!(lines.contains(&"g1f3: 29"));
+    // 1. e4 e5 has 29 moves for white, each with 29 or more replies.
+    let nodes_line = lines
+        .iter()
+        .find(|line| line.starts_with("Nodes searched"))
+        .unwrap();
+    let state =
+        State::new_from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2");
+    assert_eq!(*nodes_line, format!("Nodes searched: {}", perft(&state, 2)));
+}
+
+#[test]
+fn test_uci_stop_perft_and_ponder() {
+    // Perft runs on the search thread, so `stop` is read while it is busy.
+    let output = run_uci_session("position startpos\ngo perft 9\nstop\nisready\nquit\n");
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines, ["info string perft was stopped", "readyok"]);
+
+    // A ponder search doesn't run out of time before `ponderhit`, but it does stop on `stop`.
+    let output =
+        run_uci_session("position startpos\ngo ponder wtime 10 btime 10\nstop\nisready\nquit\n");
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines[lines.len() - 2].starts_with("bestmove "));
+    assert_eq!(lines[lines.len() - 1], "readyok");
+}
+
+#[test]
+fn test_uci_go_and_stop() {
+    let output = run_uci_session(
+        "position fen 7k/8/8/8/8/8/8/R6K w - - 0 1 moves a1a2 h8g8\ngo infinite\nisready\nstop\n\
+         position startpos moves e2e5\ngo depth 1\nquit\n",
+    );
+    // The info lines of the search depend on timing, so only the rest is compared.
+    let lines: Vec<&str> = output
+        .lines()
+        .filter(|line| !line.starts_with("info depth"))
+        .collect();
+
+    assert_eq!(lines[0], "readyok");
+    assert!(lines[1].starts_with("bestmove "));
+    assert_eq!(lines[2], "info string illegal move e2e5");
+    assert!(lines[3].starts_with("bestmove "));
+}
+
+#[test]
+fn test_uci_go_prints_mate_score() {
+    use std::io::{BufRead, BufReader, Write};
+    use std::process::{Command, Stdio};
+
+    let mut engine = Command::new(env!("CARGO_BIN_EXE_Mouse"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = engine.stdin.take().unwrap();
+    stdin
+        .write_all(b"position fen 6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1\ngo depth 3\n")
+        .unwrap();
+    stdin.flush().unwrap();
+
+    // `quit` would stop the search right away, so it is only sent once the search is done.
+    let lines: Vec<String> = BufReader::new(engine.stdout.take().unwrap())
+        .lines()
+        .map(|line| line.unwrap())
+        .take_while(|line| !line.starts_with("bestmove"))
+        .collect();
+    stdin.write_all(b"quit\n").unwrap();
+    assert!(engine.wait().unwrap().success());
+
+    let info_line = lines.last().unwrap();
+    assert!(info_line.starts_with("info depth 1 score mate 1 "));
+    assert!(info_line.ends_with(" pv a1a8"));
+}
+
+fn search_to_depth(fen: &str, depth: u8) -> mouse::SearchResult {
+    let limits = SearchLimits {
+        depth: Some(depth),
+        ..SearchLimits::default()
+    };
+    search(
+        &State::new_from_fen(fen),
+        &limits,
+        &TranspositionTable::new(1),
+        &AtomicBool::new(false),
+        |_| {},
+    )
+}
+
+#[test]
+fn test_search_finds_mates() {
+    // Back rank mate in one.
+    let result = search_to_depth("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 4);
+    assert_eq!(result.best_move, Some(moove_from_uci_notation("a1a8")));
+    assert_eq!(result.score, MATE_SCORE - 1);
+    assert_eq!(get_mate_distance(result.score), Some(1));
+    // A mate for us within the searched depth can't get any shorter, so the search stops right away.
+    assert_eq!(result.depth, 1);
+
+    // Mate in two with a queen sacrifice: 1. Qd8+ Bxd8 2. Re8#
+    let result = search_to_depth(
+        "r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 1",
+        4,
+    );
+    assert_eq!(get_mate_distance(result.score), Some(2));
+    assert_eq!(result.best_move, Some(moove_from_uci_notation("d5d8")));
+    assert_eq!(result.pv.len(), 3);
+    assert_eq!(result.pv[0], result.best_move.unwrap());
+
+    // The side to move is getting mated.
+    let result = search_to_depth("7k/8/8/8/8/8/5PPP/r5K1 w - - 0 1", 4);
+    assert_eq!(get_mate_distance(result.score), Some(0));
+
+    // Being mated doesn't end the search early. 1... Kg8 2. Ra8# is forced.
+    let result = search_to_depth("7k/8/6K1/8/8/8/8/R7 b - - 0 1", 4);
+    assert_eq!(get_mate_distance(result.score), Some(-1));
+    assert_eq!(result.depth, 4);
+}
+
+#[test]
+fn test_search_without_legal_moves() {
+    // Stalemate.
+    let result = search_to_depth("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1", 3);
+    assert_eq!(result.best_move, None);
+    assert_eq!(result.score, 0);
+}
+
+#[test]
+fn test_search_wins_material() {
+    // The rook on d5 hangs, the knight on b5 is defended.
+    let result = search_to_depth("4k3/8/p7/1n1r4/8/8/3Q4/4K3 w - - 0 1", 3);
+    assert_eq!(result.best_move, Some(moove_from_uci_notation("d2d5")));
+    assert!(result.score > 0);
+}
+
+#[test]
+fn test_search_respects_limits() {
+    let state = State::new_from_fen("startpos");
+    let tt = TranspositionTable::new(1);
+    let stop = AtomicBool::new(false);
+
+    let mut depths = Vec::new();
+    let limits = SearchLimits {
+        depth: Some(3),
+        ..SearchLimits::default()
+    };
+    let result = search(&state, &limits, &tt, &stop, |result| {
+        depths.push(result.depth)
+    });
+    assert_eq!(depths, vec![1, 2, 3]);
+    assert_eq!(result.depth, 3);
+    assert!(get_legal_moves(&state).contains(result.best_move.unwrap()));
+
+    let limits = SearchLimits {
+        nodes: Some(5_000),
+        ..SearchLimits::default()
+    };
+    let result = search(&state, &limits, &tt, &stop, |_| {});
+    // The node limit is only checked every few nodes.
+    assert!(result.nodes < 5_000 + 1024);
+    assert!(result.best_move.is_some());
+
+    let limits = SearchLimits {
+        move_time: Some(Duration::from_millis(50)),
+        ..SearchLimits::default()
+    };
+    let result = search(&state, &limits, &tt, &stop, |_| {});
+    assert!(result.elapsed < Duration::from_millis(1_000));
+    assert!(result.best_move.is_some());
+
+    // A search that is stopped before it starts still returns a legal move.
+    let result = search(
+        &state,
+        &SearchLimits::default(),
+        &tt,
+        &AtomicBool::new(true),
+        |_| {},
+    );
+    assert_eq!(result.depth, 0);
+    assert!(get_legal_moves(&state).contains(result.best_move.unwrap()));
+}
+
+#[test]
+fn test_search_ponder_starts_the_clock_on_ponderhit() {
+    let state = State::new_from_fen("startpos");
+    let tt = TranspositionTable::new(1);
+    let stop = AtomicBool::new(false);
+    let ponder = Arc::new(AtomicBool::new(true));
+
+    // The move time only counts once the flag is cleared, so the search has to outlast the ponder time.
+    let limits = SearchLimits {
+        move_time: Some(Duration::from_millis(20)),
+        ponder: Some(ponder.clone()),
+        ..SearchLimits::default()
+    };
+    let ponderhit = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(200));
+        ponder.store(false, AtomicOrdering::Relaxed);
+    });
+    let result = search(&state, &limits, &tt, &stop, |_| {});
+    ponderhit.join().unwrap();
+
+    assert!(result.elapsed >= Duration::from_millis(200));
+    assert!(result.elapsed < Duration::from_millis(1_000));
+    assert!(result.best_move.is_some());
+}
+
+/// Flips the board vertically and swaps the colors of all pieces, the side to move and the castling rights.
+fn mirror_fen(fen: &str) -> String {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    let swap_case = |text: &str| -> String {
+        text.chars()
+            .map(|character| match character.is_ascii_uppercase() {
+                true => character.to_ascii_lowercase(),
+                false => character.to_ascii_uppercase(),
+            })
+            .collect()
+    };
+
+    let ranks: Vec<String> = fields[0].split('/').rev().map(swap_case).collect();
+    let active_color = if fields[1] == "w" { "b" } else { "w" };
+    let castling_rights = match fields[2] {
+        "-" => "-".to_string(),
+        rights => {
+            let swapped = swap_case(rights);
+            let mut white: String = swapped.chars().filter(char::is_ascii_uppercase).collect();
+            white.extend(swapped.chars().filter(char::is_ascii_lowercase));
+            white
+        }
+    };
+    let en_passant = match fields[3] {
+        "-" => "-".to_string(),
+        square => {
+            let rank = if &square[1..] == "3" { "6" } else { "3" };
+            format!("{}{}", &square[..1], rank)
+        }
+    };
+
+    format!(
+        "{} {} {} {} {}",
+        ranks.join("/"),
+        active_color,
+        castling_rights,
+        en_passant,
+        fields[4..].join(" ")
+    )
+}
+
+#[test]
+fn test_evaluation_is_symmetric() {
+    let fens = [
+        "startpos",
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        "r2q1rk1/pP1p2pp/Q4n2/bbp1p3/Np6/1B3NBn/pPPP1PPP/R3K2R b KQ - 0 1",
+        "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+        "4k3/8/8/8/8/8/8/4K2R b K - 0 1",
+        "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 2",
+    ];
+
+    for fen in fens {
+        let state = State::new_from_fen(fen);
+        let mirrored_fen = mirror_fen(&state.to_fen());
+        let mirrored_state = State::new_from_fen(&mirrored_fen);
+
+        // Both sides to move are in the exact same situation, so the scores have to match.
+        assert_eq!(evaluate(&state), evaluate(&mirrored_state), "{}", fen);
+        assert_eq!(mirror_fen(&mirrored_fen), state.to_fen());
+    }
+}
+
+#[test]
+fn test_evaluation_favors_material() {
+    assert_eq!(
+        evaluate(&State::new_from_fen("4k3/8/8/8/8/8/8/4K3 w - - 0 1")),
+        0
+    );
+
+    // The side to move is a queen up.
+    let state = State::new_from_fen("4k3/8/8/8/8/8/8/3QK3 w - - 0 1");
+    assert!(evaluate(&state) > 800);
+    // The same position with black to move.
+    let state = State::new_from_fen("4k3/8/8/8/8/8/8/3QK3 b - - 0 1");
+    assert!(evaluate(&state) < -800);
+}
+
+#[test]
+fn test_transposition_table_store_and_probe() {
+    let tt = TranspositionTable::new(1);
+    let e2e4 = moove_from_uci_notation("e2e4");
+    assert_eq!(tt.probe(42), None);
+
+    tt.store(42, 5, Bound::Exact, -1234, Some(e2e4));
+    let entry = tt.probe(42).unwrap();
+    assert_eq!(entry.best_move, Some(e2e4));
+    assert_eq!(entry.score, -1234);
+    assert_eq!(entry.depth, 5);
+    assert_eq!(entry.bound, Bound::Exact);
+
+    // A fail low does not know a best move, the old one is kept.
+    tt.store(42, 6, Bound::Upper, 31_000, None);
+    let entry = tt.probe(42).unwrap();
+    assert_eq!(entry.best_move, Some(e2e4));
+    assert_eq!(
+        (entry.score, entry.depth, entry.bound),
+        (31_000, 6, Bound::Upper)
+    );
+
+    // Promotions survive the round trip through 16 bits.
+    let promotion = moove_from_uci_notation("a7a8n");
+    tt.store(43, 1, Bound::Lower, 0, Some(promotion));
+    assert_eq!(tt.probe(43).unwrap().best_move, Some(promotion));
+
+    tt.clear();
+    assert_eq!(tt.probe(42), None);
+    assert_eq!(tt.probe(43), None);
+}
+
+#[test]
+fn test_transposition_table_replacement() {
+    let mut tt = TranspositionTable::new(1);
+    let bucket_count = tt.size_in_bytes() as u64 / 32;
+    assert!(bucket_count.is_power_of_two());
+    assert!(tt.size_in_bytes() <= 1024 * 1024);
+
+    // All of these keys map to the same bucket.
+    let deep_key = 7;
+    let shallow_key = 7 + bucket_count;
+    let other_shallow_key = 7 + 2 * bucket_count;
+    tt.store(deep_key, 10, Bound::Exact, 1, None);
+    tt.store(shallow_key, 2, Bound::Exact, 2, None);
+    tt.store(other_shallow_key, 3, Bound::Exact, 3, None);
+
+    // The deep entry is kept, the shallow ones replace each other.
+    assert_eq!(tt.probe(deep_key).unwrap().score, 1);
+    assert_eq!(tt.probe(shallow_key), None);
+    assert_eq!(tt.probe(other_shallow_key).unwrap().score, 3);
+
+    // Entries of older searches are replaced, no matter how deep they are.
+    tt.new_search();
+    tt.store(shallow_key, 1, Bound::Exact, 2, None);
+    assert_eq!(tt.probe(deep_key), None);
+    assert_eq!(tt.probe(shallow_key).unwrap().score, 2);
+
+    tt.resize(2);
+    assert_eq!(tt.size_in_bytes(), 2 * 1024 * 1024);
+    assert_eq!(tt.probe(shallow_key), None);
+}
+
+#[test]
+fn test_transposition_table_hashfull() {
+    let tt = TranspositionTable::new(1);
+    assert_eq!(tt.hashfull(), 0);
+
+    for key in 0..100 {
+        tt.store(key, 1, Bound::Exact, 0, None);
+    }
+    // The first 500 buckets are sampled, 100 of them have one of their two slots filled.
+    assert_eq!(tt.hashfull(), 100);
+
+    // Entries of older searches don't count.
+    tt.new_search();
+    assert_eq!(tt.hashfull(), 0);
+}
+
+#[test]
+fn test_transposition_table_is_shared_between_threads() {
+    let tt = TranspositionTable::new(1);
+
+    std::thread::scope(|scope| {
+        for thread_index in 0..4u64 {
+            let tt = &tt;
+            scope.spawn(move || {
+                for key in (thread_index * 1000)..((thread_index + 1) * 1000) {
+                    tt.store(key, 1, Bound::Lower, key as i16, None);
+                }
+            });
+        }
+    });
+
+    for key in 0..4000 {
+        assert_eq!(tt.probe(key).unwrap().score, key as i16);
+    }
+}
+
+#[test]
+fn test_search_with_shared_transposition_table() {
+    let state = State::new_from_fen("r1b2k1r/ppp1bppp/8/1B1Q4/5q2/2P5/PPP2PPP/R3R1K1 w - - 1 1");
+    let tt = TranspositionTable::new(1);
+    let limits = SearchLimits {
+        depth: Some(4),
+        ..SearchLimits::default()
+    };
+    let stop = AtomicBool::new(false);
+
+    let first = search(&state, &limits, &tt, &stop, |_| {});
+    assert!(tt.hashfull() > 0);
+    assert_eq!(
+        tt.probe(state.zobrist_key).unwrap().best_move,
+        first.best_move
+    );
+
+    // The second search can reuse the results of the first one.
+    let second = search(&state, &limits, &tt, &stop, |_| {});
+    assert_eq!(second.best_move, first.best_move);
+    assert_eq!(second.score, first.score);
+    assert!(second.nodes <= first.nodes);
+}
+
+fn clock_limits(time_left_ms: u64, increment_ms: u64, moves_to_go: Option<u32>) -> SearchLimits {
+    SearchLimits {
+        clock: Some(Clock {
+            time_left: Duration::from_millis(time_left_ms),
+            increment: Duration::from_millis(increment_ms),
+            moves_to_go,
+        }),
+        move_overhead: Duration::from_millis(100),
+        ..SearchLimits::default()
+    }
+}
+
+#[test]
+fn test_time_manager_limits() {
+    // No time limit at all.
+    let time_manager = TimeManager::new(&SearchLimits::default());
+    assert_eq!(time_manager.get_soft_limit(), None);
+    assert!(!time_manager.is_out_of_time(Duration::from_secs(3600)));
+
+    // A fixed move time is used completely, minus the overhead.
+    let limits = SearchLimits {
+        move_time: Some(Duration::from_millis(1000)),
+        move_overhead: Duration::from_millis(100),
+        ..SearchLimits::default()
+    };
+    let time_manager = TimeManager::new(&limits);
+    assert_eq!(time_manager.get_soft_limit(), None);
+    assert_eq!(
+        time_manager.get_hard_limit(),
+        Some(Duration::from_millis(900))
+    );
+    assert!(time_manager.should_start_iteration(Duration::from_millis(899)));
+    assert!(!time_manager.is_out_of_time(Duration::from_millis(899)));
+    assert!(time_manager.is_out_of_time(Duration::from_millis(900)));
+
+    // 60 seconds for the rest of the game: 59.9s / 30 moves + 75% of the increment.
+    let time_manager = TimeManager::new(&clock_limits(60_000, 1_000, None));
+    assert_eq!(time_manager.get_soft_limit().unwrap().as_millis(), 2746);
+    assert_eq!(time_manager.get_hard_limit().unwrap().as_millis(), 10_986);
+
+    // With fewer moves to go, more time is used, but never more than half of what is left.
+    let time_manager = TimeManager::new(&clock_limits(10_100, 0, Some(2)));
+    assert_eq!(
+        time_manager.get_soft_limit(),
+        Some(Duration::from_millis(5000))
+    );
+    assert_eq!(
+        time_manager.get_hard_limit(),
+        Some(Duration::from_millis(5000))
+    );
+
+    // The overhead is bigger than the remaining time, so the search has to stop right away.
+    let time_manager = TimeManager::new(&clock_limits(50, 0, None));
+    assert!(time_manager.is_out_of_time(Duration::ZERO));
+}
+
+#[test]
+fn test_time_manager_adapts_to_the_search() {
+    let e2e4 = Some(moove_from_uci_notation("e2e4"));
+    let d2d4 = Some(moove_from_uci_notation("d2d4"));
+
+    // A stable best move and score needs less and less time.
+    let mut time_manager = TimeManager::new(&clock_limits(30_100, 0, None));
+    let base_soft_limit = Duration::from_millis(1000);
+    assert_eq!(time_manager.get_soft_limit(), Some(base_soft_limit));
+    let mut soft_limits = Vec::new();
+    for _ in 0..6 {
+        time_manager.on_iteration_finished(e2e4, 30);
+        soft_limits.push(time_manager.get_soft_limit().unwrap().as_millis());
+    }
+    assert_eq!(soft_limits, vec![2000, 1200, 1000, 850, 750, 750]);
+    assert!(time_manager.should_start_iteration(Duration::from_millis(749)));
+    assert!(!time_manager.should_start_iteration(Duration::from_millis(750)));
+
+    // A new best move resets the stability.
+    time_manager.on_iteration_finished(d2d4, 30);
+    assert_eq!(time_manager.get_soft_limit(), Some(2 * base_soft_limit));
+
+    // Small score changes are ignored, big drops give the search more time.
+    time_manager.on_iteration_finished(d2d4, 15);
+    assert_eq!(
+        time_manager.get_soft_limit(),
+        Some(Duration::from_millis(1200))
+    );
+    time_manager.on_iteration_finished(d2d4, -45);
+    assert_eq!(
+        time_manager.get_soft_limit(),
+        Some(Duration::from_millis(1500))
+    );
+    time_manager.on_iteration_finished(d2d4, -500);
+    assert_eq!(
+        time_manager.get_soft_limit(),
+        Some(Duration::from_millis(1700))
+    );
+
+    // Scaling never goes past the hard limit.
+    time_manager.on_iteration_finished(e2e4, -1000);
+    assert_eq!(time_manager.get_soft_limit(), time_manager.get_hard_limit());
+}
+
+#[test]
+fn test_perft_parallel_matches_perft() {
+    for perft_fixture in FAST_PERFT.iter() {
+        let state = State::new_from_fen(perft_fixture.perft_setup.fen);
+        for threads in [1, 3, 8] {
+            assert_eq!(
+                perft_parallel(&state, perft_fixture.depth, threads),
+                perft_fixture.expected_nodes,
+                "{} with {} threads",
+                perft_fixture.perft_setup.name,
+                threads
+            );
+        }
+    }
+
+    let state = State::new_from_fen("startpos");
+    assert_eq!(perft_parallel(&state, 0, 4), 1);
+    assert_eq!(perft_parallel(&state, 1, 4), 20);
+}
+
+#[test]
+fn test_perft_divide_parallel_matches_perft_divide() {
+    let fens = [
+        "startpos",
+        // Only a few root moves, so the work is split at ply 2.
+        "4k3/8/8/8/8/8/3q4/4K3 w - - 0 1",
+        // A root move that mates, so it has no replies.
+        "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1",
+    ];
+
+    for fen in fens {
+        let state = State::new_from_fen(fen);
+        for depth in 1..=3 {
+            let expected = perft_divide(&state, depth);
+            assert_eq!(perft_divide_parallel(&state, depth, 4), expected, "{}", fen);
+            assert_eq!(
+                perft_divide_parallel(&state, depth, 64),
+                expected,
+                "{}",
+                fen
+            );
+        }
+    }
+}
+
+#[test]
+fn test_perft_divide_stoppable() {
+    let state = State::new_from_fen("startpos");
+    assert_eq!(
+        perft_divide_stoppable(&state, 3, 4, &AtomicBool::new(false)),
+        Some(perft_divide(&state, 3))
+    );
+    assert_eq!(
+        perft_divide_stoppable(&state, 3, 4, &AtomicBool::new(true)),
+        None
+    );
+}
+
+/// Perft of a reference with a fake bug: It generates the illegal king move e1e3 after 1. e4 e5.
+fn buggy_reference_perft(state: &mut State, depth: u8, buggy_key: u64) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut nodes = u64::from(state.zobrist_key == buggy_key && depth == 1);
+    for moove in get_legal_moves(state) {
+        let undo = state.make_move(moove);
+        nodes += buggy_reference_perft(state, depth - 1, buggy_key);
+        state.unmake_move(moove, undo);
+    }
+    nodes
+}
+
+fn buggy_reference_divide(state: &State, depth: u8, buggy_key: u64) -> Divide {
+    let mut state = state.clone();
+    let mut divide = Vec::new();
+    for moove in get_legal_moves(&state) {
+        let undo = state.make_move(moove);
+        let nodes = buggy_reference_perft(&mut state, depth - 1, buggy_key);
+        state.unmake_move(moove, undo);
+        divide.push((moove.to_string(), nodes));
+    }
+    if state.zobrist_key == buggy_key && depth == 1 {
+        divide.push(("e1e3".to_string(), 1));
+    }
+    divide
+}
+
+#[test]
+fn test_bisect_finds_the_broken_position() {
+    let state = State::new_from_fen("startpos");
+    let buggy_key =
+        State::new_from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2")
+            .zobrist_key;
+
+    let bisection = bisect(&state, 3, 4, |state, depth| {
+        Ok::<_, ()>(Some(buggy_reference_divide(state, depth, buggy_key)))
+    })
+    .unwrap()
+    .unwrap();
+
+    assert!(bisection.is_minimal());
+    assert_eq!(
+        bisection.moves,
+        vec![
+            moove_from_uci_notation("e2e4"),
+            moove_from_uci_notation("e7e5")
+        ]
+    );
+    assert_eq!(bisection.depth, 1);
+    assert_eq!(
+        bisection.fen,
+        "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq e6 0 2"
+    );
+    assert_eq!(bisection.missing_moves, vec![("e1e3".to_string(), 1)]);
+    assert!(bisection.extra_moves.is_empty());
+
+    // Without the bug, there is nothing to find.
+    let bisection = bisect(&state, 3, 4, |state, depth| {
+        Ok::<_, ()>(Some(buggy_reference_divide(state, depth, 0)))
+    })
+    .unwrap()
+    .unwrap();
+    assert!(bisection.is_match());
+
+    // A reference that can't look deeper stops the bisection at the root.
+    let bisection = bisect(&state, 3, 4, |next_state, depth| {
+        let is_root = next_state.zobrist_key == state.zobrist_key;
+        Ok::<_, ()>(is_root.then(|| buggy_reference_divide(next_state, depth, buggy_key)))
+    })
+    .unwrap()
+    .unwrap();
+    assert!(bisection.moves.is_empty());
+    assert!(!bisection.is_minimal());
+    let e2e4_nodes = perft(&state.make_move_copy(moove_from_uci_notation("e2e4")), 2);
+    assert_eq!(
+        bisection.count_mismatches,
+        vec![("e2e4".to_string(), e2e4_nodes, e2e4_nodes + 1)]
+    );
+}
+
+#[test]
+fn test_bisect_writes_chess960_castles_like_the_reference() {
+    // Only black's castling is Chess960, but uci engines then write white's castles as e1h1 and e1a1 as well.
+    let state = State::new_from_fen("1r2k1r1/8/8/8/8/8/8/R3K2R w KQgb - 0 1");
+    let bisection = bisect(&state, 2, 4, |state, depth| {
+        let divide = perft_divide(state, depth)
+            .into_iter()
+            .map(|(moove, nodes)| (to_uci(state, moove, true), nodes))
+            .collect();
+        Ok::<_, ()>(Some(divide))
+    })
+    .unwrap()
+    .unwrap();
+    assert!(bisection.is_match());
+}
+
+fn run_mouse(arguments: &[&str]) -> (i32, String, String) {
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_Mouse"))
+        .args(arguments)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+        String::from_utf8(output.stderr).unwrap(),
+    )
+}
+
+#[test]
+fn test_bisect_command() {
+    // Mouse as its own reference engine agrees with itself.
+    let (code, stdout, _) = run_mouse(&[
+        "bisect",
+        "3",
+        "startpos",
+        "--engine",
+        env!("CARGO_BIN_EXE_Mouse"),
+    ]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout.trim(), "No mismatch found at depth 3.");
+
+    // A divide file that is missing a move and has a wrong count.
+    let path = std::env::temp_dir().join(format!("mouse_bisect_{}.txt", std::process::id()));
+    let mut divide = String::new();
+    for (moove, nodes) in perft_divide(&State::new_from_fen("startpos"), 2) {
+        match moove.to_string().as_str() {
+            "a2a3" => {}
+            "b2b3" => divide.push_str("b2b3: 21\n"),
+            uci_move => divide.push_str(&format!("{} {}\n", uci_move, nodes)),
+        }
+    }
+    divide.push_str("\nNodes searched: 400\n");
+    std::fs::write(&path, divide).unwrap();
+
+    let (code, stdout, _) = run_mouse(&[
+        "bisect",
+        "2",
+        "startpos",
+        "--divide-file",
+        path.to_str().unwrap(),
+    ]);
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(code, 1);
+    assert!(stdout.contains("Extra move: a2a3 (ours: 20)"));
+    assert!(stdout.contains("Different count: b2b3 (ours: 20, reference: 21)"));
+    assert!(!stdout.contains("Continue from"));
+
+    let (code, _, stderr) = run_mouse(&["bisect", "x", "startpos", "--engine", "stockfish"]);
+    assert_eq!(code, 2);
+    assert!(stderr.starts_with("invalid depth x"));
+}
+
+#[test]
+fn test_perft_command() {
+    let (code, stdout, _) = run_mouse(&["perft", "3"]);
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("Nodes searched: 8902\n"));
+
+    let (code, stdout, _) = run_mouse(&[
+        "perft",
+        "2",
+        "--moves",
+        "e2e4",
+        "e5",
+        "--divide",
+        "--threads",
+        "2",
+    ]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("e1e2: 29\n"));
+    assert!(stdout.contains("Nodes searched: 835\n"));
+
+    let (code, stdout, _) = run_mouse(&["perft", "1", "--fen", "8/8/8/8/8/8/8/K6k w - - 0 1"]);
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("Nodes searched: 3\n"));
+}
+
+#[test]
+fn test_perftree_command() {
+    let (code, stdout, _) = run_mouse(&["perftree", "1", "startpos", "e2e4 e7e5"]);
+    assert_eq!(code, 0);
+    assert!(stdout.contains("e1e2 1\n"));
+    assert!(stdout.ends_with("\n29\n"));
+}
+
+#[test]
+fn test_fen_and_moves_commands() {
+    let (code, stdout, _) = run_mouse(&[
+        "fen",
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR",
+        "b",
+        "KQkq",
+        "e3",
+    ]);
+    assert_eq!(code, 0);
+    assert_eq!(
+        stdout,
+        "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1\n"
+    );
+
+    let (code, stdout, stderr) = run_mouse(&["fen", "8/8/8/8/8/8/8/8 w - - 0 1"]);
+    assert_eq!(code, 1);
+    assert!(stdout.is_empty());
+    assert!(stderr.starts_with("invalid fen"));
+
+    let (code, stdout, _) =
+        run_mouse(&["moves", "--san", "--fen", "4k3/8/8/8/8/8/8/4K2R w K - 0 1"]);
+    assert_eq!(code, 0);
+    let moves: HashSet<&str> = stdout.lines().collect();
+    assert_eq!(moves.len(), 15);
+    assert!(moves.contains("O-O+") || moves.contains("O-O"));
+    assert!(moves.contains("Rh8+"));
+
+    let (code, stdout, _) = run_mouse(&["moves", "--moves", "e4 e5 Nf3"]);
+    assert_eq!(code, 0);
+    assert_eq!(stdout.lines().count(), 29);
+    assert!(stdout.contains("b8c6\n"));
+}
+
+#[test]
+fn test_bench_command() {
+    let (code, stdout, _) = run_mouse(&["bench", "2"]);
+    assert_eq!(code, 0);
+    let last_line = stdout.lines().last().unwrap();
+    let words: Vec<&str> = last_line.split_whitespace().collect();
+    assert_eq!(words.len(), 4);
+    assert!(words[0].parse::<u64>().unwrap() > 0);
+    assert_eq!(words[1], "nodes");
+    assert_eq!(words[3], "nps");
+}
+
+#[test]
+fn test_command_line_errors() {
+    for arguments in [
+        &["perft"][..],
+        &["perft", "x"],
+        &["perft", "2", "--fen"],
+        &["perft", "2", "--threads", "0"],
+        &["perft", "2", "--san"],
+        &["perft", "2", "--moves", "e2e5"],
+        &["perft", "2", "--fen", "not a fen"],
+        &["perftree", "2"],
+        &["bench", "2", "3"],
+        &["fen"],
+        &["moves", "--divide"],
+        &["unknown"],
+    ] {
+        let (code, stdout, stderr) = run_mouse(arguments);
+        assert_eq!(code, 2, "{:?}", arguments);
+        assert!(stdout.is_empty(), "{:?}", arguments);
+        assert!(stderr.contains("usage: Mouse"), "{:?}", arguments);
+    }
+
+    let (code, stdout, _) = run_mouse(&["help"]);
+    assert_eq!(code, 0);
+    assert!(stdout.starts_with("usage: Mouse"));
+}